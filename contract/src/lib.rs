@@ -3,6 +3,9 @@ use serde::Serialize;
 use near_sdk::collections::Vector;
 use near_sdk::{env, near_bindgen, Promise, AccountId};
 
+mod migration;
+pub use migration::{DaoCreationContractV0, VersionedDaoCreationContract, STATE_VERSION};
+
 // VOTE
 // Vote structor 
 #[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
@@ -206,6 +209,7 @@ impl Dao {
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct DaoCreationContract {
     records: Vector<Dao>,
+    state_version: u8,
 }
 
 // Define the default, which automatically initializes the contract
@@ -233,6 +237,7 @@ impl DaoCreationContract {
         assert!(env::state_read::<Self>().is_none(), "Already initialized");
         Self {
             records: Vector::new(b"a"),
+            state_version: STATE_VERSION,
         }
     }
 
@@ -410,4 +415,57 @@ impl DaoCreationContract {
         }
     }
 
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    // context where the factory calls itself
+    fn owner_context() -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("lightency_dao.testnet".parse().unwrap())
+            .predecessor_account_id("lightency_dao.testnet".parse().unwrap())
+            .signer_account_id("lightency_dao.testnet".parse().unwrap());
+        builder
+    }
+
+    #[test]
+    fn test_migrate_from_v0(){
+        testing_env!(owner_context().build());
+        let mut records = Vector::new(b"a");
+        let mut dao = Dao::new();
+        dao.dao_name = "solar".to_string();
+        dao.founder = "issameths.testnet".to_string();
+        records.push(&dao);
+        env::state_write(&DaoCreationContractV0 { records });
+
+        let contract = DaoCreationContract::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert_eq!(contract.get_all_daos().len(), 1);
+        assert_eq!(contract.get_dao("solar".to_string()).founder, "issameths.testnet");
+    }
+
+    #[test]
+    fn test_migrate_current(){
+        testing_env!(owner_context().build());
+        let mut contract = DaoCreationContract::new();
+        contract.add_dao("solar".to_string(), "energy".to_string(), 50, 1, 0, 0);
+        env::state_write(&contract);
+
+        let contract = DaoCreationContract::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert_eq!(contract.get_dao("solar".to_string()).dao_purpose, "energy");
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by owner")]
+    fn test_migrate_not_owner(){
+        testing_env!(owner_context().predecessor_account_id("issameths.testnet".parse().unwrap()).build());
+        DaoCreationContract::migrate();
+    }
+}
@@ -0,0 +1,61 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::Vector;
+use near_sdk::{env, near_bindgen};
+
+use crate::{assert_self, Dao, DaoCreationContract, DaoCreationContractExt};
+
+// Version of the layout written by this code, bumped with every change of the layout
+pub const STATE_VERSION: u8 = 1;
+
+// Layout deployed before the state was versioned
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct DaoCreationContractV0 {
+    pub records: Vector<Dao>,
+}
+
+// Every layout the state has had
+pub enum VersionedDaoCreationContract {
+    V0(DaoCreationContractV0),
+    V1(DaoCreationContract),
+}
+
+impl VersionedDaoCreationContract {
+    // Function to pick the layout from the version at the end of the state
+    // The unversioned layout ends with the prefix of its records instead
+    pub fn read() -> Self {
+        let bytes = env::storage_read(b"STATE").expect("The contract isn't initialized");
+        match bytes.last().copied() {
+            Some(STATE_VERSION) => VersionedDaoCreationContract::V1(
+                DaoCreationContract::try_from_slice(&bytes).expect("The state doesn't match its version"),
+            ),
+            _ => VersionedDaoCreationContract::V0(
+                DaoCreationContractV0::try_from_slice(&bytes).expect("The state doesn't match its version"),
+            ),
+        }
+    }
+
+    // Function to convert any layout to the current one
+    pub fn into_current(self) -> DaoCreationContract {
+        match self {
+            VersionedDaoCreationContract::V0(state) => DaoCreationContract {
+                records: state.records,
+                state_version: STATE_VERSION,
+            },
+            VersionedDaoCreationContract::V1(state) => state,
+        }
+    }
+}
+
+#[near_bindgen]
+impl DaoCreationContract {
+    // Function to upgrade the stored state after deploying a new version of the code
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        assert_self();
+        VersionedDaoCreationContract::read().into_current()
+    }
+
+    pub fn get_state_version(&self) -> u8 {
+        self.state_version
+    }
+}
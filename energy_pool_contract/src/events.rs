@@ -0,0 +1,144 @@
+// Events of the energy pool in the NEP-297 format (`EVENT_JSON:` logs).
+// Amounts are in yoctoNEAR, or in the smallest unit of the token when one is set.
+
+pub use lightency_events::Event;
+use near_sdk::json_types::{U128, U64};
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+pub struct ProposalCreateData<'a> {
+    pub title: &'a str,
+    pub creator: &'a str,
+    pub amount: U128,
+    pub benificiary: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<&'a str>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct VoteData<'a> {
+    pub title: &'a str,
+    pub voter: &'a str,
+    pub vote: u8,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PayoutData<'a> {
+    pub title: &'a str,
+    pub benificiary: &'a str,
+    pub amount: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<&'a str>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DepositData<'a> {
+    pub sender_id: &'a str,
+    pub token: &'a str,
+    pub amount: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<&'a str>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct StreamStartData<'a> {
+    pub stream_id: u64,
+    pub title: &'a str,
+    pub benificiary: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<&'a str>,
+    pub amount: U128,
+    pub rate: U128,
+    pub start: U64,
+    pub end: U64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct StreamWithdrawData<'a> {
+    pub stream_id: u64,
+    pub benificiary: &'a str,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+pub struct StreamCancelData<'a> {
+    pub stream_id: u64,
+    pub title: &'a str,
+    pub refunded: U128,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MilestoneSubmitData<'a> {
+    pub title: &'a str,
+    pub index: u32,
+    pub evidence: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MilestoneVoteData<'a> {
+    pub title: &'a str,
+    pub index: u32,
+    pub voter: &'a str,
+    pub vote: u8,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MilestoneReleaseData<'a> {
+    pub title: &'a str,
+    pub index: u32,
+    pub benificiary: &'a str,
+    pub amount: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<&'a str>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MilestoneExpireData<'a> {
+    pub title: &'a str,
+    pub index: u32,
+    pub refunded: U128,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MemberData<'a> {
+    pub account_id: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum PoolEvent<'a> {
+    ProposalCreate(&'a [ProposalCreateData<'a>]),
+    Vote(&'a [VoteData<'a>]),
+    Payout(&'a [PayoutData<'a>]),
+    Deposit(&'a [DepositData<'a>]),
+    StreamStart(&'a [StreamStartData<'a>]),
+    StreamWithdraw(&'a [StreamWithdrawData<'a>]),
+    StreamCancel(&'a [StreamCancelData<'a>]),
+    MilestoneSubmit(&'a [MilestoneSubmitData<'a>]),
+    MilestoneVote(&'a [MilestoneVoteData<'a>]),
+    MilestoneRelease(&'a [MilestoneReleaseData<'a>]),
+    MilestoneExpire(&'a [MilestoneExpireData<'a>]),
+    MemberJoin(&'a [MemberData<'a>]),
+    MemberLeave(&'a [MemberData<'a>]),
+}
+
+impl Event for PoolEvent<'_> {
+    // Schema version of each event
+    fn version(&self) -> &'static str {
+        match self {
+            PoolEvent::ProposalCreate(_) => "1.0.0",
+            PoolEvent::Vote(_) => "1.0.0",
+            PoolEvent::Payout(_) => "1.0.0",
+            PoolEvent::Deposit(_) => "1.0.0",
+            PoolEvent::StreamStart(_) => "1.0.0",
+            PoolEvent::StreamWithdraw(_) => "1.0.0",
+            PoolEvent::StreamCancel(_) => "1.0.0",
+            PoolEvent::MilestoneSubmit(_) => "1.0.0",
+            PoolEvent::MilestoneVote(_) => "1.0.0",
+            PoolEvent::MilestoneRelease(_) => "1.0.0",
+            PoolEvent::MilestoneExpire(_) => "1.0.0",
+            PoolEvent::MemberJoin(_) => "1.0.0",
+            PoolEvent::MemberLeave(_) => "1.0.0",
+        }
+    }
+}
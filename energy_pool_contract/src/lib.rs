@@ -1,6 +1,30 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use serde::{Serialize , Deserialize};
-use near_sdk::{env, near_bindgen, Promise, AccountId};
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::{env, near_bindgen, ext_contract, Gas, Promise, PromiseError, PromiseOrValue, AccountId};
+use near_sdk::json_types::U128;
+
+mod events;
+mod membership;
+mod migration;
+mod milestones;
+mod streams;
+mod tokens;
+use events::{Event, PayoutData, PoolEvent, ProposalCreateData, VoteData};
+pub use membership::MemberChange;
+pub use migration::{EnergiePoolContractV0, ProposalsV0, VersionedEnergiePoolContract, STATE_VERSION};
+pub use milestones::{Milestone, MilestoneInput, MilestonePlan, MilestoneStatus, MILESTONE_REVIEW_PERIOD};
+pub use streams::Stream;
+pub use tokens::STORAGE_DEPOSIT;
+
+pub const TGAS: u64 = 1_000_000_000_000;
+
+//Define NEP-141 token contracts
+#[ext_contract(ext_ft)]
+pub trait FungibleToken {
+    fn ft_transfer (&mut self, receiver_id:AccountId, amount:U128, memo:Option<String>);
+    fn storage_deposit (&mut self, account_id:Option<AccountId>, registration_only:Option<bool>);
+}
 
 
 // VOTE
@@ -43,6 +67,8 @@ pub struct Proposals{
     pub duration_min:u64,
     pub list_voters:Vec<String>,
     pub votes:Vec<Vote>,
+    // NEP-141 token paid in its smallest unit, NEAR in whole tokens when not set
+    pub token: Option<String>,
 }
 
 // Proposals implementation 
@@ -62,6 +88,7 @@ impl Proposals {
             duration_min:0,
             list_voters: Vec::new(),
             votes:Vec::new(),
+            token: None,
         }
     }
 
@@ -97,7 +124,8 @@ impl Proposals {
             duration_hours: self.duration_hours, 
             duration_min: self.duration_min,
             list_voters: self.list_voters.clone(), 
-            votes: self.votes.clone() 
+            votes: self.votes.clone(),
+            token: self.token.clone(),
         }
     }
 
@@ -113,8 +141,9 @@ impl Proposals {
         self.time_of_creation+(self.duration_days*86400000000+self.duration_hours*3600000000+self.duration_min*60000000)
     }
 
+    // Check that the vote is over and accepted the proposal
     pub fn check_proposal(&self)->bool{
-        if env::block_timestamp() > self.end_time() {
+        if env::block_timestamp() > self.end_time() && self.votes_for > self.votes_against {
             return true;
         }
         return false;
@@ -128,6 +157,24 @@ impl Proposals {
 pub struct EnergiePoolContract {
     members: Vec<String>,
     records: Vec<Proposals>,
+    tokens: UnorderedSet<String>,
+    balances: UnorderedMap<String, u128>,
+    // Length in seconds of the stream of each stream proposal
+    stream_durations: LookupMap<String, u64>,
+    // Stream started by each stream proposal
+    proposal_streams: LookupMap<String, u64>,
+    // Streams proposed for cancellation, by proposal title
+    stream_cancellations: LookupMap<String, u64>,
+    streams: Vector<Stream>,
+    // Tranches of each milestone proposal
+    milestone_plans: LookupMap<String, MilestonePlan>,
+    // Member changes proposed, by proposal title
+    member_changes: LookupMap<String, MemberChange>,
+    // Proposals paid by check_and_send_near, kept apart from the records votes are written to
+    executed_proposals: LookupSet<String>,
+    // yoctoNEAR owed to streams and committed milestones that the pool can't pay out elsewhere
+    reserved_near: u128,
+    state_version: u8,
 }
 
 // Define the default, which automatically initializes the contract
@@ -150,13 +197,29 @@ fn assert_self() {
 // To be implemented in the front end 
 #[near_bindgen]
 impl EnergiePoolContract {
+    // The first members are given at init, the next ones join through a proposal
     #[init]
-    pub fn new() -> Self {
+    pub fn new(members: Option<Vec<String>>) -> Self {
         assert!(env::state_read::<Self>().is_none(), "Already initialized");
-        Self {
+        let mut this = Self {
             members: Vec::new(),
             records: Vec::new(),
+            tokens: UnorderedSet::new(b"w"),
+            balances: UnorderedMap::new(b"t"),
+            stream_durations: LookupMap::new(b"d"),
+            proposal_streams: LookupMap::new(b"p"),
+            stream_cancellations: LookupMap::new(b"c"),
+            streams: Vector::new(b"s"),
+            milestone_plans: LookupMap::new(b"l"),
+            member_changes: LookupMap::new(b"a"),
+            executed_proposals: LookupSet::new(b"x"),
+            reserved_near: 0,
+            state_version: STATE_VERSION,
+        };
+        for account in members.unwrap_or_default() {
+            this.internal_add_member(account);
         }
+        this
     }
 
     // delete all proposals
@@ -169,29 +232,6 @@ impl EnergiePoolContract {
 
     // Methods.
 
-    // Add a member
-    pub fn add_member (&mut self, account:String) {
-        let mut existance = false;
-        for i in self.members.clone(){
-            if i == account {
-                existance = true;
-                break;
-            }
-        }
-        assert!(existance == false , "Account already exist");
-        self.members.push(account);
-    }
-
-    // Remove a member
-    pub fn remove_member (&mut self, account:String) {
-        for i in 0..self.members.len(){
-            if self.members[i] == account {
-                self.members.swap_remove(i);
-                break;
-            }
-        }
-    }
-
     // create proposal
     pub fn create_proposal (
         &mut self,
@@ -201,7 +241,8 @@ impl EnergiePoolContract {
         benificiary: String,
         duration_days:u64,
         duration_hours:u64,
-        duration_min:u64
+        duration_min:u64,
+        token: Option<String>
     ){  
         let mut existance = false;
         for i in self.members.clone(){
@@ -211,6 +252,14 @@ impl EnergiePoolContract {
             }
         }
         assert!(existance == true , "You are not one of the councils");
+        // votes and payouts look proposals up by title, a second one would shadow the first
+        assert!(
+            self.records.iter().all(|p| p.title != title),
+            "A proposal with this title already exists"
+        );
+        if let Some(token) = &token {
+            self.assert_known_token(token);
+        }
         let proposal=Proposals{
             title: title,
             description: description,
@@ -224,26 +273,20 @@ impl EnergiePoolContract {
             duration_hours:duration_hours,
             duration_min:duration_min,
             list_voters: Vec::new(),
-            votes:Vec::new()
+            votes:Vec::new(),
+            token: token,
         };
+        PoolEvent::ProposalCreate(&[ProposalCreateData {
+            title: &proposal.title,
+            creator: &proposal.proposal_creator,
+            amount: U128(proposal.amount),
+            benificiary: &proposal.benificiary,
+            token: proposal.token.as_deref(),
+        }])
+        .emit();
         self.records.push(proposal);
     }
 
-    // replace a proposal 
-    pub fn replace_proposal(&mut self, proposal: Proposals){
-        let mut index =0;
-        for i in 0..self.records.len(){
-            match self.records.get(i){
-                Some(p) => if p.title==proposal.title {
-                    index=i;
-                },
-                None => panic!("There is no DAOs"),
-            }
-        }
-        self.records.swap_remove(index);
-        self.records.insert(index, proposal);
-    }
-
     // get all proposals 
     pub fn get_proposals(&self) -> Vec<Proposals>{
         self.records.clone()
@@ -275,9 +318,24 @@ impl EnergiePoolContract {
         title: String,
         vote: u8
     ){
-        let mut proposal = self.get_specific_proposal(title);
+        assert!(
+            self.members.contains(&env::signer_account_id().to_string()),
+            "You must be a member to vote"
+        );
+        let mut proposal = self.get_specific_proposal(title.clone());
         proposal = proposal.create_vote(vote);
         self.replace_proposal(proposal);
+        PoolEvent::Vote(&[VoteData {
+            title: &title,
+            voter: env::signer_account_id().as_str(),
+            vote,
+        }])
+        .emit();
+    }
+
+    // check if a proposal was paid by check_and_send_near
+    pub fn is_executed(&self, title: String) -> bool {
+        self.executed_proposals.contains(&title)
     }
 
     // get votes for 
@@ -298,25 +356,353 @@ impl EnergiePoolContract {
         proposal.votes_against + proposal.votes_for
     }
 
+    // check the proposal and send its amount to the benificiary if it passed
+    // A proposal is paid once, it can be executed again only if the transfer failed
+    pub fn check_and_send_near(&mut self,title: String) -> PromiseOrValue<String>{
+        assert!(
+            self.stream_durations.get(&title).is_none(),
+            "This proposal is paid as a stream, start it with start_stream"
+        );
+        assert!(
+            self.milestone_plans.get(&title).is_none(),
+            "This proposal is paid by milestones, release them with release_milestone"
+        );
+        let proposal = self.get_specific_proposal(title.clone());
+        if !proposal.check_proposal() {
+            return PromiseOrValue::Value("Proposal refused".to_string());
+        }
+        assert!(self.executed_proposals.insert(&title), "The proposal is already executed");
+        assert!(proposal.amount > 0, "The proposal doesn't pay anything");
+        let benificiary: AccountId= proposal.get_benificiary().try_into().unwrap();
+        let amount= match &proposal.token {
+            Some(_) => proposal.get_amount(),
+            None => proposal.get_amount() * 1000000000000000000000000,
+        };
+        match &proposal.token {
+            Some(token) => self.internal_withdraw(token, amount),
+            None => assert!(self.available_near() >= amount, "The pool doesn't hold enough NEAR"),
+        }
+        let payment= match &proposal.token {
+            Some(token) => self.internal_transfer(token, benificiary, amount),
+            None => self.pay(amount,benificiary),
+        };
+        payment
+            .then(
+                Self::ext(env::current_account_id())
+                .with_static_gas(Gas(5 * TGAS))
+                .payout_callback(title, U128(amount))
+            )
+            .into()
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn payout_callback(
+        &mut self,
+        title: String,
+        amount: U128,
+        #[callback_result] call_result: Result<(), PromiseError>,
+    ) -> String {
+        let proposal = self.get_specific_proposal(title.clone());
+        if call_result.is_err() {
+            if let Some(token) = &proposal.token {
+                let balance = self.balances.get(token).unwrap_or(0);
+                self.balances.insert(token, &(balance + amount.0));
+            }
+            self.executed_proposals.remove(&title);
+            "The transfer failed, the proposal can be executed again".to_string()
+        } else {
+            PoolEvent::Payout(&[PayoutData {
+                title: &title,
+                benificiary: &proposal.benificiary,
+                amount,
+                token: proposal.token.as_deref(),
+            }])
+            .emit();
+            "Proposal accepted and amount was sent".to_string()
+        }
+    }
+}
+
+impl EnergiePoolContract {
+    // replace a proposal, not exposed so votes can't be forged
+    fn replace_proposal(&mut self, proposal: Proposals){
+        let mut index =0;
+        for i in 0..self.records.len(){
+            match self.records.get(i){
+                Some(p) => if p.title==proposal.title {
+                    index=i;
+                },
+                None => panic!("There is no DAOs"),
+            }
+        }
+        self.records.swap_remove(index);
+        self.records.insert(index, proposal);
+    }
+
     // funtion that pay near to an account
-    pub fn pay(&self, amount: u128, to: AccountId) -> Promise {
+    fn pay(&self, amount: u128, to: AccountId) -> Promise {
         Promise::new(to).transfer(amount)
     }
 
-    // check the proposal and send near to the benificiary if it's true
-    pub fn check_and_send_near(&self,title: String) -> String{
-        let proposal = self.get_specific_proposal(title);
-        let check= proposal.check_proposal();
-        if check==true {
-            let benificiary= proposal.get_benificiary().try_into().unwrap();
-            let amount= proposal.get_amount() * 1000000000000000000000000;
-            let _payment=self.pay(amount,benificiary);
-            let msg="Proposal accepted and amount was sent".to_string();
-            msg
-        }else{
-            let msg="Proposal refused".to_string();
-            msg
-        }
+    // NEAR of the pool that isn't locked for its storage or reserved
+    pub(crate) fn available_near(&self) -> u128 {
+        env::account_balance()
+            .saturating_sub(env::storage_usage() as u128 * env::storage_byte_cost())
+            .saturating_sub(self.reserved_near)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+    use near_sdk::json_types::U64;
+    use near_sdk::ONE_NEAR;
+    use super::*;
+
+    fn member_context() -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("energie_pool.testnet".parse().unwrap())
+            .predecessor_account_id("alach.testnet".parse().unwrap())
+            .signer_account_id("alach.testnet".parse().unwrap());
+        builder
+    }
+
+    // Creates a 10 NEAR proposal for issameths.testnet with one vote and ends its vote
+    fn contract_with_proposal(vote: u8) -> EnergiePoolContract {
+        testing_env!(member_context().build());
+        let mut contract = EnergiePoolContract::new(Some(vec!["alach.testnet".to_string()]));
+        contract.create_proposal("solar".to_string(), "description".to_string(), 10, "issameths.testnet".to_string(), 0, 0, 1, None);
+        contract.add_vote("solar".to_string(), vote);
+        testing_env!(member_context().block_timestamp(60000001).build());
+        contract
+    }
+
+    //testing the migration of the deployed state, which ends with a vote timestamp
+    #[test]
+    fn test_migrate_from_v0(){
+        testing_env!(member_context().block_timestamp(1_700_000_000_000_000_000).build());
+        let mut proposal = Proposals::new();
+        proposal.title = "solar".to_string();
+        proposal.amount = 10;
+        proposal.create_vote(1);
+        let proposal = ProposalsV0 {
+            title: proposal.title,
+            description: proposal.description,
+            proposal_creator: proposal.proposal_creator,
+            amount: proposal.amount,
+            benificiary: proposal.benificiary,
+            votes_for: proposal.votes_for,
+            votes_against: proposal.votes_against,
+            time_of_creation: proposal.time_of_creation,
+            duration_days: proposal.duration_days,
+            duration_hours: proposal.duration_hours,
+            duration_min: proposal.duration_min,
+            list_voters: proposal.list_voters,
+            votes: proposal.votes,
+        };
+        env::state_write(&EnergiePoolContractV0 { members: vec!["alach.testnet".to_string()], records: vec![proposal] });
+
+        testing_env!(member_context().predecessor_account_id("energie_pool.testnet".parse().unwrap()).build());
+        let contract = EnergiePoolContract::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert_eq!(contract.members, vec!["alach.testnet".to_string()]);
+        assert_eq!(contract.records[0].votes_for, 1);
+        assert_eq!(contract.records[0].token, None);
+    }
+
+    #[test]
+    fn test_migrate_current(){
+        let contract = contract_with_proposal(1);
+        env::state_write(&contract);
+        testing_env!(member_context().predecessor_account_id("energie_pool.testnet".parse().unwrap()).build());
+        let contract = EnergiePoolContract::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert_eq!(contract.records[0].title, "solar".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by owner")]
+    fn test_migrate_not_owner(){
+        testing_env!(member_context().build());
+        EnergiePoolContract::migrate();
+    }
+
+    #[test]
+    fn test_check_and_send_near(){
+        let mut contract = contract_with_proposal(1);
+        assert!(matches!(contract.check_and_send_near("solar".to_string()), PromiseOrValue::Promise(_)));
+        assert!(contract.is_executed("solar".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "The proposal is already executed")]
+    fn test_check_and_send_near_twice(){
+        let mut contract = contract_with_proposal(1);
+        contract.check_and_send_near("solar".to_string());
+        contract.check_and_send_near("solar".to_string());
+    }
+
+    #[test]
+    fn test_check_and_send_near_refused(){
+        let mut contract = contract_with_proposal(0);
+        assert!(matches!(
+            contract.check_and_send_near("solar".to_string()),
+            PromiseOrValue::Value(msg) if msg == "Proposal refused"
+        ));
+        assert!(!contract.is_executed("solar".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "The pool doesn't hold enough NEAR")]
+    fn test_check_and_send_near_without_balance(){
+        let mut contract = contract_with_proposal(1);
+        testing_env!(member_context().block_timestamp(60000001).account_balance(ONE_NEAR).build());
+        contract.check_and_send_near("solar".to_string());
+    }
+
+    //testing that NEAR owed to a stream can't pay another proposal
+    #[test]
+    #[should_panic(expected = "The pool doesn't hold enough NEAR")]
+    fn test_check_and_send_near_reserved_by_stream(){
+        testing_env!(member_context().build());
+        let mut contract = EnergiePoolContract::new(Some(vec!["alach.testnet".to_string()]));
+        contract.create_proposal("solar".to_string(), "description".to_string(), 10, "issameths.testnet".to_string(), 0, 0, 1, None);
+        contract.add_vote("solar".to_string(), 1);
+        contract.create_stream_proposal("stream".to_string(), "description".to_string(), 10, "issameths.testnet".to_string(), None, U64(100), 0, 0, 1);
+        contract.add_vote("stream".to_string(), 1);
+        testing_env!(member_context().block_timestamp(60000001).account_balance(15 * ONE_NEAR).build());
+        contract.start_stream("stream".to_string());
+        contract.check_and_send_near("solar".to_string());
+    }
+
+    // Creates the "solar" proposal and a passed 10 NEAR milestone proposal for issameths.testnet
+    // whose only milestone is due at `deadline`, then ends the votes
+    fn contract_with_milestone(deadline: u64) -> EnergiePoolContract {
+        let mut contract = contract_with_proposal(1);
+        testing_env!(member_context().build());
+        let milestone = MilestoneInput { description: "panels".to_string(), amount: U128(10), deadline: U64(deadline) };
+        contract.create_milestone_proposal("farm".to_string(), "description".to_string(), "issameths.testnet".to_string(), None, vec![milestone], 0, 0, 1);
+        contract.add_vote("farm".to_string(), 1);
+        testing_env!(member_context().block_timestamp(60000001).account_balance(15 * ONE_NEAR).build());
+        contract
+    }
+
+    //testing that NEAR committed to milestones can't pay another proposal
+    #[test]
+    #[should_panic(expected = "The pool doesn't hold enough NEAR")]
+    fn test_check_and_send_near_reserved_by_milestones(){
+        let mut contract = contract_with_milestone(1_000_000_000);
+        contract.commit_milestones("farm".to_string());
+        contract.check_and_send_near("solar".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "The deadline of a milestone is before the end of the vote")]
+    fn test_commit_milestones_deadline_before_vote_end(){
+        let mut contract = contract_with_milestone(10);
+        contract.commit_milestones("farm".to_string());
+    }
+
+    //testing that evidence nobody accepts expires after the review period and frees the NEAR
+    #[test]
+    fn test_expire_submitted_milestone(){
+        let mut contract = contract_with_milestone(1_000_000_000);
+        contract.commit_milestones("farm".to_string());
+        testing_env!(member_context().predecessor_account_id("issameths.testnet".parse().unwrap()).block_timestamp(100000000).build());
+        contract.submit_milestone("farm".to_string(), 0, "https://lightency.io/farm".to_string());
+
+        testing_env!(member_context().block_timestamp(1_000_000_001 + MILESTONE_REVIEW_PERIOD).account_balance(15 * ONE_NEAR).build());
+        contract.expire_milestone("farm".to_string(), 0);
+        assert_eq!(contract.get_milestones("farm".to_string()).unwrap().milestones[0].status, MilestoneStatus::Expired);
+        assert!(matches!(contract.check_and_send_near("solar".to_string()), PromiseOrValue::Promise(_)));
+    }
+
+    #[test]
+    #[should_panic(expected = "The deadline of the milestone isn't over")]
+    fn test_expire_submitted_milestone_during_review(){
+        let mut contract = contract_with_milestone(1_000_000_000);
+        contract.commit_milestones("farm".to_string());
+        testing_env!(member_context().predecessor_account_id("issameths.testnet".parse().unwrap()).block_timestamp(100000000).build());
+        contract.submit_milestone("farm".to_string(), 0, "https://lightency.io/farm".to_string());
+        testing_env!(member_context().block_timestamp(1_000_000_001).build());
+        contract.expire_milestone("farm".to_string(), 0);
+    }
+
+    #[test]
+    fn test_payout_failed(){
+        let mut contract = contract_with_proposal(1);
+        contract.check_and_send_near("solar".to_string());
+        assert_eq!(
+            contract.payout_callback("solar".to_string(), U128(10 * ONE_NEAR), Err(PromiseError::Failed)),
+            "The transfer failed, the proposal can be executed again"
+        );
+        assert!(!contract.is_executed("solar".to_string()));
+        contract.check_and_send_near("solar".to_string());
+        assert!(!near_sdk::test_utils::get_logs().iter().any(|log| log.contains("\"event\":\"payout\"")));
+    }
+
+    //testing that the payout is logged once the transfer went through
+    #[test]
+    fn test_payout_event(){
+        let mut contract = contract_with_proposal(1);
+        contract.check_and_send_near("solar".to_string());
+        assert!(!near_sdk::test_utils::get_logs().iter().any(|log| log.contains("\"event\":\"payout\"")));
+        assert_eq!(
+            contract.payout_callback("solar".to_string(), U128(10 * ONE_NEAR), Ok(())),
+            "Proposal accepted and amount was sent"
+        );
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains("\"event\":\"payout\"")));
+    }
+
+    //testing a member joining and leaving through proposals
+    #[test]
+    fn test_member_proposals(){
+        let mut contract = contract_with_proposal(1);
+        contract.create_member_proposal("join".to_string(), "description".to_string(), MemberChange::Add("issameths.testnet".to_string()), 0, 0, 1);
+        contract.add_vote("join".to_string(), 1);
+        testing_env!(member_context().block_timestamp(120000002).build());
+        contract.execute_member_change("join".to_string());
+        assert_eq!(contract.get_members(), vec!["alach.testnet".to_string(), "issameths.testnet".to_string()]);
+
+        testing_env!(member_context().predecessor_account_id("issameths.testnet".parse().unwrap()).build());
+        contract.leave_pool();
+        assert_eq!(contract.get_members(), vec!["alach.testnet".to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "The member proposal hasn't passed")]
+    fn test_member_proposal_refused(){
+        let mut contract = contract_with_proposal(1);
+        contract.create_member_proposal("join".to_string(), "description".to_string(), MemberChange::Add("issameths.testnet".to_string()), 0, 0, 1);
+        contract.add_vote("join".to_string(), 0);
+        testing_env!(member_context().block_timestamp(120000002).build());
+        contract.execute_member_change("join".to_string());
+    }
+
+    //testing that a refused member proposal can't be shadowed by a passing proposal
+    #[test]
+    #[should_panic(expected = "A proposal with this title already exists")]
+    fn test_member_proposal_shadowed(){
+        let mut contract = contract_with_proposal(1);
+        contract.create_member_proposal("join".to_string(), "description".to_string(), MemberChange::Add("issameths.testnet".to_string()), 0, 0, 1);
+        contract.add_vote("join".to_string(), 0);
+        contract.create_proposal("join".to_string(), "description".to_string(), 1, "issameths.testnet".to_string(), 0, 0, 1, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "You must be a member to vote")]
+    fn test_vote_not_member(){
+        let mut contract = contract_with_proposal(1);
+        testing_env!(member_context().signer_account_id("issameths.testnet".parse().unwrap()).build());
+        contract.add_vote("solar".to_string(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The pool must keep at least one member")]
+    fn test_last_member_leaves(){
+        let mut contract = contract_with_proposal(1);
+        contract.leave_pool();
+    }
+}
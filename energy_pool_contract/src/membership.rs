@@ -0,0 +1,89 @@
+//! Members of the pool and how they join or leave.
+//!
+//! The first members are set at init, then every change goes through a proposal voted by the
+//! members. A member can always leave on its own as long as the pool keeps one member.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::{env, near_bindgen};
+use serde::{Deserialize, Serialize};
+
+use crate::events::{Event, MemberData, PoolEvent};
+use crate::{EnergiePoolContract, EnergiePoolContractExt};
+
+// Change of membership proposed to the members
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemberChange {
+    Add(String),
+    Remove(String),
+}
+
+impl EnergiePoolContract {
+    pub(crate) fn internal_add_member(&mut self, account: String) {
+        assert!(!self.members.contains(&account), "Account already exist");
+        PoolEvent::MemberJoin(&[MemberData { account_id: &account }]).emit();
+        self.members.push(account);
+    }
+
+    fn internal_remove_member(&mut self, account: &String) {
+        let index = self.members.iter().position(|m| m == account).expect("This account isn't a member");
+        assert!(self.members.len() > 1, "The pool must keep at least one member");
+        self.members.swap_remove(index);
+        PoolEvent::MemberLeave(&[MemberData { account_id: account }]).emit();
+    }
+}
+
+#[near_bindgen]
+impl EnergiePoolContract {
+    // Function to propose adding or removing a member
+    pub fn create_member_proposal(
+        &mut self,
+        title: String,
+        description: String,
+        change: MemberChange,
+        duration_days: u64,
+        duration_hours: u64,
+        duration_min: u64,
+    ) {
+        let account = match &change {
+            MemberChange::Add(account) => {
+                assert!(!self.members.contains(account), "Account already exist");
+                account.clone()
+            }
+            MemberChange::Remove(account) => {
+                assert!(self.members.contains(account), "This account isn't a member");
+                account.clone()
+            }
+        };
+        assert!(self.member_changes.get(&title).is_none(), "A member proposal with this title already exists");
+        self.create_proposal(title.clone(), description, 0, account, duration_days, duration_hours, duration_min, None);
+        self.member_changes.insert(&title, &change);
+    }
+
+    // Function to apply the change of a passed member proposal, callable by anyone
+    pub fn execute_member_change(&mut self, title: String) {
+        let change = self.member_changes.get(&title).expect("There is no member proposal with this title");
+        assert!(
+            self.get_specific_proposal(title.clone()).check_proposal(),
+            "The member proposal hasn't passed"
+        );
+        self.member_changes.remove(&title);
+        match change {
+            MemberChange::Add(account) => self.internal_add_member(account),
+            MemberChange::Remove(account) => self.internal_remove_member(&account),
+        }
+    }
+
+    // Function to leave the pool
+    pub fn leave_pool(&mut self) {
+        self.internal_remove_member(&env::predecessor_account_id().to_string());
+    }
+
+    pub fn get_members(&self) -> Vec<String> {
+        self.members.clone()
+    }
+
+    pub fn get_member_change(&self, title: String) -> Option<MemberChange> {
+        self.member_changes.get(&title)
+    }
+}
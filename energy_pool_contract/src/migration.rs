@@ -0,0 +1,118 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::{env, near_bindgen};
+
+use crate::{assert_self, EnergiePoolContract, EnergiePoolContractExt, Proposals, Vote};
+
+// Version of the layout written by this code, bumped with every change of a deployed layout
+pub const STATE_VERSION: u8 = 1;
+
+// Proposal deployed before the NEP-141 tokens, always paid in NEAR
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct ProposalsV0 {
+    pub title: String,
+    pub description: String,
+    pub proposal_creator: String,
+    pub amount: u128,
+    pub benificiary: String,
+    pub votes_for: u32,
+    pub votes_against: u32,
+    pub time_of_creation: u64,
+    pub duration_days: u64,
+    pub duration_hours: u64,
+    pub duration_min: u64,
+    pub list_voters: Vec<String>,
+    pub votes: Vec<Vote>,
+}
+
+impl From<ProposalsV0> for Proposals {
+    fn from(proposal: ProposalsV0) -> Self {
+        Proposals {
+            title: proposal.title,
+            description: proposal.description,
+            proposal_creator: proposal.proposal_creator,
+            amount: proposal.amount,
+            benificiary: proposal.benificiary,
+            votes_for: proposal.votes_for,
+            votes_against: proposal.votes_against,
+            time_of_creation: proposal.time_of_creation,
+            duration_days: proposal.duration_days,
+            duration_hours: proposal.duration_hours,
+            duration_min: proposal.duration_min,
+            list_voters: proposal.list_voters,
+            votes: proposal.votes,
+            token: None,
+        }
+    }
+}
+
+// Layout deployed before the state was versioned
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct EnergiePoolContractV0 {
+    pub members: Vec<String>,
+    pub records: Vec<ProposalsV0>,
+}
+
+impl From<EnergiePoolContractV0> for EnergiePoolContract {
+    fn from(state: EnergiePoolContractV0) -> Self {
+        EnergiePoolContract {
+            members: state.members,
+            records: state.records.into_iter().map(Proposals::from).collect(),
+            tokens: UnorderedSet::new(b"w"),
+            balances: UnorderedMap::new(b"t"),
+            stream_durations: LookupMap::new(b"d"),
+            proposal_streams: LookupMap::new(b"p"),
+            stream_cancellations: LookupMap::new(b"c"),
+            streams: Vector::new(b"s"),
+            milestone_plans: LookupMap::new(b"l"),
+            member_changes: LookupMap::new(b"a"),
+            executed_proposals: LookupSet::new(b"x"),
+            reserved_near: 0,
+            state_version: STATE_VERSION,
+        }
+    }
+}
+
+// Layouts the state can be stored with
+pub enum VersionedEnergiePoolContract {
+    V0(EnergiePoolContractV0),
+    Current(Box<EnergiePoolContract>),
+}
+
+impl VersionedEnergiePoolContract {
+    // Function to read the stored state with the layout named by its last byte
+    // The versioned layout ends with `state_version`, the unversioned one with the last byte of a
+    // vote timestamp or of the length of an empty list, never a version
+    pub fn read() -> Self {
+        let bytes = env::storage_read(b"STATE").expect("The contract isn't initialized");
+        let state = |bytes: &[u8]| -> Result<Self, std::io::Error> {
+            Ok(match bytes.last().copied() {
+                Some(STATE_VERSION) => VersionedEnergiePoolContract::Current(Box::new(EnergiePoolContract::try_from_slice(bytes)?)),
+                _ => VersionedEnergiePoolContract::V0(EnergiePoolContractV0::try_from_slice(bytes)?),
+            })
+        };
+        state(&bytes).expect("The state doesn't match its version")
+    }
+
+    // Function to convert the stored layout to the current one
+    pub fn into_current(self) -> EnergiePoolContract {
+        match self {
+            VersionedEnergiePoolContract::V0(state) => state.into(),
+            VersionedEnergiePoolContract::Current(state) => *state,
+        }
+    }
+}
+
+#[near_bindgen]
+impl EnergiePoolContract {
+    // Function to upgrade the stored state after deploying a new version of the code
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        assert_self();
+        VersionedEnergiePoolContract::read().into_current()
+    }
+
+    pub fn get_state_version(&self) -> u8 {
+        self.state_version
+    }
+}
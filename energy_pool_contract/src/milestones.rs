@@ -0,0 +1,296 @@
+//! Project funding released in tranches.
+//!
+//! A milestone proposal splits its amount between milestones with a deadline. Once the proposal
+//! has passed, its total is committed and the benificiary submits evidence for each milestone.
+//! A tranche is released when more than half of the members vote for it. A milestone still
+//! without evidence after its deadline, or whose evidence isn't accepted within the review period
+//! that follows it, expires and gives its tranche back to the pool.
+
+// Milestone proposals take the arguments of create_proposal plus their milestones
+#![allow(clippy::too_many_arguments)]
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, near_bindgen, AccountId, Gas, Promise, PromiseError};
+use serde::{Deserialize, Serialize};
+
+use crate::events::{Event, MilestoneExpireData, MilestoneReleaseData, MilestoneSubmitData, MilestoneVoteData, PoolEvent};
+use crate::{EnergiePoolContract, EnergiePoolContractExt, TGAS};
+
+// Time the members have after the deadline of a milestone to accept its evidence, 7 days
+pub const MILESTONE_REVIEW_PERIOD: u64 = 7 * 86_400_000_000_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MilestoneStatus {
+    // Waiting for the evidence of the benificiary
+    Pending,
+    // Evidence submitted, the members are voting
+    Submitted,
+    Released,
+    Expired,
+}
+
+// Milestone as proposed, the amount is in the unit of the proposal
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MilestoneInput {
+    pub description: String,
+    pub amount: U128,
+    // Nanoseconds timestamp
+    pub deadline: U64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Milestone {
+    pub description: String,
+    pub amount: U128,
+    pub deadline: U64,
+    pub evidence: Option<String>,
+    pub votes_for: u32,
+    pub votes_against: u32,
+    pub list_voters: Vec<String>,
+    pub status: MilestoneStatus,
+}
+
+// Milestones of a proposal, `committed` once the proposal has passed and its total is set aside
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MilestonePlan {
+    pub committed: bool,
+    pub milestones: Vec<Milestone>,
+}
+
+impl EnergiePoolContract {
+    fn internal_get_plan(&self, title: &String) -> MilestonePlan {
+        self.milestone_plans.get(title).expect("There is no milestone proposal with this title")
+    }
+
+    // Function to convert an amount of a proposal to yoctoNEAR or to the smallest unit of its token
+    fn internal_payout_amount(token: &Option<String>, amount: u128) -> u128 {
+        match token {
+            Some(_) => amount,
+            None => amount * 1000000000000000000000000,
+        }
+    }
+}
+
+#[near_bindgen]
+impl EnergiePoolContract {
+    // Function to propose funding a project in tranches, the amount of the proposal is their sum
+    // Amounts are in whole NEAR without a token, in the smallest unit of the token otherwise
+    pub fn create_milestone_proposal(
+        &mut self,
+        title: String,
+        description: String,
+        benificiary: String,
+        token: Option<String>,
+        milestones: Vec<MilestoneInput>,
+        duration_days: u64,
+        duration_hours: u64,
+        duration_min: u64,
+    ) {
+        assert!(!milestones.is_empty(), "The proposal needs at least one milestone");
+        assert!(self.milestone_plans.get(&title).is_none(), "A milestone proposal with this title already exists");
+        let amount = milestones.iter().map(|m| m.amount.0).sum();
+        let milestones = milestones
+            .into_iter()
+            .map(|m| {
+                assert!(m.amount.0 > 0, "Each milestone must release something");
+                Milestone {
+                    description: m.description,
+                    amount: m.amount,
+                    deadline: m.deadline,
+                    evidence: None,
+                    votes_for: 0,
+                    votes_against: 0,
+                    list_voters: Vec::new(),
+                    status: MilestoneStatus::Pending,
+                }
+            })
+            .collect();
+        self.create_proposal(
+            title.clone(),
+            description,
+            amount,
+            benificiary,
+            duration_days,
+            duration_hours,
+            duration_min,
+            token,
+        );
+        self.milestone_plans.insert(&title, &MilestonePlan { committed: false, milestones });
+    }
+
+    // Function to set aside the total of a passed milestone proposal, callable by anyone
+    pub fn commit_milestones(&mut self, title: String) {
+        let mut plan = self.internal_get_plan(&title);
+        assert!(!plan.committed, "The milestones are already committed");
+        let proposal = self.get_specific_proposal(title.clone());
+        assert!(proposal.check_proposal(), "The proposal hasn't passed");
+        assert!(
+            plan.milestones.iter().all(|m| m.deadline.0 > proposal.end_time()),
+            "The deadline of a milestone is before the end of the vote"
+        );
+        match &proposal.token {
+            Some(token) => self.internal_withdraw(token, proposal.amount),
+            None => {
+                let amount = Self::internal_payout_amount(&None, proposal.amount);
+                assert!(self.available_near() >= amount, "The pool doesn't hold enough NEAR");
+                self.reserved_near += amount;
+            }
+        }
+        plan.committed = true;
+        self.milestone_plans.insert(&title, &plan);
+    }
+
+    // Function for the benificiary to submit the evidence of a milestone
+    pub fn submit_milestone(&mut self, title: String, index: u32, evidence: String) {
+        let mut plan = self.internal_get_plan(&title);
+        assert!(plan.committed, "The milestones aren't committed yet");
+        assert_eq!(
+            env::predecessor_account_id().to_string(),
+            self.get_specific_proposal(title.clone()).benificiary,
+            "Only the benificiary can submit a milestone"
+        );
+        let milestone = plan.milestones.get_mut(index as usize).expect("There is no milestone with this index");
+        assert_eq!(milestone.status, MilestoneStatus::Pending, "The milestone isn't waiting for evidence");
+        assert!(env::block_timestamp() <= milestone.deadline.0, "The deadline of the milestone is over");
+        milestone.evidence = Some(evidence.clone());
+        milestone.status = MilestoneStatus::Submitted;
+        self.milestone_plans.insert(&title, &plan);
+        PoolEvent::MilestoneSubmit(&[MilestoneSubmitData {
+            title: &title,
+            index,
+            evidence: &evidence,
+        }])
+        .emit();
+    }
+
+    // Function for the members to vote on the evidence of a milestone
+    // The tranche can be released once more than half of the members voted for it, and the
+    // benificiary can submit new evidence once more than half voted against it
+    pub fn vote_milestone(&mut self, title: String, index: u32, vote: u8) {
+        let voter = env::signer_account_id().to_string();
+        assert!(self.members.contains(&voter), "You must be a member to vote");
+        let majority = self.members.len() as u32 / 2 + 1;
+        let mut plan = self.internal_get_plan(&title);
+        let milestone = plan.milestones.get_mut(index as usize).expect("There is no milestone with this index");
+        assert_eq!(milestone.status, MilestoneStatus::Submitted, "The milestone has no evidence to vote on");
+        assert!(!milestone.list_voters.contains(&voter), "You already voted");
+        milestone.list_voters.push(voter.clone());
+        if vote == 0 {
+            milestone.votes_against += 1;
+        } else {
+            milestone.votes_for += 1;
+        }
+        if milestone.votes_against >= majority {
+            milestone.status = MilestoneStatus::Pending;
+            milestone.votes_for = 0;
+            milestone.votes_against = 0;
+            milestone.list_voters.clear();
+        }
+        self.milestone_plans.insert(&title, &plan);
+        PoolEvent::MilestoneVote(&[MilestoneVoteData {
+            title: &title,
+            index,
+            voter: &voter,
+            vote,
+        }])
+        .emit();
+    }
+
+    // Function to pay the tranche of an accepted milestone, callable by anyone
+    pub fn release_milestone(&mut self, title: String, index: u32) -> Promise {
+        let majority = self.members.len() as u32 / 2 + 1;
+        let mut plan = self.internal_get_plan(&title);
+        let milestone = plan.milestones.get_mut(index as usize).expect("There is no milestone with this index");
+        assert_eq!(milestone.status, MilestoneStatus::Submitted, "The milestone has no evidence to release");
+        assert!(milestone.votes_for >= majority, "The milestone isn't accepted by the members");
+        milestone.status = MilestoneStatus::Released;
+        let amount = milestone.amount.0;
+        self.milestone_plans.insert(&title, &plan);
+
+        let proposal = self.get_specific_proposal(title.clone());
+        let amount = Self::internal_payout_amount(&proposal.token, amount);
+        if proposal.token.is_none() {
+            self.reserved_near -= amount;
+        }
+        PoolEvent::MilestoneRelease(&[MilestoneReleaseData {
+            title: &title,
+            index,
+            benificiary: &proposal.benificiary,
+            amount: U128(amount),
+            token: proposal.token.as_deref(),
+        }])
+        .emit();
+        let benificiary: AccountId = proposal.benificiary.parse().unwrap();
+        let payment = match &proposal.token {
+            Some(token) => self.internal_transfer(token, benificiary, amount),
+            None => Promise::new(benificiary).transfer(amount),
+        };
+        payment.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(Gas(5 * TGAS))
+                .release_milestone_callback(title, index, U128(amount)),
+        )
+    }
+
+    // Function to close a milestone without accepted evidence, callable by anyone once its deadline
+    // passed, or once the review period passed if evidence was submitted
+    // Its tranche goes back to the pool
+    pub fn expire_milestone(&mut self, title: String, index: u32) {
+        let majority = self.members.len() as u32 / 2 + 1;
+        let mut plan = self.internal_get_plan(&title);
+        assert!(plan.committed, "The milestones aren't committed yet");
+        let milestone = plan.milestones.get_mut(index as usize).expect("There is no milestone with this index");
+        let expiry = match milestone.status {
+            MilestoneStatus::Pending => milestone.deadline.0,
+            MilestoneStatus::Submitted => {
+                assert!(milestone.votes_for < majority, "The milestone is accepted, release it");
+                milestone.deadline.0 + MILESTONE_REVIEW_PERIOD
+            }
+            _ => panic!("The milestone is already closed"),
+        };
+        assert!(env::block_timestamp() > expiry, "The deadline of the milestone isn't over");
+        milestone.status = MilestoneStatus::Expired;
+        let amount = milestone.amount.0;
+        self.milestone_plans.insert(&title, &plan);
+
+        let token = self.get_specific_proposal(title.clone()).token;
+        match &token {
+            Some(token) => {
+                let balance = self.balances.get(token).unwrap_or(0);
+                self.balances.insert(token, &(balance + amount));
+            }
+            None => self.reserved_near -= Self::internal_payout_amount(&None, amount),
+        }
+        PoolEvent::MilestoneExpire(&[MilestoneExpireData {
+            title: &title,
+            index,
+            refunded: U128(Self::internal_payout_amount(&token, amount)),
+        }])
+        .emit();
+    }
+
+    pub fn get_milestones(&self, title: String) -> Option<MilestonePlan> {
+        self.milestone_plans.get(&title)
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn release_milestone_callback(
+        &mut self,
+        title: String,
+        index: u32,
+        amount: U128,
+        #[callback_result] call_result: Result<(), PromiseError>,
+    ) {
+        if call_result.is_err() {
+            let mut plan = self.internal_get_plan(&title);
+            plan.milestones[index as usize].status = MilestoneStatus::Submitted;
+            self.milestone_plans.insert(&title, &plan);
+            if self.get_specific_proposal(title).token.is_none() {
+                self.reserved_near += amount.0;
+            }
+            env::log_str("The transfer failed, the milestone can be released again");
+        }
+    }
+}
@@ -0,0 +1,263 @@
+//! Grants paid as a stream.
+//!
+//! A passed stream proposal pays its amount at a constant rate per second between the start and
+//! the end of the stream, and the benificiary withdraws what has accrued whenever they like. A
+//! passed cancel proposal stops the flow, tokens that haven't streamed go back to the pool.
+
+// Stream proposals take the arguments of create_proposal plus the length of the stream
+#![allow(clippy::too_many_arguments)]
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, near_bindgen, AccountId, Gas, Promise, PromiseError};
+use serde::{Deserialize, Serialize};
+
+use crate::events::{Event, PoolEvent, StreamCancelData, StreamStartData, StreamWithdrawData};
+use crate::{EnergiePoolContract, EnergiePoolContractExt, TGAS};
+
+const ONE_SECOND: u64 = 1_000_000_000;
+
+// Grant flowing to its benificiary
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stream {
+    pub id: u64,
+    pub title: String,
+    pub benificiary: String,
+    // NEP-141 token of the stream, NEAR when not set
+    pub token: Option<String>,
+    // Total paid by the stream in yoctoNEAR or in the smallest unit of the token
+    pub amount: U128,
+    // Amount accrued every second
+    pub rate: U128,
+    // Start and end of the flow in nanoseconds
+    pub start: U64,
+    pub end: U64,
+    pub withdrawn: U128,
+    pub cancelled: bool,
+}
+
+impl Stream {
+    // Function to get what has flowed to the benificiary at `timestamp`
+    pub fn streamed(&self, timestamp: u64) -> u128 {
+        if timestamp >= self.end.0 {
+            return self.amount.0;
+        }
+        let seconds = (timestamp.saturating_sub(self.start.0) / ONE_SECOND) as u128;
+        std::cmp::min(self.rate.0 * seconds, self.amount.0)
+    }
+
+    pub fn withdrawable(&self, timestamp: u64) -> u128 {
+        self.streamed(timestamp) - self.withdrawn.0
+    }
+}
+
+impl EnergiePoolContract {
+    fn internal_get_stream(&self, stream_id: u64) -> Stream {
+        self.streams.get(stream_id).expect("There is no stream with this id")
+    }
+}
+
+#[near_bindgen]
+impl EnergiePoolContract {
+    // Function to propose paying `amount` to `benificiary` over `stream_duration` seconds
+    // The amount is in whole NEAR without a token, in the smallest unit of the token otherwise
+    pub fn create_stream_proposal(
+        &mut self,
+        title: String,
+        description: String,
+        amount: u128,
+        benificiary: String,
+        token: Option<String>,
+        stream_duration: U64,
+        duration_days: u64,
+        duration_hours: u64,
+        duration_min: u64,
+    ) {
+        assert!(stream_duration.0 > 0, "The stream can't be empty");
+        assert!(amount > 0, "The stream must pay something");
+        self.create_proposal(
+            title.clone(),
+            description,
+            amount,
+            benificiary,
+            duration_days,
+            duration_hours,
+            duration_min,
+            token,
+        );
+        self.stream_durations.insert(&title, &stream_duration.0);
+    }
+
+    // Function to start the stream of a passed proposal, callable by anyone
+    pub fn start_stream(&mut self, title: String) -> u64 {
+        let duration = self.stream_durations.get(&title).expect("There is no stream proposal with this title");
+        assert!(self.proposal_streams.get(&title).is_none(), "The stream is already started");
+        let proposal = self.get_specific_proposal(title.clone());
+        assert!(proposal.check_proposal(), "The proposal hasn't passed");
+        let amount = match &proposal.token {
+            Some(token) => {
+                self.internal_withdraw(token, proposal.amount);
+                proposal.amount
+            }
+            None => {
+                let amount = proposal.amount * 1000000000000000000000000;
+                assert!(self.available_near() >= amount, "The pool doesn't hold enough NEAR");
+                self.reserved_near += amount;
+                amount
+            }
+        };
+        let start = env::block_timestamp();
+        let stream = Stream {
+            id: self.streams.len(),
+            title,
+            benificiary: proposal.benificiary,
+            token: proposal.token,
+            amount: U128(amount),
+            rate: U128(amount / duration as u128),
+            start: U64(start),
+            end: U64(start + duration * ONE_SECOND),
+            withdrawn: U128(0),
+            cancelled: false,
+        };
+        PoolEvent::StreamStart(&[StreamStartData {
+            stream_id: stream.id,
+            title: &stream.title,
+            benificiary: &stream.benificiary,
+            token: stream.token.as_deref(),
+            amount: stream.amount,
+            rate: stream.rate,
+            start: stream.start,
+            end: stream.end,
+        }])
+        .emit();
+        self.proposal_streams.insert(&stream.title, &stream.id);
+        self.streams.push(&stream);
+        stream.id
+    }
+
+    // Function to send the benificiary what has accrued on its stream
+    pub fn withdraw_from_stream(&mut self, stream_id: u64) -> Promise {
+        let mut stream = self.internal_get_stream(stream_id);
+        assert_eq!(
+            env::predecessor_account_id().to_string(),
+            stream.benificiary,
+            "Only the benificiary can withdraw from the stream"
+        );
+        let amount = stream.withdrawable(env::block_timestamp());
+        assert!(amount > 0, "Nothing to withdraw yet");
+        stream.withdrawn = U128(stream.withdrawn.0 + amount);
+        self.streams.replace(stream_id, &stream);
+        if stream.token.is_none() {
+            self.reserved_near -= amount;
+        }
+        PoolEvent::StreamWithdraw(&[StreamWithdrawData {
+            stream_id,
+            benificiary: &stream.benificiary,
+            amount: U128(amount),
+        }])
+        .emit();
+        let benificiary: AccountId = stream.benificiary.parse().unwrap();
+        let payment = match &stream.token {
+            Some(token) => self.internal_transfer(token, benificiary, amount),
+            None => Promise::new(benificiary).transfer(amount),
+        };
+        payment.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(Gas(5 * TGAS))
+                .stream_withdraw_callback(stream_id, U128(amount)),
+        )
+    }
+
+    // Function to propose stopping a stream
+    pub fn create_cancel_stream_proposal(
+        &mut self,
+        title: String,
+        description: String,
+        stream_id: u64,
+        duration_days: u64,
+        duration_hours: u64,
+        duration_min: u64,
+    ) {
+        let stream = self.internal_get_stream(stream_id);
+        assert!(!stream.cancelled, "The stream is already cancelled");
+        self.create_proposal(
+            title.clone(),
+            description,
+            0,
+            stream.benificiary,
+            duration_days,
+            duration_hours,
+            duration_min,
+            None,
+        );
+        self.stream_cancellations.insert(&title, &stream_id);
+    }
+
+    // Function to stop the stream of a passed cancel proposal, callable by anyone
+    // What has accrued stays withdrawable and the tokens left go back to the pool
+    pub fn cancel_stream(&mut self, title: String) {
+        let stream_id = self.stream_cancellations.get(&title).expect("There is no cancellation with this title");
+        assert!(self.get_specific_proposal(title.clone()).check_proposal(), "The cancel proposal hasn't passed");
+        self.stream_cancellations.remove(&title);
+        let mut stream = self.internal_get_stream(stream_id);
+        assert!(!stream.cancelled, "The stream is already cancelled");
+        let now = env::block_timestamp();
+        let streamed = stream.streamed(now);
+        let refunded = stream.amount.0 - streamed;
+        match &stream.token {
+            Some(token) => {
+                let balance = self.balances.get(token).unwrap_or(0);
+                self.balances.insert(token, &(balance + refunded));
+            }
+            None => self.reserved_near -= refunded,
+        }
+        stream.amount = U128(streamed);
+        stream.end = U64(std::cmp::min(now, stream.end.0));
+        stream.cancelled = true;
+        self.streams.replace(stream_id, &stream);
+        PoolEvent::StreamCancel(&[StreamCancelData {
+            stream_id,
+            title: &title,
+            refunded: U128(refunded),
+        }])
+        .emit();
+    }
+
+    pub fn get_stream(&self, stream_id: u64) -> Option<Stream> {
+        self.streams.get(stream_id)
+    }
+
+    // Function to get the stream started by a proposal
+    pub fn get_proposal_stream(&self, title: String) -> Option<Stream> {
+        self.proposal_streams.get(&title).and_then(|stream_id| self.streams.get(stream_id))
+    }
+
+    pub fn get_streams(&self, from_index: u64, limit: u64) -> Vec<Stream> {
+        (from_index..std::cmp::min(from_index + limit, self.streams.len()))
+            .map(|i| self.streams.get(i).unwrap())
+            .collect()
+    }
+
+    // Function to get what the benificiary of a stream can withdraw now
+    pub fn get_withdrawable(&self, stream_id: u64) -> U128 {
+        U128(self.internal_get_stream(stream_id).withdrawable(env::block_timestamp()))
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn stream_withdraw_callback(
+        &mut self,
+        stream_id: u64,
+        amount: U128,
+        #[callback_result] call_result: Result<(), PromiseError>,
+    ) {
+        if call_result.is_err() {
+            let mut stream = self.internal_get_stream(stream_id);
+            stream.withdrawn = U128(stream.withdrawn.0 - amount.0);
+            self.streams.replace(stream_id, &stream);
+            if stream.token.is_none() {
+                self.reserved_near += amount.0;
+            }
+            env::log_str("The transfer failed, the amount can be withdrawn again");
+        }
+    }
+}
@@ -0,0 +1,80 @@
+//! NEP-141 tokens held by the pool.
+//!
+//! NEAR stays the default currency of the proposals and is paid from the account balance.
+//! Whitelisted tokens are received through `ft_on_transfer` and their balances are tracked so a
+//! proposal can't pay out more than the pool received.
+
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, AccountId, Gas, Promise, PromiseOrValue};
+
+use crate::events::{DepositData, Event, PoolEvent};
+use crate::{assert_self, ext_ft, EnergiePoolContract, EnergiePoolContractExt, TGAS};
+
+// Deposit attached to register a benificiary on a token, the token refunds what isn't used
+pub const STORAGE_DEPOSIT: u128 = 12_500_000_000_000_000_000_000;
+
+impl EnergiePoolContract {
+    pub(crate) fn assert_known_token(&self, token: &str) {
+        assert!(self.tokens.contains(&token.to_string()), "The token {} isn't whitelisted", token);
+    }
+
+    pub(crate) fn internal_withdraw(&mut self, token: &str, amount: u128) {
+        let balance = self.balances.get(&token.to_string()).unwrap_or(0);
+        assert!(balance >= amount, "The pool doesn't hold enough {}", token);
+        self.balances.insert(&token.to_string(), &(balance - amount));
+    }
+
+    // Function to send `amount` of `token` to `receiver`, registering it on the token first
+    pub(crate) fn internal_transfer(&self, token: &str, receiver: AccountId, amount: u128) -> Promise {
+        let token_id: AccountId = token.parse().unwrap();
+        ext_ft::ext(token_id.clone())
+            .with_static_gas(Gas(5 * TGAS))
+            .with_attached_deposit(STORAGE_DEPOSIT)
+            .storage_deposit(Some(receiver.clone()), Some(true))
+            .then(
+                ext_ft::ext(token_id)
+                    .with_static_gas(Gas(5 * TGAS))
+                    .with_attached_deposit(1)
+                    .ft_transfer(receiver, U128(amount), None),
+            )
+    }
+}
+
+#[near_bindgen]
+impl EnergiePoolContract {
+    // Function receiving the whitelisted tokens sent to the pool
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        let token = env::predecessor_account_id().to_string();
+        self.assert_known_token(&token);
+        let balance = self.balances.get(&token).unwrap_or(0);
+        self.balances.insert(&token, &(balance + amount.0));
+        PoolEvent::Deposit(&[DepositData {
+            sender_id: sender_id.as_str(),
+            token: &token,
+            amount,
+            memo: Some(msg.as_str()).filter(|msg| !msg.is_empty()),
+        }])
+        .emit();
+        PromiseOrValue::Value(U128(0))
+    }
+
+    // Function to accept a NEP-141 token
+    pub fn add_token(&mut self, token: AccountId) {
+        assert_self();
+        self.tokens.insert(&token.to_string());
+    }
+
+    // Function to stop accepting a token, its balance stays spendable
+    pub fn remove_token(&mut self, token: AccountId) {
+        assert_self();
+        self.tokens.remove(&token.to_string());
+    }
+
+    pub fn get_tokens(&self) -> Vec<String> {
+        self.tokens.to_vec()
+    }
+
+    pub fn get_token_balance(&self, token: String) -> U128 {
+        U128(self.balances.get(&token).unwrap_or(0))
+    }
+}
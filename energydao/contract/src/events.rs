@@ -0,0 +1,56 @@
+// NEP-297 events emitted by the Energy DAO, logged with the `EVENT_JSON:` prefix.
+
+pub use lightency_events::Event;
+use near_sdk::json_types::{Base58CryptoHash, U128};
+use serde::Serialize;
+
+use crate::Role;
+
+#[derive(Serialize, Debug)]
+pub struct ProposalCreateData<'a> {
+    pub id: &'a str,
+    pub proposal_type: u8,
+    pub proposal_name: &'a str,
+    pub creator: &'a str,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+pub struct VoteData<'a> {
+    pub proposal_id: &'a str,
+    pub voter: &'a str,
+    pub vote: u8,
+}
+
+#[derive(Serialize, Debug)]
+pub struct UpgradeData<'a> {
+    pub proposal_id: &'a str,
+    pub code_hash: Base58CryptoHash,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MemberRemoveData<'a> {
+    pub account_id: &'a str,
+    pub role: Role,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum DaoEvent<'a> {
+    ProposalCreate(&'a [ProposalCreateData<'a>]),
+    Vote(&'a [VoteData<'a>]),
+    Upgrade(&'a [UpgradeData<'a>]),
+    MemberRemove(&'a [MemberRemoveData<'a>]),
+}
+
+impl Event for DaoEvent<'_> {
+    // Each event keeps its own schema version
+    fn version(&self) -> &'static str {
+        match self {
+            DaoEvent::ProposalCreate(_) => "1.0.0",
+            DaoEvent::Vote(_) => "1.0.0",
+            DaoEvent::Upgrade(_) => "1.0.0",
+            DaoEvent::MemberRemove(_) => "1.0.0",
+        }
+    }
+}
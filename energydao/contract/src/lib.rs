@@ -1,8 +1,18 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{UnorderedMap};
-use near_sdk::{env, near_bindgen, ext_contract,Gas};
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap};
+use near_sdk::{env, near_bindgen, ext_contract,Gas, CryptoHash};
+use near_sdk::json_types::U128;
 use serde::{Serialize,Deserialize};
 
+mod events;
+mod membership;
+mod migration;
+mod upgrade;
+use events::{DaoEvent, Event, ProposalCreateData, VoteData};
+pub use membership::{Role, ENERGY_FUND, MIN_COUNCIL_SIZE, REMOVE_COUNCIL_PROPOSAL};
+pub use migration::{EnergyDaoV0, VersionedEnergyDao, STATE_VERSION};
+pub use upgrade::UPGRADE_PROPOSAL;
+
 pub const TGAS: u64 = 1_000_000_000_000;
 
 #[ext_contract(ext_lts)]
@@ -12,7 +22,7 @@ pub trait Lts {
 
 // VOTE
 // Vote structor 
-#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize)]
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Vote{
     pub address: String,
     pub vote:u8,
@@ -32,7 +42,7 @@ impl Vote {
 }
 
 // Proposal structor
-#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize)]
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Proposal{
     pub id:String,
     pub proposal_type: u8,
@@ -128,8 +138,15 @@ impl Proposal{
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct EnergyDao {
-    members: UnorderedMap<String,u8>,
+    members: UnorderedMap<String,Role>,
     proposals: Vec<Proposal>,
+    // Code hashes proposed for an upgrade and the id of their proposal
+    upgrades: LookupMap<CryptoHash, String>,
+    // Councils proposed for removal, by proposal id
+    removals: LookupMap<String, String>,
+    // Councils seated by the Energy Fund, which it can unseat when their shares fall
+    fund_councils: LookupSet<String>,
+    state_version: u8,
 }
 
 // Define the default, which automatically initializes the contract
@@ -148,6 +165,23 @@ fn assert_self() {
     );
 }
 
+impl EnergyDao {
+    // Replace a proposal whith a new one, not exposed so votes and results can't be forged
+    pub(crate) fn replace_proposal(&mut self, proposal: Proposal){
+        let mut index =0;
+        for i in 0..self.proposals.len(){
+            match self.proposals.get(i){
+                Some(p) => if p.id==proposal.id {
+                    index=i;
+                },
+                None => panic!("There is no PROPOSALs"),
+            }
+        }
+        self.proposals.swap_remove(index);
+        self.proposals.insert(index, proposal);
+    }
+}
+
 // Implement the contract structure
 // To be implemented in the front end
 #[near_bindgen]
@@ -158,12 +192,16 @@ impl EnergyDao {
         Self {
             members : UnorderedMap::new(b"m"),
             proposals : Vec::new(),
+            upgrades: LookupMap::new(b"u"),
+            removals: LookupMap::new(b"x"),
+            fund_councils: LookupSet::new(b"n"),
+            state_version: STATE_VERSION,
         }
     }
 
     pub fn init(&mut self) {
         assert_self();
-        self.members.insert(&env::current_account_id().to_string(), &0);
+        self.members.insert(&env::current_account_id().to_string(), &Role::Council);
     }
 
     // delete all members 
@@ -174,47 +212,33 @@ impl EnergyDao {
 
     // get all councils
     pub fn get_councils(&self) -> Vec<String> {
-        let mut vec = Vec::new();
-        for i in self.members.keys() {
-            if self.members.get(&i).unwrap() == 0 {
-                vec.push(i);
-            }
-        }
-        vec
+        self.members
+            .iter()
+            .filter(|(_, role)| *role == Role::Council)
+            .map(|(account, _)| account)
+            .collect()
     }
 
     // get all communities
     pub fn get_communities(&self) -> Vec<String> {
-        let mut vec = Vec::new();
-        for i in self.members.keys() {
-            if self.members.get(&i).unwrap() == 1 {
-                vec.push(i);
-            }
-        }
-        vec
+        self.members
+            .iter()
+            .filter(|(_, role)| *role == Role::Community)
+            .map(|(account, _)| account)
+            .collect()
+    }
+
+    // get the role of a member
+    pub fn get_role(&self, account:String) -> Option<Role> {
+        self.members.get(&account)
     }
 
     pub fn check_member(&self, account:String) -> bool {
-        let mut result=false;
-        for i in 0..self.members.keys_as_vector().len() {
-            if self.members.keys_as_vector().get(i).unwrap() == account {
-                result = true;
-                break;
-            } 
-        }
-        result
+        self.members.get(&account).is_some()
     }
 
     pub fn check_council (&self, account:String) -> bool {
-        if self.check_member(account.clone()) == false {
-            return false ;
-        }else {
-            if self.members.get(&account).unwrap() == 0 {
-                return true;
-            }else {
-                return false;
-            }
-        }
+        self.members.get(&account) == Some(Role::Council)
     }
 
     // Create a new proposal 
@@ -234,6 +258,11 @@ impl EnergyDao {
             true,
             "Proposals can be created only by the councils"
         );
+        // upgrades and removals look their proposal up by id, a second one would shadow it
+        assert!(
+            self.proposals.iter().all(|p| p.id != id),
+            "A proposal with this id already exists"
+        );
         let proposal=Proposal{
             id:id,
             proposal_type:proposal_type,
@@ -250,24 +279,17 @@ impl EnergyDao {
             list_voters:Vec::new(),
             votes:Vec::new()
         };
+        DaoEvent::ProposalCreate(&[ProposalCreateData {
+            id: &proposal.id,
+            proposal_type: proposal.proposal_type,
+            proposal_name: &proposal.proposal_name,
+            creator: &proposal.proposal_creator,
+            amount: U128(proposal.amount),
+        }])
+        .emit();
         self.proposals.push(proposal);
     }
 
-    // Replace a proposal whith a new one 
-    pub fn replace_proposal(&mut self, proposal: Proposal){
-        let mut index =0;
-        for i in 0..self.proposals.len(){
-            match self.proposals.get(i){
-                Some(p) => if p.id==proposal.id {
-                    index=i;
-                },
-                None => panic!("There is no PROPOSALs"),
-            }
-        }
-        self.proposals.swap_remove(index);
-        self.proposals.insert(index, proposal);
-    }
-
     // Get all proposals 
     pub fn get_proposals(&self) -> Vec<Proposal>{
         self.proposals.clone()
@@ -301,6 +323,12 @@ impl EnergyDao {
             );
             let proposal =self.get_specific_proposal(id.clone()).create_vote(vote);
             self.replace_proposal(proposal);
+            DaoEvent::Vote(&[VoteData {
+                proposal_id: &id,
+                voter: env::signer_account_id().as_str(),
+                vote,
+            }])
+            .emit();
         }else {
             panic!("Proposal has been expired");
         }
@@ -314,12 +342,17 @@ impl EnergyDao {
             true,
             "To add a council you must be one of the councils"
         );
-        self.members.insert(&account, &0);
+        self.members.insert(&account, &Role::Council);
     }
 
-    // add community
+    // add community, only the councils can let a new account in
     pub fn add_community (&mut self,account:String) {
-            self.members.insert(&account, &1); 
+        assert!(
+            self.check_council(env::signer_account_id().to_string()),
+            "To add a community member you must be one of the councils"
+        );
+        assert!(!self.check_member(account.clone()), "This account is already a member");
+        self.members.insert(&account, &Role::Community);
     }
 
     // check the proposal and return a message
@@ -347,10 +380,24 @@ impl EnergyDao {
 }
 #[cfg(test)]
 mod tests {
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
     use super::*;
 
+    // context where the dao calls itself
+    fn owner_context() -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("energydao.testnet".parse().unwrap())
+            .predecessor_account_id("energydao.testnet".parse().unwrap())
+            .signer_account_id("energydao.testnet".parse().unwrap());
+        builder
+    }
+
     #[test]
     fn test_init(){
+        testing_env!(owner_context().build());
         let mut contract = EnergyDao::new();
         contract.init();
         assert_eq!(contract.check_member(env::current_account_id().to_string()), true);
@@ -358,26 +405,30 @@ mod tests {
 
     #[test]
     fn test_delete_all(){
+        testing_env!(owner_context().build());
         let mut contract = EnergyDao::new();
         contract.init();
         contract.delete_all();
-        assert_eq!(&members.len(), 0);
+        assert_eq!(contract.members.len(), 0);
     }
 
     #[test]
     fn test_create_proposal(){
+        testing_env!(owner_context().build());
         let mut contract = EnergyDao::new();
         contract.init();
         contract.create_proposal("azerty".to_string(),1,".".to_string(),".".to_string(),123,1,1,1);
-        assert!(get_specific_proposal("azerty".to_string()).is_some());
+        assert_eq!(contract.get_specific_proposal("azerty".to_string()).id, "azerty");
     }
 
     #[test]
     fn test_replace_proposal(){
+        testing_env!(owner_context().build());
         let mut contract = EnergyDao::new();
+        contract.init();
         contract.create_proposal("azerty".to_string(),1,".".to_string(),".".to_string(),123,1,1,1);
-        let mut proposal = contract::Proposal{
-            id:"azerty".to_string,
+        let proposal = Proposal{
+            id:"azerty".to_string(),
             proposal_type:0,
             proposal_name: ".".to_string(),
             description: ".".to_string(),
@@ -392,32 +443,276 @@ mod tests {
             list_voters:Vec::new(),
             votes:Vec::new(),
         };
-        contract.replace_proposal(proposal);
+        contract.replace_proposal(proposal.clone());
         assert_eq!(contract.get_specific_proposal("azerty".to_string()), proposal);
     }
 
     #[test]
     fn test_add_vote(){
+        testing_env!(owner_context().build());
         let mut contract = EnergyDao::new();
+        contract.init();
         contract.create_proposal("azerty".to_string(),1,".".to_string(),".".to_string(),123,1,1,1);
         let proposal = contract.get_specific_proposal("azerty".to_string());
-        contract.vote(&proposal.id, 1);
-        assert_eq!(&proposal.votes.len(), 1);
+        contract.add_vote(proposal.id.clone(), 1);
+        assert_eq!(contract.get_specific_proposal(proposal.id).votes.len(), 1);
     }
 
     #[test]
     fn test_add_council(){
+        testing_env!(owner_context().build());
         let mut contract = EnergyDao::new();
         contract.init();
-        contract.add_council("thamerdridi.testnet".to_string);
-        assert!(contract.check_council("thamerdridi.testnet".to_string());)
+        contract.add_council("thamerdridi.testnet".to_string());
+        assert!(contract.check_council("thamerdridi.testnet".to_string()));
     }
 
     #[test]
     fn test_add_community(){
+        testing_env!(owner_context().build());
+        let mut contract = EnergyDao::new();
+        contract.init();
+        contract.add_community("thamerdridi.testnet".to_string());
+        assert!(contract.check_member("thamerdridi.testnet".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "To add a community member you must be one of the councils")]
+    fn test_add_community_not_council(){
+        testing_env!(owner_context().build());
+        let mut contract = EnergyDao::new();
+        contract.init();
+        testing_env!(owner_context().signer_account_id("sybil.testnet".parse().unwrap()).build());
+        contract.add_community("sybil.testnet".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "This account is already a member")]
+    fn test_add_community_twice(){
+        testing_env!(owner_context().build());
         let mut contract = EnergyDao::new();
         contract.init();
-        contract.add_community("thamerdridi.testnet".to_string);
-        assert!(contract.check_member("thamerdridi.testnet".to_string));
+        contract.add_community("thamerdridi.testnet".to_string());
+        contract.add_community("thamerdridi.testnet".to_string());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_migrate_from_v0(){
+        testing_env!(owner_context().build());
+        let mut members = UnorderedMap::new(b"m");
+        members.insert(&"thamerdridi.testnet".to_string(), &Role::Council);
+        let mut proposal = Proposal::new();
+        proposal.id = "azerty".to_string();
+        env::state_write(&EnergyDaoV0 { members, proposals: vec![proposal] });
+
+        let contract = EnergyDao::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert!(contract.check_council("thamerdridi.testnet".to_string()));
+        assert_eq!(contract.get_specific_proposal("azerty".to_string()).id, "azerty");
+    }
+
+    //testing the migration of a deployed state whose last bytes are a vote timestamp
+    #[test]
+    fn test_migrate_from_v0_with_votes(){
+        testing_env!(owner_context().block_timestamp(1_700_000_000_000_000_000).build());
+        let mut members = UnorderedMap::new(b"m");
+        members.insert(&"thamerdridi.testnet".to_string(), &Role::Council);
+        let mut proposal = Proposal::new();
+        proposal.id = "azerty".to_string();
+        proposal.create_vote(1);
+        env::state_write(&EnergyDaoV0 { members, proposals: vec![proposal] });
+
+        let contract = EnergyDao::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert!(contract.check_council("thamerdridi.testnet".to_string()));
+        assert_eq!(contract.get_specific_proposal("azerty".to_string()).votes_for, 1);
+    }
+
+    #[test]
+    fn test_migrate_current(){
+        testing_env!(owner_context().build());
+        let mut contract = EnergyDao::new();
+        contract.init();
+        env::state_write(&contract);
+
+        let contract = EnergyDao::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert!(contract.check_council("energydao.testnet".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by owner")]
+    fn test_migrate_not_owner(){
+        testing_env!(owner_context().predecessor_account_id("thamerdridi.testnet".parse().unwrap()).build());
+        EnergyDao::migrate();
+    }
+
+    // proposal to deploy `code`, voted for by the owner council
+    fn contract_with_upgrade_proposal(code: &[u8]) -> EnergyDao {
+        testing_env!(owner_context().build());
+        let mut contract = EnergyDao::new();
+        contract.init();
+        contract.create_upgrade_proposal("upgrade".to_string(), "v2".to_string(), env::sha256_array(code).into(), 0, 0, 1);
+        contract.add_vote("upgrade".to_string(), 1);
+        contract
+    }
+
+    #[test]
+    fn test_upgrade(){
+        let mut contract = contract_with_upgrade_proposal(b"new code");
+        let mut context = owner_context().predecessor_account_id("thamerdridi.testnet".parse().unwrap()).block_timestamp(60000000001).build();
+        context.input = b"new code".to_vec();
+        testing_env!(context);
+        contract.upgrade();
+        assert_eq!(contract.get_upgrade_proposal(env::sha256_array(b"new code").into()), None);
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains("\"event\":\"upgrade\"")));
+    }
+
+    #[test]
+    #[should_panic(expected = "The upgrade proposal hasn't passed")]
+    fn test_upgrade_before_end(){
+        let mut contract = contract_with_upgrade_proposal(b"new code");
+        let mut context = owner_context().build();
+        context.input = b"new code".to_vec();
+        testing_env!(context);
+        contract.upgrade();
+    }
+
+    //testing that a refused upgrade can't be shadowed by a passing proposal
+    #[test]
+    #[should_panic(expected = "A proposal with this id already exists")]
+    fn test_upgrade_shadowed_by_another_proposal(){
+        testing_env!(owner_context().build());
+        let mut contract = EnergyDao::new();
+        contract.init();
+        contract.create_upgrade_proposal("upgrade".to_string(), "v2".to_string(), env::sha256_array(b"new code").into(), 0, 0, 1);
+        contract.add_vote("upgrade".to_string(), 0);
+        contract.create_proposal("upgrade".to_string(),3,".".to_string(),".".to_string(),0,0,0,1);
+    }
+
+    //testing that community votes alone can't deploy new code
+    #[test]
+    #[should_panic(expected = "The upgrade needs the votes of more than half of the councils")]
+    fn test_upgrade_without_council_majority(){
+        let mut contract = contract_with_members();
+        contract.create_upgrade_proposal("upgrade".to_string(), "v2".to_string(), env::sha256_array(b"new code").into(), 0, 0, 1);
+        testing_env!(owner_context().signer_account_id("issameths.testnet".parse().unwrap()).build());
+        contract.add_vote("upgrade".to_string(), 1);
+        let mut context = owner_context().block_timestamp(60000000001).build();
+        context.input = b"new code".to_vec();
+        testing_env!(context);
+        contract.upgrade();
+    }
+
+    // dao with the owner and thamer as councils and issam as a community member
+    fn contract_with_members() -> EnergyDao {
+        testing_env!(owner_context().build());
+        let mut contract = EnergyDao::new();
+        contract.init();
+        contract.add_council("thamerdridi.testnet".to_string());
+        contract.add_community("issameths.testnet".to_string());
+        contract
+    }
+
+    #[test]
+    fn test_remove_member(){
+        let mut contract = contract_with_members();
+        contract.remove_member("issameths.testnet".to_string());
+        assert!(!contract.check_member("issameths.testnet".to_string()));
+        assert_eq!(contract.get_communities().len(), 0);
+    }
+
+    //testing the seat given by the Energy Fund to an investor
+    #[test]
+    fn test_add_fund_council(){
+        let mut contract = contract_with_members();
+        testing_env!(owner_context().predecessor_account_id(ENERGY_FUND.parse().unwrap()).build());
+        contract.add_fund_council("alach.testnet".to_string());
+        assert!(contract.check_council("alach.testnet".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "You are not authorized to execute this function")]
+    fn test_add_fund_council_not_fund(){
+        let mut contract = contract_with_members();
+        testing_env!(owner_context().predecessor_account_id("alach.testnet".parse().unwrap()).build());
+        contract.add_fund_council("alach.testnet".to_string());
+    }
+
+    //testing that the fund can't turn a member into a fund seat
+    #[test]
+    #[should_panic(expected = "This account is already a member")]
+    fn test_add_fund_council_member(){
+        let mut contract = contract_with_members();
+        testing_env!(owner_context().predecessor_account_id(ENERGY_FUND.parse().unwrap()).build());
+        contract.add_fund_council("thamerdridi.testnet".to_string());
+    }
+
+    //testing that the fund unseats only the councils it seated
+    #[test]
+    fn test_remove_fund_council(){
+        let mut contract = contract_with_members();
+        testing_env!(owner_context().predecessor_account_id(ENERGY_FUND.parse().unwrap()).build());
+        contract.add_fund_council("investor.testnet".to_string());
+        assert!(contract.check_council("investor.testnet".to_string()));
+        contract.remove_fund_council("investor.testnet".to_string());
+        assert!(!contract.check_member("investor.testnet".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "This account wasn't seated by the Energy Fund")]
+    fn test_remove_fund_council_elected(){
+        let mut contract = contract_with_members();
+        testing_env!(owner_context().predecessor_account_id(ENERGY_FUND.parse().unwrap()).build());
+        contract.remove_fund_council("thamerdridi.testnet".to_string());
+    }
+
+    //testing that a council can't be demoted by joining the community
+    #[test]
+    #[should_panic(expected = "This account is already a member")]
+    fn test_add_community_council(){
+        let mut contract = contract_with_members();
+        contract.add_community("thamerdridi.testnet".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Councils can only be removed through a proposal")]
+    fn test_remove_council_directly(){
+        let mut contract = contract_with_members();
+        contract.remove_member("thamerdridi.testnet".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "The DAO must keep at least 1 council(s)")]
+    fn test_leave_dao(){
+        let mut contract = contract_with_members();
+        testing_env!(owner_context().predecessor_account_id("thamerdridi.testnet".parse().unwrap()).build());
+        contract.leave_dao();
+        assert_eq!(contract.get_councils(), vec!["energydao.testnet".to_string()]);
+        testing_env!(owner_context().build());
+        contract.leave_dao();
+    }
+
+    #[test]
+    fn test_removal_proposal(){
+        let mut contract = contract_with_members();
+        contract.create_removal_proposal("remove".to_string(), "inactive".to_string(), "thamerdridi.testnet".to_string(), 0, 0, 1);
+        contract.add_vote("remove".to_string(), 1);
+        testing_env!(owner_context().predecessor_account_id("issameths.testnet".parse().unwrap()).block_timestamp(60000000001).build());
+        contract.execute_removal("remove".to_string());
+        assert!(!contract.check_member("thamerdridi.testnet".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "A proposal with this id already exists")]
+    fn test_removal_shadowed_by_another_proposal(){
+        let mut contract = contract_with_members();
+        contract.create_removal_proposal("remove".to_string(), "inactive".to_string(), "thamerdridi.testnet".to_string(), 0, 0, 1);
+        contract.add_vote("remove".to_string(), 0);
+        contract.create_proposal("remove".to_string(),1,".".to_string(),".".to_string(),123,0,0,1);
+        contract.add_vote("remove".to_string(), 1);
+        testing_env!(owner_context().predecessor_account_id("issameths.testnet".parse().unwrap()).block_timestamp(60000000001).build());
+        contract.execute_removal("remove".to_string());
+    }
+}
+
@@ -0,0 +1,146 @@
+//! Roles of the members and how they leave the DAO.
+//!
+//! Councils can remove community members directly, a council is only removed by a passed
+//! proposal or by leaving on its own. Councils seated by the Energy Fund are also removed by the
+//! fund when their shares fall under its threshold. The DAO always keeps at least
+//! `MIN_COUNCIL_SIZE` councils.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::env;
+use near_sdk::near_bindgen;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{DaoEvent, Event, MemberRemoveData, ProposalCreateData};
+use crate::{EnergyDao, EnergyDaoExt, Proposal};
+
+// Proposal type removing a council
+pub const REMOVE_COUNCIL_PROPOSAL: u8 = 4;
+
+// Number of councils the DAO can't go under
+pub const MIN_COUNCIL_SIZE: usize = 1;
+
+// Energy Fund contract, which seats its investors above its council threshold
+pub const ENERGY_FUND: &str = "energyfund.testnet";
+
+// Role of a member, stored as 0 for councils and 1 for communities
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Council,
+    Community,
+}
+
+impl EnergyDao {
+    // Function to remove a member, keeping the minimum number of councils
+    fn internal_remove_member(&mut self, account: &String) {
+        let role = self.members.get(account).expect("This account isn't a member");
+        if role == Role::Council {
+            assert!(
+                self.get_councils().len() > MIN_COUNCIL_SIZE,
+                "The DAO must keep at least {} council(s)",
+                MIN_COUNCIL_SIZE
+            );
+        }
+        self.members.remove(account);
+        self.fund_councils.remove(account);
+        DaoEvent::MemberRemove(&[MemberRemoveData { account_id: account, role }]).emit();
+    }
+}
+
+#[near_bindgen]
+impl EnergyDao {
+    // Function to remove a community member, councils are removed through a proposal
+    pub fn remove_member(&mut self, account: String) {
+        assert!(
+            self.check_council(env::signer_account_id().to_string()),
+            "Members can be removed only by the councils"
+        );
+        assert!(
+            !self.check_council(account.clone()),
+            "Councils can only be removed through a proposal"
+        );
+        self.internal_remove_member(&account);
+    }
+
+    // Function for the Energy Fund to seat one of its investors as a council
+    pub fn add_fund_council(&mut self, account: String) {
+        assert_eq!(
+            env::predecessor_account_id().to_string(),
+            ENERGY_FUND.to_string(),
+            "You are not authorized to execute this function"
+        );
+        assert!(!self.check_member(account.clone()), "This account is already a member");
+        self.members.insert(&account, &Role::Council);
+        self.fund_councils.insert(&account);
+    }
+
+    // Function for the Energy Fund to unseat a council it seated, once its shares fall under the threshold
+    pub fn remove_fund_council(&mut self, account: String) {
+        assert_eq!(
+            env::predecessor_account_id().to_string(),
+            ENERGY_FUND.to_string(),
+            "You are not authorized to execute this function"
+        );
+        assert!(self.fund_councils.contains(&account), "This account wasn't seated by the Energy Fund");
+        self.internal_remove_member(&account);
+    }
+
+    // Function to leave the DAO
+    pub fn leave_dao(&mut self) {
+        self.internal_remove_member(&env::predecessor_account_id().to_string());
+    }
+
+    // Function to propose the removal of a council
+    pub fn create_removal_proposal(
+        &mut self,
+        id: String,
+        description: String,
+        account: String,
+        duration_days: u64,
+        duration_hours: u64,
+        duration_min: u64,
+    ) {
+        assert!(
+            self.check_council(env::signer_account_id().to_string()),
+            "Proposals can be created only by the councils"
+        );
+        assert!(self.check_council(account.clone()), "This account isn't a council");
+        assert!(
+            self.get_proposals().iter().all(|p| p.id != id),
+            "A proposal with this id already exists"
+        );
+        let mut proposal = Proposal::new();
+        proposal.id = id;
+        proposal.proposal_type = REMOVE_COUNCIL_PROPOSAL;
+        proposal.proposal_name = "Remove council".to_string();
+        proposal.description = description;
+        proposal.proposal_creator = env::signer_account_id().to_string();
+        proposal.time_of_creation = env::block_timestamp();
+        proposal.duration_days = duration_days;
+        proposal.duration_hours = duration_hours;
+        proposal.duration_min = duration_min;
+        DaoEvent::ProposalCreate(&[ProposalCreateData {
+            id: &proposal.id,
+            proposal_type: proposal.proposal_type,
+            proposal_name: &proposal.proposal_name,
+            creator: &proposal.proposal_creator,
+            amount: 0.into(),
+        }])
+        .emit();
+        self.removals.insert(&proposal.id, &account);
+        self.proposals.push(proposal);
+    }
+
+    // Function to remove the council of a passed removal proposal, callable by anyone
+    pub fn execute_removal(&mut self, id: String) {
+        let account = self.removals.get(&id).expect("There is no removal with this id");
+        let proposal = self.get_specific_proposal(id.clone());
+        assert!(
+            proposal.proposal_type == REMOVE_COUNCIL_PROPOSAL,
+            "This proposal isn't the removal proposal"
+        );
+        assert!(proposal.check_proposal(), "The removal proposal hasn't passed");
+        self.removals.remove(&id);
+        self.internal_remove_member(&account);
+    }
+}
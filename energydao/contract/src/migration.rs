@@ -0,0 +1,73 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap};
+use near_sdk::{env, near_bindgen};
+
+use crate::{assert_self, EnergyDao, EnergyDaoExt, Proposal, Role};
+
+// Version of the layout written by this code, bumped with every change of a deployed layout
+pub const STATE_VERSION: u8 = 1;
+
+// Layout deployed before the state was versioned
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct EnergyDaoV0 {
+    // Roles were stored as the u8 they replaced, 0 for councils and 1 for communities
+    pub members: UnorderedMap<String, Role>,
+    pub proposals: Vec<Proposal>,
+}
+
+impl From<EnergyDaoV0> for EnergyDao {
+    fn from(state: EnergyDaoV0) -> Self {
+        EnergyDao {
+            members: state.members,
+            proposals: state.proposals,
+            upgrades: LookupMap::new(b"u"),
+            removals: LookupMap::new(b"x"),
+            fund_councils: LookupSet::new(b"n"),
+            state_version: STATE_VERSION,
+        }
+    }
+}
+
+// Layouts the state can be stored with
+pub enum VersionedEnergyDao {
+    V0(EnergyDaoV0),
+    Current(EnergyDao),
+}
+
+impl VersionedEnergyDao {
+    // Function to read the stored state with the layout named by its last byte
+    // The versioned layout ends with `state_version`, the unversioned one with the last byte of a
+    // vote timestamp or of the length of an empty list, never a version
+    pub fn read() -> Self {
+        let bytes = env::storage_read(b"STATE").expect("The contract isn't initialized");
+        let state = |bytes: &[u8]| -> Result<Self, std::io::Error> {
+            Ok(match bytes.last().copied() {
+                Some(STATE_VERSION) => VersionedEnergyDao::Current(EnergyDao::try_from_slice(bytes)?),
+                _ => VersionedEnergyDao::V0(EnergyDaoV0::try_from_slice(bytes)?),
+            })
+        };
+        state(&bytes).expect("The state doesn't match its version")
+    }
+
+    // Function to convert the stored layout to the current one
+    pub fn into_current(self) -> EnergyDao {
+        match self {
+            VersionedEnergyDao::V0(state) => state.into(),
+            VersionedEnergyDao::Current(state) => state,
+        }
+    }
+}
+
+#[near_bindgen]
+impl EnergyDao {
+    // Function to upgrade the stored state after deploying a new version of the code
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        assert_self();
+        VersionedEnergyDao::read().into_current()
+    }
+
+    pub fn get_state_version(&self) -> u8 {
+        self.state_version
+    }
+}
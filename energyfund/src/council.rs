@@ -0,0 +1,72 @@
+//! Council seats of the investors.
+//!
+//! An investor whose shares are worth at least the council threshold is seated as a council in
+//! the Treasury DAO and the Energy DAO. A seat is given once; if a DAO refuses it, the seat is
+//! requested again on the next investment. A redemption that takes the shares under the threshold
+//! gives the seat back, the DAOs then remove the account unless it already left them.
+
+use near_sdk::{env, near_bindgen, AccountId, Gas, PromiseError};
+
+use crate::events::{CouncilReleaseData, CouncilSeatData, Event, FundEvent};
+use crate::{ext_dao, EnergyFund, EnergyFundExt, TGAS};
+
+// DAOs where the investors get a seat
+pub const COUNCIL_DAOS: [&str; 2] = ["treasurydao.testnet", "energydao.testnet"];
+
+impl EnergyFund {
+    // Function to seat `account` in the DAOs once its shares reach the threshold
+    pub(crate) fn internal_check_council(&mut self, account: &AccountId) {
+        if self.councils.contains(account) || self.internal_share_value(account) < self.council_threshold {
+            return;
+        }
+        self.councils.insert(account);
+        for dao in COUNCIL_DAOS {
+            FundEvent::CouncilSeat(&[CouncilSeatData {
+                account_id: account.as_str(),
+                dao_id: dao,
+            }])
+            .emit();
+            ext_dao::ext(dao.parse().unwrap())
+                .with_static_gas(Gas(5 * TGAS))
+                .add_fund_council(account.to_string())
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(Gas(5 * TGAS))
+                        .council_callback(account.clone()),
+                );
+        }
+    }
+
+    // Function to unseat `account` from the DAOs once its shares fall under the threshold
+    pub(crate) fn internal_release_council(&mut self, account: &AccountId) {
+        if !self.councils.contains(account) || self.internal_share_value(account) >= self.council_threshold {
+            return;
+        }
+        self.councils.remove(account);
+        for dao in COUNCIL_DAOS {
+            FundEvent::CouncilRelease(&[CouncilReleaseData {
+                account_id: account.as_str(),
+                dao_id: dao,
+            }])
+            .emit();
+            ext_dao::ext(dao.parse().unwrap())
+                .with_static_gas(Gas(5 * TGAS))
+                .remove_fund_council(account.to_string());
+        }
+    }
+}
+
+#[near_bindgen]
+impl EnergyFund {
+    pub fn is_council(&self, account: AccountId) -> bool {
+        self.councils.contains(&account)
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn council_callback(&mut self, account: AccountId, #[callback_result] call_result: Result<(), PromiseError>) {
+        if call_result.is_err() {
+            self.councils.remove(&account);
+            env::log_str("A DAO refused the seat, it will be requested again on the next investment");
+        }
+    }
+}
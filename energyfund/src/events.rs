@@ -0,0 +1,76 @@
+// NEP-297 events of the Energy Fund, logged with the `EVENT_JSON:` prefix.
+// NEAR amounts and fund values are in yoctoNEAR, LTS amounts in the token smallest unit.
+
+pub use lightency_events::Event;
+use near_sdk::json_types::U128;
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+pub struct InvestData<'a> {
+    pub account_id: &'a str,
+    pub token: &'a str,
+    pub amount: U128,
+    pub value: U128,
+    pub shares: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<&'a str>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct RedeemData<'a> {
+    pub account_id: &'a str,
+    pub shares: U128,
+    pub near_amount: U128,
+    pub lts_amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CouncilSeatData<'a> {
+    pub account_id: &'a str,
+    pub dao_id: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CouncilReleaseData<'a> {
+    pub account_id: &'a str,
+    pub dao_id: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReturnsDepositData<'a> {
+    pub sender_id: &'a str,
+    pub token: &'a str,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReturnsClaimData<'a> {
+    pub account_id: &'a str,
+    pub near_amount: U128,
+    pub lts_amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum FundEvent<'a> {
+    Invest(&'a [InvestData<'a>]),
+    Redeem(&'a [RedeemData<'a>]),
+    CouncilSeat(&'a [CouncilSeatData<'a>]),
+    CouncilRelease(&'a [CouncilReleaseData<'a>]),
+    ReturnsDeposit(&'a [ReturnsDepositData<'a>]),
+    ReturnsClaim(&'a [ReturnsClaimData<'a>]),
+}
+
+impl Event for FundEvent<'_> {
+    // Each event keeps its own schema version
+    fn version(&self) -> &'static str {
+        match self {
+            FundEvent::Invest(_) => "1.0.0",
+            FundEvent::Redeem(_) => "1.0.0",
+            FundEvent::CouncilSeat(_) => "1.0.0",
+            FundEvent::CouncilRelease(_) => "1.0.0",
+            FundEvent::ReturnsDeposit(_) => "1.0.0",
+            FundEvent::ReturnsClaim(_) => "1.0.0",
+        }
+    }
+}
@@ -0,0 +1,516 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LookupMap, LookupSet, Vector};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, ext_contract, near_bindgen, AccountId, Gas, Promise, PromiseError, PromiseOrValue};
+use serde::{Deserialize, Serialize};
+
+mod council;
+mod events;
+mod returns;
+use events::{Event, FundEvent, InvestData, RedeemData};
+pub use council::COUNCIL_DAOS;
+pub use returns::{Claim, Yield, RETURNS_MSG};
+
+pub const TGAS: u64 = 1_000_000_000_000;
+
+pub const NEAR_TOKEN: &str = "NEAR";
+pub const LTS_TOKEN: &str = "light-token.testnet";
+
+// 1 LTS expressed in the token smallest unit
+pub const ONE_LTS: u128 = 100_000_000;
+
+// Smallest investment in yoctoNEAR (0.1 NEAR), it covers the storage the fund pays for the
+// investor, its yield and the history of its claims
+pub const MIN_INVESTMENT: u128 = 100_000_000_000_000_000_000_000;
+
+// Deposit attached to register an investor on LTS, the token refunds what isn't used
+pub const STORAGE_DEPOSIT: u128 = 12_500_000_000_000_000_000_000;
+
+//Define the LTS token contract
+#[ext_contract(ext_ft)]
+pub trait FungibleToken {
+    fn ft_transfer (&mut self, receiver_id:AccountId, amount:U128, memo:Option<String>);
+    fn storage_deposit (&mut self, account_id:Option<AccountId>, registration_only:Option<bool>);
+}
+
+//Define the DAOs seating the investors
+#[ext_contract(ext_dao)]
+pub trait Dao {
+    fn add_fund_council (&mut self, account:String);
+    fn remove_fund_council (&mut self, account:String);
+}
+
+// INVESTORS
+// Investor structure
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Investor {
+    pub shares: U128,
+    // The shares can't be redeemed before this timestamp in nanoseconds
+    pub locked_until: U64,
+}
+
+// Define the contract structure
+// The fund holds NEAR and LTS, its value (NAV) is counted in yoctoNEAR with the LTS price set by the owner
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct EnergyFund {
+    investors: LookupMap<AccountId, Investor>,
+    total_shares: u128,
+    near_balance: u128,
+    lts_balance: u128,
+    // yoctoNEAR per LTS
+    lts_price: u128,
+    // Value of the shares in yoctoNEAR that gives a council seat
+    council_threshold: u128,
+    // Lock-up of the shares after each investment in nanoseconds
+    lockup: u64,
+    // Investors seated in the DAOs
+    councils: LookupSet<AccountId>,
+    // LTS of redemptions whose transfer failed, claimable again
+    owed_lts: LookupMap<AccountId, u128>,
+    // Returns distributed per share since the start, scaled by ACC_PRECISION
+    acc_near_per_share: u128,
+    acc_lts_per_share: u128,
+    distributed_near: u128,
+    distributed_lts: u128,
+    yields: LookupMap<AccountId, Yield>,
+    claims: LookupMap<AccountId, Vector<Claim>>,
+}
+
+// Define the default, which automatically initializes the contract
+impl Default for EnergyFund {
+    fn default() -> Self {
+        panic!("Contract is not initialized yet")
+    }
+}
+
+// 256 bits integer to multiply two amounts before dividing them
+mod uint256 {
+    #![allow(clippy::all)]
+    uint::construct_uint! {
+        pub struct U256(4);
+    }
+}
+use uint256::U256;
+
+// Function to compute a * b / c without overflowing
+fn mul_div(a: u128, b: u128, c: u128) -> u128 {
+    (U256::from(a) * U256::from(b) / U256::from(c)).as_u128()
+}
+
+// Make sure that the caller of the function is the owner
+fn assert_self() {
+    assert_eq!(
+        env::current_account_id(),
+        env::predecessor_account_id(),
+        "Can only be called by owner"
+    );
+}
+
+impl EnergyFund {
+    // Function to get the value of the fund in yoctoNEAR
+    fn internal_nav(&self) -> u128 {
+        self.near_balance + mul_div(self.lts_balance, self.lts_price, ONE_LTS)
+    }
+
+    pub(crate) fn internal_share_value(&self, account: &AccountId) -> u128 {
+        match self.investors.get(account) {
+            Some(investor) if self.total_shares > 0 => mul_div(investor.shares.0, self.internal_nav(), self.total_shares),
+            _ => 0,
+        }
+    }
+
+    // Function to give `account` the shares of an investment worth `value` yoctoNEAR
+    // The first investment sets 1 share per yoctoNEAR, the next ones get shares at the current NAV
+    fn internal_invest(&mut self, account: AccountId, token: &str, amount: u128, value: u128, memo: Option<&str>) {
+        assert!(value >= MIN_INVESTMENT, "The investment must be worth at least {} yoctoNEAR", MIN_INVESTMENT);
+        self.internal_settle(&account);
+        let nav = self.internal_nav();
+        let shares = if self.total_shares == 0 || nav == 0 {
+            value
+        } else {
+            mul_div(value, self.total_shares, nav)
+        };
+        assert!(shares > 0, "The investment is too small to get a share");
+        if token == NEAR_TOKEN {
+            self.near_balance += amount;
+        } else {
+            self.lts_balance += amount;
+        }
+        self.total_shares += shares;
+        let mut investor = self.investors.get(&account).unwrap_or(Investor {
+            shares: U128(0),
+            locked_until: U64(0),
+        });
+        investor.shares = U128(investor.shares.0 + shares);
+        investor.locked_until = U64(env::block_timestamp() + self.lockup);
+        self.investors.insert(&account, &investor);
+        FundEvent::Invest(&[InvestData {
+            account_id: account.as_str(),
+            token,
+            amount: U128(amount),
+            value: U128(value),
+            shares: U128(shares),
+            memo,
+        }])
+        .emit();
+        self.internal_check_council(&account);
+    }
+
+    // Function to send LTS to an investor, registering it on the token first
+    fn internal_send_lts(&self, account: AccountId, amount: u128) -> Promise {
+        let token: AccountId = LTS_TOKEN.parse().unwrap();
+        ext_ft::ext(token.clone())
+            .with_static_gas(Gas(5 * TGAS))
+            .with_attached_deposit(STORAGE_DEPOSIT)
+            .storage_deposit(Some(account.clone()), Some(true))
+            .then(
+                ext_ft::ext(token)
+                    .with_static_gas(Gas(5 * TGAS))
+                    .with_attached_deposit(1)
+                    .ft_transfer(account.clone(), U128(amount), None),
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas(5 * TGAS))
+                    .send_lts_callback(account, U128(amount)),
+            )
+    }
+}
+
+// Implement the contract structure
+#[near_bindgen]
+impl EnergyFund {
+    #[init]
+    pub fn new(lts_price: U128, council_threshold: U128, lockup: U64) -> Self {
+        assert!(env::state_read::<Self>().is_none(), "Already initialized");
+        Self {
+            investors: LookupMap::new(b"i"),
+            total_shares: 0,
+            near_balance: 0,
+            lts_balance: 0,
+            lts_price: lts_price.0,
+            council_threshold: council_threshold.0,
+            lockup: lockup.0,
+            councils: LookupSet::new(b"c"),
+            owed_lts: LookupMap::new(b"o"),
+            acc_near_per_share: 0,
+            acc_lts_per_share: 0,
+            distributed_near: 0,
+            distributed_lts: 0,
+            yields: LookupMap::new(b"y"),
+            claims: LookupMap::new(b"h"),
+        }
+    }
+
+    // Function to invest the attached NEAR
+    #[payable]
+    pub fn invest_near(&mut self) {
+        let amount = env::attached_deposit();
+        self.internal_invest(env::predecessor_account_id(), NEAR_TOKEN, amount, amount, None);
+    }
+
+    // Function receiving the LTS invested with ft_transfer_call, or distributed as returns with the msg "returns"
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        assert_eq!(
+            env::predecessor_account_id().to_string(),
+            LTS_TOKEN.to_string(),
+            "Only NEAR and LTS can be invested"
+        );
+        if msg == RETURNS_MSG {
+            self.internal_distribute(&sender_id, LTS_TOKEN, amount.0);
+            return PromiseOrValue::Value(U128(0));
+        }
+        let value = mul_div(amount.0, self.lts_price, ONE_LTS);
+        self.internal_invest(sender_id, LTS_TOKEN, amount.0, value, Some(msg.as_str()).filter(|msg| !msg.is_empty()));
+        PromiseOrValue::Value(U128(0))
+    }
+
+    // Function to redeem unlocked shares for their part of the NEAR and of the LTS of the fund
+    pub fn redeem(&mut self, shares: U128) {
+        let account = env::predecessor_account_id();
+        let mut investor = self.investors.get(&account).expect("You aren't an investor");
+        assert!(shares.0 > 0 && shares.0 <= investor.shares.0, "You don't have these shares");
+        assert!(
+            env::block_timestamp() >= investor.locked_until.0,
+            "The shares are locked until {}",
+            investor.locked_until.0
+        );
+        self.internal_settle(&account);
+        let near_amount = mul_div(shares.0, self.near_balance, self.total_shares);
+        let lts_amount = mul_div(shares.0, self.lts_balance, self.total_shares);
+        self.near_balance -= near_amount;
+        self.lts_balance -= lts_amount;
+        self.total_shares -= shares.0;
+        investor.shares = U128(investor.shares.0 - shares.0);
+        if investor.shares.0 == 0 {
+            self.investors.remove(&account);
+        } else {
+            self.investors.insert(&account, &investor);
+        }
+        self.internal_release_council(&account);
+        FundEvent::Redeem(&[RedeemData {
+            account_id: account.as_str(),
+            shares,
+            near_amount: U128(near_amount),
+            lts_amount: U128(lts_amount),
+        }])
+        .emit();
+        if near_amount > 0 {
+            Promise::new(account.clone()).transfer(near_amount);
+        }
+        if lts_amount > 0 {
+            self.internal_send_lts(account, lts_amount);
+        }
+    }
+
+    // Function to claim again the LTS of a redemption whose transfer failed
+    pub fn claim_owed_lts(&mut self) -> Promise {
+        let account = env::predecessor_account_id();
+        let amount = self.owed_lts.remove(&account).expect("No LTS is owed to you");
+        self.internal_send_lts(account, amount)
+    }
+
+    // Function to change the LTS price used to value the fund, in yoctoNEAR per LTS
+    // The price isn't read from a market: between two updates the shares are priced with the last
+    // one, so the owner must follow the LTS price closely or investors can buy or redeem at a stale NAV
+    pub fn set_lts_price(&mut self, lts_price: U128) {
+        assert_self();
+        self.lts_price = lts_price.0;
+    }
+
+    pub fn set_council_threshold(&mut self, council_threshold: U128) {
+        assert_self();
+        self.council_threshold = council_threshold.0;
+    }
+
+    pub fn set_lockup(&mut self, lockup: U64) {
+        assert_self();
+        self.lockup = lockup.0;
+    }
+
+    pub fn get_investor(&self, account: AccountId) -> Option<Investor> {
+        self.investors.get(&account)
+    }
+
+    // Function to get the value of the shares of an investor in yoctoNEAR
+    pub fn get_share_value(&self, account: AccountId) -> U128 {
+        U128(self.internal_share_value(&account))
+    }
+
+    pub fn get_nav(&self) -> U128 {
+        U128(self.internal_nav())
+    }
+
+    pub fn get_total_shares(&self) -> U128 {
+        U128(self.total_shares)
+    }
+
+    // Function to get the NEAR and the LTS held by the fund
+    pub fn get_holdings(&self) -> (U128, U128) {
+        (U128(self.near_balance), U128(self.lts_balance))
+    }
+
+    pub fn get_owed_lts(&self, account: AccountId) -> U128 {
+        U128(self.owed_lts.get(&account).unwrap_or(0))
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn send_lts_callback(&mut self, account: AccountId, amount: U128, #[callback_result] call_result: Result<(), PromiseError>) {
+        if call_result.is_err() {
+            let owed = self.owed_lts.get(&account).unwrap_or(0);
+            self.owed_lts.insert(&account, &(owed + amount.0));
+            env::log_str("The LTS transfer failed, claim it again with claim_owed_lts");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::{get_logs, VMContextBuilder};
+    use near_sdk::{testing_env, ONE_NEAR};
+    use super::*;
+
+    // context where the fund calls itself
+    fn owner_context() -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("energyfund.testnet".parse().unwrap())
+            .predecessor_account_id("energyfund.testnet".parse().unwrap())
+            .signer_account_id("energyfund.testnet".parse().unwrap());
+        builder
+    }
+
+    fn investor_context(account: &str, deposit: u128) -> VMContextBuilder {
+        let mut builder = owner_context();
+        builder
+            .predecessor_account_id(account.parse().unwrap())
+            .signer_account_id(account.parse().unwrap())
+            .attached_deposit(deposit);
+        builder
+    }
+
+    // LTS at 0.5 NEAR, council seat from 100 NEAR and a lock-up of 1 minute
+    fn new_fund() -> EnergyFund {
+        testing_env!(owner_context().build());
+        EnergyFund::new(U128(ONE_NEAR / 2), U128(100 * ONE_NEAR), U64(60000000000))
+    }
+
+    //testing that the shares follow the NAV
+    #[test]
+    fn test_invest(){
+        let mut fund = new_fund();
+        testing_env!(investor_context("alach.testnet", 10 * ONE_NEAR).build());
+        fund.invest_near();
+        assert_eq!(fund.get_investor("alach.testnet".parse().unwrap()).unwrap().shares, U128(10 * ONE_NEAR));
+
+        // LTS doubles in price, 20 LTS are now worth 20 NEAR
+        testing_env!(owner_context().predecessor_account_id(LTS_TOKEN.parse().unwrap()).build());
+        fund.ft_on_transfer("oussema.testnet".parse().unwrap(), U128(20 * ONE_LTS), "".to_string());
+        testing_env!(owner_context().build());
+        fund.set_lts_price(U128(ONE_NEAR));
+        assert_eq!(fund.get_nav(), U128(30 * ONE_NEAR));
+
+        testing_env!(investor_context("issameths.testnet", 15 * ONE_NEAR).build());
+        fund.invest_near();
+        // 20 NEAR of shares were worth 30 NEAR, 15 NEAR get 10 NEAR of shares
+        assert_eq!(fund.get_investor("oussema.testnet".parse().unwrap()).unwrap().shares, U128(10 * ONE_NEAR));
+        assert_eq!(fund.get_investor("issameths.testnet".parse().unwrap()).unwrap().shares, U128(10 * ONE_NEAR));
+        assert_eq!(fund.get_share_value("issameths.testnet".parse().unwrap()), U128(15 * ONE_NEAR));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only NEAR and LTS can be invested")]
+    fn test_invest_other_token(){
+        let mut fund = new_fund();
+        testing_env!(owner_context().predecessor_account_id("usdc.testnet".parse().unwrap()).build());
+        fund.ft_on_transfer("alach.testnet".parse().unwrap(), U128(100), "".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "The investment must be worth at least 100000000000000000000000 yoctoNEAR")]
+    fn test_invest_below_minimum(){
+        let mut fund = new_fund();
+        testing_env!(owner_context().predecessor_account_id(LTS_TOKEN.parse().unwrap()).build());
+        fund.ft_on_transfer("alach.testnet".parse().unwrap(), U128(ONE_LTS / 10), "".to_string());
+    }
+
+    //testing the council seat given above the threshold
+    #[test]
+    fn test_council_seat(){
+        let mut fund = new_fund();
+        testing_env!(investor_context("alach.testnet", 60 * ONE_NEAR).build());
+        fund.invest_near();
+        assert!(!fund.is_council("alach.testnet".parse().unwrap()));
+        fund.invest_near();
+        assert!(fund.is_council("alach.testnet".parse().unwrap()));
+        assert_eq!(get_logs().iter().filter(|log| log.contains("\"event\":\"council_seat\"")).count(), 2);
+
+        testing_env!(owner_context().build());
+        fund.council_callback("alach.testnet".parse().unwrap(), Err(PromiseError::Failed));
+        assert!(!fund.is_council("alach.testnet".parse().unwrap()));
+    }
+
+    //testing that the seat is given back once a redemption takes the shares under the threshold
+    #[test]
+    fn test_council_release(){
+        let mut fund = new_fund();
+        testing_env!(investor_context("alach.testnet", 120 * ONE_NEAR).build());
+        fund.invest_near();
+        assert!(fund.is_council("alach.testnet".parse().unwrap()));
+
+        testing_env!(investor_context("alach.testnet", 0).block_timestamp(60000000000).build());
+        fund.redeem(U128(10 * ONE_NEAR));
+        assert!(fund.is_council("alach.testnet".parse().unwrap()));
+        fund.redeem(U128(20 * ONE_NEAR));
+        assert!(!fund.is_council("alach.testnet".parse().unwrap()));
+        assert_eq!(get_logs().iter().filter(|log| log.contains("\"event\":\"council_release\"")).count(), 2);
+    }
+
+    //testing the redemption of unlocked shares
+    #[test]
+    fn test_redeem(){
+        let mut fund = new_fund();
+        testing_env!(investor_context("alach.testnet", 10 * ONE_NEAR).build());
+        fund.invest_near();
+        testing_env!(owner_context().predecessor_account_id(LTS_TOKEN.parse().unwrap()).build());
+        fund.ft_on_transfer("oussema.testnet".parse().unwrap(), U128(20 * ONE_LTS), "".to_string());
+
+        testing_env!(investor_context("alach.testnet", 0).block_timestamp(60000000000).build());
+        fund.redeem(U128(5 * ONE_NEAR));
+        // half of the shares of alach are a quarter of the fund
+        assert_eq!(fund.get_holdings(), (U128(10 * ONE_NEAR - 5 * ONE_NEAR / 2), U128(15 * ONE_LTS)));
+        assert_eq!(fund.get_total_shares(), U128(15 * ONE_NEAR));
+
+        testing_env!(owner_context().build());
+        fund.send_lts_callback("alach.testnet".parse().unwrap(), U128(5 * ONE_LTS), Err(PromiseError::Failed));
+        assert_eq!(fund.get_owed_lts("alach.testnet".parse().unwrap()), U128(5 * ONE_LTS));
+    }
+
+    #[test]
+    #[should_panic(expected = "The shares are locked until 60000000000")]
+    fn test_redeem_locked(){
+        let mut fund = new_fund();
+        testing_env!(investor_context("alach.testnet", 10 * ONE_NEAR).build());
+        fund.invest_near();
+        fund.redeem(U128(ONE_NEAR));
+    }
+
+    //testing that the returns are shared between the shares held when they are deposited
+    #[test]
+    fn test_returns(){
+        let mut fund = new_fund();
+        testing_env!(investor_context("alach.testnet", 30 * ONE_NEAR).build());
+        fund.invest_near();
+        testing_env!(investor_context("oussema.testnet", 10 * ONE_NEAR).build());
+        fund.invest_near();
+        testing_env!(investor_context("energydao.testnet", 8 * ONE_NEAR).build());
+        fund.deposit_returns();
+        // the returns stay out of the NAV
+        assert_eq!(fund.get_nav(), U128(40 * ONE_NEAR));
+
+        // issameths invests after the deposit and earns nothing of it
+        testing_env!(investor_context("issameths.testnet", 40 * ONE_NEAR).build());
+        fund.invest_near();
+        testing_env!(owner_context().predecessor_account_id(LTS_TOKEN.parse().unwrap()).build());
+        fund.ft_on_transfer("energydao.testnet".parse().unwrap(), U128(80 * ONE_LTS), RETURNS_MSG.to_string());
+        assert_eq!(fund.get_total_shares(), U128(80 * ONE_NEAR));
+        assert_eq!(fund.get_distributed_returns(), (U128(8 * ONE_NEAR), U128(80 * ONE_LTS)));
+
+        let returns = fund.get_yield("alach.testnet".parse().unwrap());
+        assert_eq!((returns.unclaimed_near, returns.unclaimed_lts), (U128(6 * ONE_NEAR), U128(30 * ONE_LTS)));
+        let returns = fund.get_yield("issameths.testnet".parse().unwrap());
+        assert_eq!((returns.unclaimed_near, returns.unclaimed_lts), (U128(0), U128(40 * ONE_LTS)));
+    }
+
+    //testing the claims and the lifetime yield of an investor
+    #[test]
+    fn test_claim_returns(){
+        let mut fund = new_fund();
+        testing_env!(investor_context("alach.testnet", 10 * ONE_NEAR).build());
+        fund.invest_near();
+        testing_env!(investor_context("energydao.testnet", 2 * ONE_NEAR).build());
+        fund.deposit_returns();
+
+        // the redemption settles the returns of the redeemed shares
+        testing_env!(investor_context("alach.testnet", 0).block_timestamp(60000000000).build());
+        fund.redeem(U128(10 * ONE_NEAR));
+        fund.claim_returns();
+        assert!(get_logs().iter().any(|log| log.contains("\"event\":\"returns_claim\"")));
+
+        let returns = fund.get_yield("alach.testnet".parse().unwrap());
+        assert_eq!((returns.unclaimed_near, returns.claimed_near), (U128(0), U128(2 * ONE_NEAR)));
+        let claims = fund.get_claims("alach.testnet".parse().unwrap(), 0, 10);
+        assert_eq!(claims.len(), 1);
+        assert_eq!(claims[0].near_amount, U128(2 * ONE_NEAR));
+        assert_eq!(claims[0].timestamp, U64(60000000000));
+    }
+
+    #[test]
+    #[should_panic(expected = "There are no shares to distribute to")]
+    fn test_returns_without_shares(){
+        let mut fund = new_fund();
+        testing_env!(investor_context("energydao.testnet", ONE_NEAR).build());
+        fund.deposit_returns();
+    }
+}
@@ -0,0 +1,154 @@
+//! Returns of the financed projects distributed to the shareholders.
+//!
+//! Returns are deposited in NEAR or LTS and kept apart from the holdings of the fund, they don't
+//! change its NAV. Each deposit raises an accumulator of returns per share, and an investor earns
+//! its shares times the rise of the accumulator since its last checkpoint. The earnings are
+//! settled before every change of shares and stay claimable until the investor claims them.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::Vector;
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, near_bindgen, AccountId, Promise};
+use serde::{Deserialize, Serialize};
+
+use crate::events::{Event, FundEvent, ReturnsClaimData, ReturnsDepositData};
+use crate::{mul_div, EnergyFund, EnergyFundExt, NEAR_TOKEN};
+
+// Scale of the accumulators, shares are counted in yoctoNEAR at the first investment
+pub const ACC_PRECISION: u128 = 1_000_000_000_000_000_000_000_000;
+
+// msg of an LTS transfer depositing returns instead of investing
+pub const RETURNS_MSG: &str = "returns";
+
+// Returns of an investor
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Yield {
+    // Accumulators when the earnings were last settled
+    pub near_checkpoint: U128,
+    pub lts_checkpoint: U128,
+    pub unclaimed_near: U128,
+    pub unclaimed_lts: U128,
+    pub claimed_near: U128,
+    pub claimed_lts: U128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Claim {
+    pub near_amount: U128,
+    pub lts_amount: U128,
+    pub timestamp: U64,
+}
+
+impl EnergyFund {
+    // Function to get the returns of `account` with its earnings since the last checkpoint
+    fn internal_yield(&self, account: &AccountId) -> Yield {
+        let mut returns = self.yields.get(account).unwrap_or(Yield {
+            near_checkpoint: U128(0),
+            lts_checkpoint: U128(0),
+            unclaimed_near: U128(0),
+            unclaimed_lts: U128(0),
+            claimed_near: U128(0),
+            claimed_lts: U128(0),
+        });
+        let shares = self.investors.get(account).map(|investor| investor.shares.0).unwrap_or(0);
+        let earned_near = mul_div(shares, self.acc_near_per_share - returns.near_checkpoint.0, ACC_PRECISION);
+        let earned_lts = mul_div(shares, self.acc_lts_per_share - returns.lts_checkpoint.0, ACC_PRECISION);
+        returns.unclaimed_near = U128(returns.unclaimed_near.0 + earned_near);
+        returns.unclaimed_lts = U128(returns.unclaimed_lts.0 + earned_lts);
+        returns.near_checkpoint = U128(self.acc_near_per_share);
+        returns.lts_checkpoint = U128(self.acc_lts_per_share);
+        returns
+    }
+
+    // Function to settle the earnings of `account`, called before its shares change
+    pub(crate) fn internal_settle(&mut self, account: &AccountId) {
+        let returns = self.internal_yield(account);
+        self.yields.insert(account, &returns);
+    }
+
+    // Function to share `amount` of `token` between the shares
+    pub(crate) fn internal_distribute(&mut self, sender_id: &AccountId, token: &str, amount: u128) {
+        assert!(amount > 0, "Attach the returns to distribute");
+        assert!(self.total_shares > 0, "There are no shares to distribute to");
+        let increase = mul_div(amount, ACC_PRECISION, self.total_shares);
+        if token == NEAR_TOKEN {
+            self.acc_near_per_share += increase;
+            self.distributed_near += amount;
+        } else {
+            self.acc_lts_per_share += increase;
+            self.distributed_lts += amount;
+        }
+        FundEvent::ReturnsDeposit(&[ReturnsDepositData {
+            sender_id: sender_id.as_str(),
+            token,
+            amount: U128(amount),
+        }])
+        .emit();
+    }
+}
+
+#[near_bindgen]
+impl EnergyFund {
+    // Function to deposit NEAR returns of a project, LTS returns are sent with the msg "returns"
+    #[payable]
+    pub fn deposit_returns(&mut self) {
+        self.internal_distribute(&env::predecessor_account_id(), NEAR_TOKEN, env::attached_deposit());
+    }
+
+    // Function to claim the returns earned by the shares
+    pub fn claim_returns(&mut self) {
+        let account = env::predecessor_account_id();
+        let mut returns = self.internal_yield(&account);
+        let (near_amount, lts_amount) = (returns.unclaimed_near.0, returns.unclaimed_lts.0);
+        assert!(near_amount > 0 || lts_amount > 0, "You have no returns to claim");
+        returns.claimed_near = U128(returns.claimed_near.0 + near_amount);
+        returns.claimed_lts = U128(returns.claimed_lts.0 + lts_amount);
+        returns.unclaimed_near = U128(0);
+        returns.unclaimed_lts = U128(0);
+        self.yields.insert(&account, &returns);
+
+        let mut claims = self.claims.get(&account).unwrap_or_else(|| {
+            let mut prefix = b"k".to_vec();
+            prefix.extend(env::sha256(account.as_bytes()));
+            Vector::new(prefix)
+        });
+        claims.push(&Claim {
+            near_amount: U128(near_amount),
+            lts_amount: U128(lts_amount),
+            timestamp: U64(env::block_timestamp()),
+        });
+        self.claims.insert(&account, &claims);
+        FundEvent::ReturnsClaim(&[ReturnsClaimData {
+            account_id: account.as_str(),
+            near_amount: U128(near_amount),
+            lts_amount: U128(lts_amount),
+        }])
+        .emit();
+        if near_amount > 0 {
+            Promise::new(account.clone()).transfer(near_amount);
+        }
+        if lts_amount > 0 {
+            self.internal_send_lts(account, lts_amount);
+        }
+    }
+
+    // Function to get the returns of an investor, claimed and claimable, since its first investment
+    pub fn get_yield(&self, account: AccountId) -> Yield {
+        self.internal_yield(&account)
+    }
+
+    // Function to get the claims of an investor, oldest first
+    pub fn get_claims(&self, account: AccountId, from_index: u64, limit: u64) -> Vec<Claim> {
+        match self.claims.get(&account) {
+            Some(claims) => (from_index..std::cmp::min(from_index + limit, claims.len()))
+                .map(|i| claims.get(i).unwrap())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // Function to get the NEAR and the LTS distributed since the start of the fund
+    pub fn get_distributed_returns(&self) -> (U128, U128) {
+        (U128(self.distributed_near), U128(self.distributed_lts))
+    }
+}
@@ -0,0 +1,57 @@
+// NEP-297 events shared by the Lightency contracts
+// Every event is logged as `EVENT_JSON:{"standard":"lightency","version":..,"event":..,"data":[..]}`,
+// each contract declares its events as an enum tagged with `event` and `data` and gives their versions.
+
+use near_sdk::env;
+use serde::Serialize;
+
+pub const EVENT_STANDARD: &str = "lightency";
+
+#[derive(Serialize, Debug)]
+struct EventLog<'a, T: Serialize> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a T,
+}
+
+pub trait Event: Serialize + Sized {
+    // Version of the data schema of each event, bumped when its fields change
+    fn version(&self) -> &'static str;
+
+    fn emit(&self) {
+        let log = EventLog {
+            standard: EVENT_STANDARD,
+            version: self.version(),
+            event: self,
+        };
+        env::log_str(&format!("EVENT_JSON:{}", serde_json::to_string(&log).unwrap()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::get_logs;
+
+    #[derive(Serialize)]
+    #[serde(tag = "event", content = "data", rename_all = "snake_case")]
+    enum TestEvent<'a> {
+        Ping(&'a [&'a str]),
+    }
+
+    impl Event for TestEvent<'_> {
+        fn version(&self) -> &'static str {
+            "1.0.0"
+        }
+    }
+
+    #[test]
+    fn test_emit() {
+        TestEvent::Ping(&["alach.testnet"]).emit();
+        assert_eq!(
+            get_logs(),
+            vec![r#"EVENT_JSON:{"standard":"lightency","version":"1.0.0","event":"ping","data":["alach.testnet"]}"#]
+        );
+    }
+}
@@ -81,6 +81,10 @@ const CouncilPage = () => {
           <>Buy LTS</>
         ) : rowData.proposal_type === 2 ? (
           <>Sell LTS</>
+        ) : rowData.proposal_type === 3 ? (
+          <>Upgrade contract</>
+        ) : rowData.proposal_type === 4 ? (
+          <>Remove council</>
         ) : (
           <> Fund project</>
         )}
@@ -68,10 +68,12 @@ export async function initContract() {
         'get_votes_for',
         'get_votes_against',
         'get_nember_votes',
+        'get_members',
       ],
       changeMethods: [
-        'add_member',
-        'remove_member',
+        'create_member_proposal',
+        'execute_member_change',
+        'leave_pool',
         'create_proposal',
         'add_vote',
         'check_and_send_near',
@@ -0,0 +1,127 @@
+//! ERC-20 style allowances on top of NEP-141.
+//!
+//! An owner approves a spender for an amount, optionally until a deadline, and the spender can
+//! then move the owner's tokens with `ft_transfer_from`. The storage used by an approval is paid
+//! by the owner with the deposit attached to `ft_approve`.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, Balance};
+
+use crate::events::{FtApproveData, FtTransferFromData, LtsEvent};
+use crate::{settle_storage, Contract, ContractExt};
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Allowance {
+    pub amount: U128,
+    /// Block timestamp (nanoseconds) after which the allowance can't be used anymore.
+    pub expires_at: Option<U64>,
+}
+
+impl Allowance {
+    pub fn is_expired(&self) -> bool {
+        matches!(self.expires_at, Some(expires_at) if env::block_timestamp() > expires_at.0)
+    }
+}
+
+impl Contract {
+    /// Sets the allowance and charges (or refunds) the storage difference to the owner.
+    pub(crate) fn internal_approve(
+        &mut self,
+        owner_id: &AccountId,
+        spender_id: &AccountId,
+        amount: Balance,
+        expires_at: Option<U64>,
+        attached_deposit: Balance,
+    ) {
+        assert_ne!(owner_id, spender_id, "The owner can't approve itself");
+        if let Some(expires_at) = expires_at {
+            assert!(expires_at.0 > env::block_timestamp(), "The expiry must be in the future");
+        }
+        let initial_storage = env::storage_usage();
+        let key = (owner_id.clone(), spender_id.clone());
+        if amount == 0 {
+            self.allowances.remove(&key);
+        } else {
+            self.allowances.insert(&key, &Allowance { amount: amount.into(), expires_at });
+        }
+        settle_storage(initial_storage, attached_deposit, "approval");
+
+        LtsEvent::Approve(&[FtApproveData {
+            owner_id,
+            spender_id,
+            amount: amount.into(),
+            expires_at,
+        }])
+        .emit();
+    }
+
+    /// Consumes `amount` of the allowance given by `owner_id` to `spender_id`.
+    pub(crate) fn internal_spend_allowance(
+        &mut self,
+        owner_id: &AccountId,
+        spender_id: &AccountId,
+        amount: Balance,
+    ) {
+        let key = (owner_id.clone(), spender_id.clone());
+        let mut allowance = self.allowances.get(&key).expect("No allowance for this spender");
+        assert!(!allowance.is_expired(), "The allowance has expired");
+        assert!(allowance.amount.0 >= amount, "The allowance is too low");
+        // The entry is kept at 0 so the storage paid by the owner is only released by the owner
+        allowance.amount = (allowance.amount.0 - amount).into();
+        self.allowances.insert(&key, &allowance);
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Allows `spender_id` to transfer up to `amount` tokens of the caller until `expires_at`
+    /// (nanoseconds, no expiry if omitted). Replaces any previous allowance, `0` removes it.
+    /// The caller pays the storage of the approval with the attached deposit.
+    #[payable]
+    pub fn ft_approve(&mut self, spender_id: AccountId, amount: U128, expires_at: Option<U64>) {
+        let owner_id = env::predecessor_account_id();
+        self.internal_approve(&owner_id, &spender_id, amount.0, expires_at, env::attached_deposit());
+    }
+
+    /// Amount `spender_id` can still transfer on behalf of `owner_id`.
+    pub fn ft_allowance(&self, owner_id: AccountId, spender_id: AccountId) -> U128 {
+        match self.allowances.get(&(owner_id, spender_id)) {
+            Some(allowance) if !allowance.is_expired() => allowance.amount,
+            _ => U128(0),
+        }
+    }
+
+    /// Full allowance record, including its expiry.
+    pub fn ft_allowance_info(&self, owner_id: AccountId, spender_id: AccountId) -> Option<Allowance> {
+        self.allowances.get(&(owner_id, spender_id))
+    }
+
+    /// Transfers `amount` tokens of `owner_id` to `receiver_id` using the allowance of the caller.
+    /// Requires exactly 1 yoctoNEAR attached, like `ft_transfer`.
+    #[payable]
+    pub fn ft_transfer_from(
+        &mut self,
+        owner_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+    ) {
+        assert_one_yocto();
+        let spender_id = env::predecessor_account_id();
+        self.assert_not_frozen(&[&owner_id, &spender_id, &receiver_id]);
+        self.internal_spend_allowance(&owner_id, &spender_id, amount.0);
+        self.internal_seed_checkpoints(&[&owner_id, &receiver_id]);
+        self.token.internal_transfer(&owner_id, &receiver_id, amount.0, memo);
+        self.internal_checkpoint(&[&owner_id, &receiver_id]);
+        LtsEvent::TransferFrom(&[FtTransferFromData {
+            owner_id: &owner_id,
+            spender_id: &spender_id,
+            receiver_id: &receiver_id,
+            amount,
+        }])
+        .emit();
+    }
+}
@@ -0,0 +1,166 @@
+//! Historical balances for snapshot based governance.
+//!
+//! Every change of a balance (transfer, mint, burn, refund of `ft_transfer_call`, account
+//! closing) records the new balance of the account at the current block height, mints and burns
+//! also record the new total supply. The DAOs can then weight votes with the balances at the block a proposal was created,
+//! so tokens moved during a vote aren't counted twice.
+//!
+//! An account keeps its last `MAX_CHECKPOINTS` balances, paid with its registration: once the
+//! history is full the oldest checkpoint is overwritten, and the balance at a block older than
+//! the history is 0. The history of the total supply isn't capped.
+//!
+//! Accounts holding tokens from before the snapshots have no history until their first change,
+//! their balance is the same at every block until then.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::Vector;
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, near_bindgen, AccountId, Balance};
+
+use crate::{Contract, ContractExt};
+
+/// Number of balances kept per account.
+pub const MAX_CHECKPOINTS: u64 = 8;
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug)]
+pub struct Checkpoint {
+    pub block_height: u64,
+    pub balance: Balance,
+}
+
+/// Index of the oldest checkpoint. A full history is a ring, its block heights go down only
+/// from the newest checkpoint to the oldest one.
+fn oldest(checkpoints: &Vector<Checkpoint>, cap: u64) -> u64 {
+    if checkpoints.len() < cap {
+        return 0;
+    }
+    let (mut low, mut high) = (0, checkpoints.len() - 1);
+    while low < high {
+        let middle = (low + high) / 2;
+        if checkpoints.get(middle).unwrap().block_height > checkpoints.get(high).unwrap().block_height {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
+
+/// Records `balance` at the current block in a history of at most `cap` checkpoints, only the
+/// last value of a block is kept.
+fn record(checkpoints: &mut Vector<Checkpoint>, balance: Balance, cap: u64) {
+    let checkpoint = Checkpoint { block_height: env::block_height(), balance };
+    if checkpoints.is_empty() {
+        checkpoints.push(&checkpoint);
+        return;
+    }
+    let len = checkpoints.len();
+    let first = oldest(checkpoints, cap);
+    let last = (first + len - 1) % len;
+    if checkpoints.get(last).unwrap().block_height == checkpoint.block_height {
+        checkpoints.replace(last, &checkpoint);
+    } else if len < cap {
+        checkpoints.push(&checkpoint);
+    } else {
+        checkpoints.replace(first, &checkpoint);
+    }
+}
+
+/// Value at `block_height`: the last checkpoint at or before it, 0 before the first one.
+fn value_at(checkpoints: &Vector<Checkpoint>, block_height: u64, cap: u64) -> Balance {
+    let len = checkpoints.len();
+    let first = oldest(checkpoints, cap);
+    let get = |index: u64| checkpoints.get((first + index) % len).unwrap();
+    let (mut low, mut high) = (0, len);
+    while low < high {
+        let middle = (low + high) / 2;
+        if get(middle).block_height <= block_height {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    match low {
+        0 => 0,
+        index => get(index - 1).balance,
+    }
+}
+
+impl Contract {
+    /// History of `account_id`, empty if it has none yet.
+    fn account_checkpoints(&self, account_id: &AccountId) -> Vector<Checkpoint> {
+        self.checkpoints.get(account_id).unwrap_or_else(|| {
+            let mut prefix = b"h".to_vec();
+            prefix.extend(env::sha256(account_id.as_bytes()));
+            Vector::new(prefix)
+        })
+    }
+
+    /// Records the current balances of `accounts`.
+    pub(crate) fn internal_checkpoint(&mut self, accounts: &[&AccountId]) {
+        for account_id in accounts {
+            let mut checkpoints = self.account_checkpoints(account_id);
+            let balance = self.token.accounts.get(account_id).unwrap_or(0);
+            record(&mut checkpoints, balance, MAX_CHECKPOINTS);
+            self.checkpoints.insert(account_id, &checkpoints);
+        }
+    }
+
+    /// Starts the history of `accounts` with the balance they held before their first change,
+    /// called before changing balances.
+    pub(crate) fn internal_seed_checkpoints(&mut self, accounts: &[&AccountId]) {
+        for account_id in accounts {
+            let balance = self.token.accounts.get(account_id).unwrap_or(0);
+            self.internal_seed_checkpoint(account_id, balance);
+        }
+    }
+
+    /// Starts the history of `account_id` with `balance` if it has none. Only the accounts of the
+    /// unversioned token hold tokens without a history, their balance didn't change since the
+    /// upgrade so it is recorded from the first block.
+    pub(crate) fn internal_seed_checkpoint(&mut self, account_id: &AccountId, balance: Balance) {
+        if balance == 0 || self.checkpoints.get(account_id).is_some() {
+            return;
+        }
+        let mut checkpoints = self.account_checkpoints(account_id);
+        checkpoints.push(&Checkpoint { block_height: 0, balance });
+        self.checkpoints.insert(account_id, &checkpoints);
+    }
+
+    /// Records the current total supply, only mints and burns change it.
+    pub(crate) fn internal_supply_checkpoint(&mut self) {
+        record(&mut self.supply_checkpoints, self.token.total_supply, u64::MAX);
+    }
+
+    /// Adds the storage of a full history of checkpoints to the storage an account has to pay
+    /// for when it registers, so `storage_balance_bounds` covers it.
+    pub(crate) fn internal_measure_checkpoint_storage(&mut self) {
+        let initial_storage = env::storage_usage();
+        let tmp_account_id: AccountId = "a".repeat(64).parse().unwrap();
+        self.internal_checkpoint(&[&tmp_account_id]);
+        let mut checkpoints = self.checkpoints.get(&tmp_account_id).unwrap();
+        for block_height in 1..MAX_CHECKPOINTS {
+            checkpoints.push(&Checkpoint { block_height, balance: 0 });
+        }
+        self.checkpoints.insert(&tmp_account_id, &checkpoints);
+        self.token.account_storage_usage += env::storage_usage() - initial_storage;
+        self.checkpoints.remove(&tmp_account_id).unwrap().clear();
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Balance of `account_id` at the end of block `block_height`. An account without a history
+    /// has held its current balance since the snapshots started.
+    pub fn ft_balance_at(&self, account_id: AccountId, block_height: U64) -> U128 {
+        match self.checkpoints.get(&account_id) {
+            Some(checkpoints) => value_at(&checkpoints, block_height.0, MAX_CHECKPOINTS).into(),
+            None => self.token.accounts.get(&account_id).unwrap_or(0).into(),
+        }
+    }
+
+    /// Total supply at the end of block `block_height`.
+    pub fn total_supply_at(&self, block_height: U64) -> U128 {
+        value_at(&self.supply_checkpoints, block_height.0, u64::MAX).into()
+    }
+}
@@ -0,0 +1,78 @@
+//! Account freezing for regulatory compliance.
+//!
+//! The owner grants the compliance role to the accounts allowed to freeze and unfreeze holders.
+//! A frozen account can't send or receive transfers, be minted to or stake. Nothing changes for
+//! anyone as long as no account is frozen.
+
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::events::{FtComplianceData, FtFreezeData, LtsEvent};
+use crate::{Contract, ContractExt};
+
+impl Contract {
+    /// Panics if one of `accounts` is frozen.
+    pub(crate) fn assert_not_frozen(&self, accounts: &[&AccountId]) {
+        for account_id in accounts {
+            assert!(!self.frozen.contains(account_id), "The account {} is frozen", account_id);
+        }
+    }
+
+    fn assert_compliance(&self) {
+        assert!(
+            self.compliance.contains(&env::predecessor_account_id()),
+            "Only the compliance role can freeze accounts"
+        );
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn add_compliance(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        if self.compliance.insert(&account_id) {
+            LtsEvent::ComplianceGrant(&[FtComplianceData { account_id: &account_id }]).emit();
+        }
+    }
+
+    pub fn remove_compliance(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        if self.compliance.remove(&account_id) {
+            LtsEvent::ComplianceRevoke(&[FtComplianceData { account_id: &account_id }]).emit();
+        }
+    }
+
+    pub fn get_compliance(&self) -> Vec<AccountId> {
+        self.compliance.to_vec()
+    }
+
+    /// Freezes `account_id`, `reason` is only logged in the event.
+    pub fn freeze(&mut self, account_id: AccountId, reason: Option<String>) {
+        self.assert_compliance();
+        assert!(self.frozen.insert(&account_id), "The account is already frozen");
+        LtsEvent::Freeze(&[FtFreezeData {
+            account_id: &account_id,
+            by: &env::predecessor_account_id(),
+            reason: reason.as_deref(),
+        }])
+        .emit();
+    }
+
+    pub fn unfreeze(&mut self, account_id: AccountId, reason: Option<String>) {
+        self.assert_compliance();
+        assert!(self.frozen.remove(&account_id), "The account isn't frozen");
+        LtsEvent::Unfreeze(&[FtFreezeData {
+            account_id: &account_id,
+            by: &env::predecessor_account_id(),
+            reason: reason.as_deref(),
+        }])
+        .emit();
+    }
+
+    pub fn is_frozen(&self, account_id: AccountId) -> bool {
+        self.frozen.contains(&account_id)
+    }
+
+    pub fn get_frozen_accounts(&self) -> Vec<AccountId> {
+        self.frozen.to_vec()
+    }
+}
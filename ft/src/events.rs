@@ -0,0 +1,128 @@
+//! Lightency specific events of the LTS token.
+//!
+//! NEP-141 transfers, mints and burns keep using the standard `nep141` events, everything the
+//! standard doesn't cover is logged here under the `lightency` standard.
+
+use near_sdk::json_types::{Base64VecU8, U128, U64};
+use near_sdk::serde::Serialize;
+use near_sdk::{env, AccountId};
+
+pub const EVENT_STANDARD: &str = "lightency";
+
+/// An allowance given by `owner_id` to `spender_id`.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtApproveData<'a> {
+    pub owner_id: &'a AccountId,
+    pub spender_id: &'a AccountId,
+    pub amount: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<U64>,
+}
+
+/// Tokens moved by `spender_id` out of the allowance of `owner_id`.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtTransferFromData<'a> {
+    pub owner_id: &'a AccountId,
+    pub spender_id: &'a AccountId,
+    pub receiver_id: &'a AccountId,
+    pub amount: U128,
+}
+
+/// An allowance granted through a signed permit.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtPermitData<'a> {
+    pub owner_id: &'a AccountId,
+    pub spender_id: &'a AccountId,
+    pub amount: U128,
+    pub nonce: U64,
+}
+
+/// New metadata of the token, the icon is left out to keep the log small.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtMetadataUpdateData<'a> {
+    pub name: &'a str,
+    pub symbol: &'a str,
+    pub icon_updated: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference_hash: Option<&'a Base64VecU8>,
+}
+
+/// An account given or removed the compliance role.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtComplianceData<'a> {
+    pub account_id: &'a AccountId,
+}
+
+/// An account frozen or unfrozen by the compliance account `by`.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtFreezeData<'a> {
+    pub account_id: &'a AccountId,
+    pub by: &'a AccountId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<&'a str>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+#[must_use = "don't forget to `.emit()` this event"]
+pub enum LtsEvent<'a> {
+    #[serde(rename = "ft_approve")]
+    Approve(&'a [FtApproveData<'a>]),
+    #[serde(rename = "ft_transfer_from")]
+    TransferFrom(&'a [FtTransferFromData<'a>]),
+    #[serde(rename = "ft_permit")]
+    Permit(&'a [FtPermitData<'a>]),
+    #[serde(rename = "ft_metadata_update")]
+    MetadataUpdate(&'a [FtMetadataUpdateData<'a>]),
+    #[serde(rename = "ft_compliance_grant")]
+    ComplianceGrant(&'a [FtComplianceData<'a>]),
+    #[serde(rename = "ft_compliance_revoke")]
+    ComplianceRevoke(&'a [FtComplianceData<'a>]),
+    #[serde(rename = "ft_freeze")]
+    Freeze(&'a [FtFreezeData<'a>]),
+    #[serde(rename = "ft_unfreeze")]
+    Unfreeze(&'a [FtFreezeData<'a>]),
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a LtsEvent<'a>,
+}
+
+impl LtsEvent<'_> {
+    /// Version of the data schema of the event.
+    pub fn version(&self) -> &'static str {
+        match self {
+            LtsEvent::Approve(_) => "1.0.0",
+            LtsEvent::TransferFrom(_) => "1.0.0",
+            LtsEvent::Permit(_) => "1.0.0",
+            LtsEvent::MetadataUpdate(_) => "1.0.0",
+            LtsEvent::ComplianceGrant(_) => "1.0.0",
+            LtsEvent::ComplianceRevoke(_) => "1.0.0",
+            LtsEvent::Freeze(_) => "1.0.0",
+            LtsEvent::Unfreeze(_) => "1.0.0",
+        }
+    }
+
+    /// Logs the event with the `EVENT_JSON:` prefix.
+    pub fn emit(self) {
+        let log = EventLog { standard: EVENT_STANDARD, version: self.version(), event: &self };
+        env::log_str(&format!(
+            "EVENT_JSON:{}",
+            near_sdk::serde_json::to_string(&log).unwrap_or_else(|_| env::abort())
+        ));
+    }
+}
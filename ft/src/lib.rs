@@ -16,20 +16,33 @@ NOTES:
     keys on its account.
 */
 
-use std::convert::TryInto;
-
 use near_contract_standards::fungible_token::metadata::{
     FungibleTokenMetadata, FungibleTokenMetadataProvider, FT_METADATA_SPEC,
 };
+use near_contract_standards::fungible_token::core::FungibleTokenCore;
+use near_contract_standards::fungible_token::resolver::FungibleTokenResolver;
 use near_contract_standards::fungible_token::FungibleToken;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::LazyOption;
-use near_sdk::json_types::U128;
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedSet, Vector};
+use near_sdk::json_types::{Base64VecU8, U128};
 use near_sdk::{
     env, ext_contract, log, near_bindgen, AccountId, Balance, Gas, PanicOnDefault, Promise,
-    PromiseError, PromiseOrValue,
+    PromiseError, PromiseOrValue, PublicKey,
 };
 
+mod allowance;
+mod checkpoints;
+mod compliance;
+mod events;
+mod migration;
+mod permit;
+
+pub use crate::allowance::Allowance;
+pub use crate::checkpoints::{Checkpoint, MAX_CHECKPOINTS};
+use crate::events::{FtMetadataUpdateData, LtsEvent};
+pub use crate::migration::{ContractV0, VersionedContract, REWARDER_ACCOUNT, STATE_VERSION};
+pub use crate::permit::Permit;
+
 pub const TGAS: u64 = 1_000_000_000_000;
 
 #[ext_contract(ext_ft)]
@@ -43,22 +56,49 @@ pub trait Rewarder {
 pub struct Contract {
     token: FungibleToken,
     metadata: LazyOption<FungibleTokenMetadata>,
+    owner_id: AccountId,
+    max_supply: Balance,
+    // Contracts allowed to mint (rewarder, treasury), the rewarder is allowed from the start
+    minters: UnorderedSet<AccountId>,
+    // Accounts allowed to burn tokens of any holder
+    burners: UnorderedSet<AccountId>,
+    minted_by: LookupMap<AccountId, Balance>,
+    // Allowances given by an owner to a spender
+    allowances: LookupMap<(AccountId, AccountId), Allowance>,
+    // Keys registered to sign permits and the next permit nonce of each account
+    permit_keys: LookupMap<AccountId, PublicKey>,
+    permit_nonces: LookupMap<AccountId, u64>,
+    // Balance history of each account and of the total supply, used for vote snapshots
+    checkpoints: LookupMap<AccountId, Vector<Checkpoint>>,
+    supply_checkpoints: Vector<Checkpoint>,
+    // Accounts allowed to freeze holders and the frozen holders
+    compliance: UnorderedSet<AccountId>,
+    frozen: UnorderedSet<AccountId>,
+    state_version: u8,
 }
 
 const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAANUAAADKCAMAAAAFHvX/AAAABGdBTUEAALGPC/xhBQAAAAFzUkdCAK7OHOkAAAAnUExURQAAAP/eAP/eAP/eAP/eAP/eAP/eAP/eAP/eAP/eAP/eAP/eAP/eALqkKaQAAAAMdFJOUwAN8DYg3k+GasmftEtw3E0AABG2SURBVHja7V3XduM4DDWLWP3/3ztiQWGTHVt2MueID7sZO5EEArgoBKDb7VrXuta1rnWta13rWte61rWuda1rXeta17rWta51rWv92SW0Mi6o8nP+rwvOKC3+W4KUs9Fv8n6XOv1bhfyxue8fbD5Y89+RtlMUtzusWMnJVAj8XPrg1H9DmXaBKErL5Y/D3dT/s7VTZsT/QFKU+MxbtHH/n648KiLo9k9C8ET49scJEwa5lMVr15ydKi+qPm35ByUT93YY2VkKG7BZ9XfZ5HH3na6f7VRaFLwigh40LasfEBb/JMO03WDf2fMZoEUkigt9FlCx/B3y17u/RpcOZc9lpyNIgcoEi4ZSpot1Q/4UXdrKutu6UzSUNpt/wYBUhu4KqnL679Al3LbSDIW4XlSuiCAiSMuwchVv/gRRxleaJt8lFFcogEDM/qlU690J6g8o1NEOI1dsAkYkUQIH53TJ3xbD8hibW5AMGpT1S0gQwQ2xfaCraKhXv84oafVKOAEgVCZoJ82D7dpW7FCxsOv3NGp7sK+I60W/UJ8GbJ/wP+rfISoLy6EOIHPKDwpEUKMsHojA9htgqONDBdD3loqiXUl7IpB7yC77ddAwkpB6+WwAeq5KHEqknWP7cPlvS2G1Uoc2E0Eh1h/QKJN1PrLrMnxft8D6LvdcAK4nAQzwSWy+mscz24Mrf8NTWu0oMsQh5KEIHmB7gfave06692q3uVojCRFJMJxQdeD8f9/LdYw5sLHuANdRAJkILrC9uhbcrusv8Uxs/K5L9cIHd8zmhso/ClGOQWL35L8liS4Jne6My+BqIzFJh3TvQ7UB8WJ/EpnxazLos+Rrkptt4g8CMQ3eoQgOTtMoy5l1Un0NKUwft9Z4hOs4ylj7/EirbLAdcIcuIEpup/yO+KjhUpuCuKnLnwx4jLgeGllDGhMw9gpFzBZVHCurnP8gJO77H24stG2sSqcWrqY3O4OL/2TYPiqUgRxcgD+yH/TSUZgSZ2QX2bcQBg5sNlFKGZeWURq9JwiIR6uLNAGT7WHk8q6PlO+OzIqu92yYdmDgm55ooyS1lPUJq96pweqaWLPZyKpkIwhGz0YK2ZwF7DJfjSYDdVVhw4D+iPYggclVwg3lqtXt9XO3HBFZVa4ZP6JaAlKRAkj0yB1JdIGa191VyKO82CUMCjFZ3UpTgg2DiTa4hP2UUkXH88qZGZU7zCGAdHvGdbv5aJ3ZFWpfSb1siD77C6KSGHtGFyj0wKq0mzIL+wdMl8q7nwSq3CwzS3CpQ1yugplFVU9OFIUWaB6YKQiSbQ9tn8kiax7GqC+CeuaNQx22FPmBgqN66PDE1joOEpUmUNG0exuce+V9DJ+QQaTGw/PmG4sOuOgpYx8/CSF67iN3a/whURqJVfCT+ID7pCU/3IjdnRlKIF2C8D4rU14hHeADcfgbcPhAGkY7pjd+lHKyqxtI3PB4jaSkCY2bYyhtgpeygXW5Rav04AJKnqh3eDs7ufFpUMH2iWDC9aJuW8Mi+Blxu7ZAKFE8EtNlPOi41bMbb2cCRuTKj9snfBskKc8tKhxJlfoKv0vfjume3Aw87CqZxSBmrIqcP/Y4M/WKqxSaM4Eiee3nOYcHoStgWjrHNyRwqVbGevgK0CLzeJtoVatLncifECVynpDkeYZLjuUl4UB1UXSA5QuALTlXhmS1wqBmNz6JVbazXbJnlmNiBG4vhcu7X7H7F6x+CeSzprS1p2Bqrbjpxqcxy/c5BiImwrM48tSKnmCIoo2NgIMylWEYzYPdmk3Pf1QuRawaJM6cp1lm5Hvkx4YRiHIkTeAJaV7PRCUzRfAEP9MRQBaxakSHzCxxlq3q00Hqzg7YdpAy98bAwPmPWSN7KYbhZzqZrJ01GCISeTN0PMGtCEurnC1ZQkVWAlOdBAxpk9jtPsWuWbuTAQBYg5fM2XKymMmKCmmZWd0klP6sCGSawtzw3hKIEgHVS0HGxhrdAWAthineedFCR5CBp65TD8meE5Hk3RHTq1sWIUciyrHUcpgWAGpwhk3zR9kSIi1x+vxTyXkNK9ycWDxhA6gK4PrkXT84BkcnPwOgRbLKYZwZLXwDVFKcgRVTE4Ghid44JGeiCgw+qAYpapfNtkNH0N3ZCZfUz+/yDwVwxXGy+5ad3uRP3HOH1YIcJYvsjuzQ1a40Ip4hgGb5TdlWGcF05V91TxcWkKMUIIAXW2x9zWcMzWkCyP1pA2c3eXdd74EfWQ0PZEXETvXAPT9BBPXBAa5qrKStemZ+lOQC01vgzzSXPgDl8BEEHOMdVQ2Ykj/zqzNZaW+aJFI8CnvD216TPRJino+p&zyG2nwYLWXIDt4BrVKdv3zPE/tBB6ZN&2RevDz4UcNqTuoD9MJz2oF3rOB1xcANoL8u0h4nopF6nq4Qfukat11mrN3fiuWpmpyxM875eowcqLOTsAmro1mmri7TSUtm/GjjO1EspSgLGHSoqex86VSpi8DlUrbQbsDWU2CsvERPDfUay+7lfwHhCJ7nh9HL3N0Umxs5SjxBzFhEJN78Pw9g2LlW7DckvNHkYm9oFY5W4rqo70rableWVGYhnJhIyMZUnQ36iy07gpTetRv3m6uGbLiO4xVap4m1p2OJAaEyRr/9EoQm+Ueqqyd4lJLOval+IDpiyj78dU3SIefPQ40O5nzPhhefbwlYMQaVomqeamWjMBjCtoeoKq6sPguWuLEK3sB2O7DPbPIReX75mU+pICxQbrKPUJqmoxOLidYvN975LqslUvw4UIHBtEb7HAOaqQ5Ja+2zNUWeR3/r0kkZsd7slY9rKDq/08n8Ia4twNGyTWTuczVFXZAwMZVm10CPkvexc7E0Ym7UaYNuxeA9lEztqPeYaq2n5mqO6Y1GjAD+s3Khf6MVUDkwRDww0S1aUKRq9N4zNUZbSmq+T8hST518P5w1lHPtTVlqDDCFd4VE31QT/BU1QV507UjkGTpJqls2V0nyip4wlzX9Q4FKNbH8StHdunqHKZ86KwLCOQaXdyt1XnEsaaKtmm1QeoQnPgSD9FVY0F61YxeRZcROxJbdMcHWRjsmBbEb1Wz/0UVfUyQNXGtXRXZ0bY282qml1uQNnq+rLHOYGqCqjUkTvZ3BEWfxQzss7zScL8q1QVTJaTGoAXOEXoOgbcX6aK+dhydAx+jH2S+baitTDnUxXmVLFoVcZTJi3w+JfrKmDg/RMYyNGiheDzShMSagy62oKwe5Oqau+YvXI1vDoJJZYI30aOttRuC/Lg3rHC1bdolbW550kkiUlYykSh+tdQFnx/zw8seqmZx+SOsPz1un2zjd7XrmT8XF6hJIptmRB9hqrKJEXRzX1td5OT/fKZdz5FHLeJe7kGPbjbugPzGapY6KlYJCIHHwnyGK9TFcmvFPOIxI0B35KqR2BxZ6FnnDvqLGf8ciwswr1JLYnRhQmUjF8rVs1yBktraIWLbei5jUEV1+q3qrSSbLVpQNG5MA4OTsRBUl9NamPEmHkMBOiiz/zs6kzJO+/fOm7MsdvDtDdUdiybgCdU9UpmMaU4XqJL7O/a4N4qLKYOlS7tLYanriIYXqMKSlX88E0z3Ancv/BWgzT3xpoda30xyPrHBV48lsCa9lWtviwT+/7p4/Tp6lSFYzpLe0MqcHXwqeSwWo7AUSyr7T9K7L972Djm+Pq0d2YZZP3jiwdL9TAFDw8eJPbNmyXg8/OvVjLsDYve1GvWEc7/K2Tog8Q+S9y8kYZZ2yBkmbtRSedLzRzQglKbn7AlyodF1BHfbIQ5rNjYtezeVSi2tSA/kL94Y1WOuThvHRq+X0ZyXLFB1ZZQBWnuP5YOV6tjDLUtHPcxqrdLfg4PlnkNvWW1ID86YDeSlVsosijri1gY3veWHbYH+oAmHpSjVPDonxHlepU8LMf375fe+nWbJN5agFxAEPk8WUaySrUs67lLS/i1IL9dGnM7ii+oQifCvIAMHaU6zjwt4CWilyDqwYJwhxeU4m1st1T8XTrrY1X3cpj3ROlAbea51W6lUnlLo2YWzx7PaHBcSTHVfkfwCLb6jE8MNcqbvkGchH9J4rU06GcI4Dq+CLyYuT6JrFWcIjwqkW4mf2XmetAnVm9tFiLy/onPAgWpht7TvMOs+xlcSpX0tp6AFqmjJ4sfGXNWJz8xlWc1H8zLKCKfWYbtOJmsDIB14oWMkyyeqt08pa2ulBRjk/Cd18m7qY084/B0eh3EqBxbBXwu1i8HbSKpWU7XrlohWKtcqXe3uBHFMUG7Ne+Miyd1YM1aKsieFKNFZCk2oayKWTkaz5215OrX5peiXcUiVoPcXXoU/BMaKm7T8hDXbSgzvZo3oqswG5mQCkRKz1UZmWAFN8jY7iUmharhflY7vh7KsMlPA/I0kSUsb2lucjmYT6lfedakkImC7nHqL4zDo5zVCT240G2PXung89RvWQBQwkxVQc3dwTqIAqEBsJbEOYm1/YYPW21vfGYjdM8sqqFnOFWap8otxWr+Lcskwnxb2ifIsJDSqq5b7kxWDcyKfB4v3rVxlKBdOGU2ulkQghIEMGSh6KIdALZH93N71luLSOLe3rRE5lBkiW3QuyKFNLYjLd4CSDbabF2KGZnVVvIqee58Af74AmdBDDBimxYlzRJFdcQKHzePwxWySjVVjMSsBt3j/dx5JLzHi+Z29AJRh1xvejg66ZGdF+8YGKs981x4oOVOs1XcQIVba/I7gegHXJAWxQ0OlaVMY2T69k07zHYkY0ts09sJjVejp4LD5Gh4EbFqGEZiLCcttdWatmd9dxW7USSsZ5CEDdkWzp2YQJlIzYGDd77SFCX4IDzuqTQsVmlH4XA5UKxp4/xxODWJFJrRE6p5JBoDY+UTGGybCdTN2CJ+9SIS+jOji0oSSWOIigKPg6ZwcBGMScwK2b3pRaTpCQUr/L0NmdmIKS4JpXH9IyN+QAY9l/f0kx6GgmGjepaXlBPfdogox6ehvAwmX0JR4VevnFZzPzaneT4ifyym49g0wlfd8BiqkuvZfIEIZwChnxtIQEreUR2V9qmpj5EC1TI/A18NcGsUKj0U5qbihCr4wo+DYGEc4mYIYt17ZfmPHKet6fjz/dDlZq7rRizZOZfm4Gw+zcTxNZ8MbQbD+EBw5jfULGx9/MwyfExXP8Sye21BaKcfCiF0np4F6Tw6WhtGPcKV6Dc+OyjbVjmAgpxxmCHuOT51e4Kt2LRE9BSG0c1IV/3LeP/kKFVh+dueWGJMDUNUNR/2qDjkSMD1eOv0kU1HBByqmYJvvDDA9hNPh4dimeM2pw2f6k7/cVs6Sv3tWyv7mbYXl36aumXDlf3AwfEMwAxTRzNdXxv9bZvZFatB0sijRoPw6GOSma3gx669c3D70vBvJXnMsJymjse3PE0Kc2HnUwsmo5tV/NI7OByTkaNp6sAONmcDiVkdIE1GN3/7LQGTaerT47OAo16ORn4/ZP6XeOaPXxaEpokMLvaYHp3tPnyhwgf5ZB7uKRYM0ut6HozSv3VW+et0ueqDimOsbN/dMHm/w8J21JnF6us69XiQD0re8JaU8Oi8xslfeqdNHbqkj/Nt/LQXRfLR9KE8CvOX3j9U8shHGxrbl79QaehhuFSh1f4SCNbbr9nl+MxERQfn7qhmR/lfe6NS+wSrV5p0r4ZCOD94pVI1w0HffnHBG9PMMba3b2Bb47o4fqPbt9k1fwUgTe9KHTsYaK0qTCG2/11GtXHr7EWmho845FUVE1wHmv7ICxtpuvWgXzQdYqP6kGmTgv5zL9dkU3v79uvIz1oZ37rRqCrI+8HbmX5PvfDItDkLbl77V41XHwZjQ9zm9O2vLXwVL29MoP7EjU/+9TSjHQsX/t6LeMEnHRt6/XAwiU11aegS/sGffss1a68sw5UDG99Ng8KdMpY3Z/xB0esDL8uqfDY8yNptMB3885fB+L/7zu4O0mwzjwcKXXA6Oe8B+T9IAh0z9Ap1X+GwlFA0L2G//YcrFTClc7hSNAfEbbyc6X9dQhcK6os2tNLidq1rXeta17rWta51rWtd61rX+h/WPzqBQmLTmfl4AAAAAElFTkSuQmCC";
 
-fn assert_self() {
-    assert_eq!(
-        env::current_account_id(),
-        env::signer_account_id(),
-        "Can only be called by the token contract"
-    );
-}
-
-fn assert() {
-    if env::signer_account_id().to_string() != env::current_account_id().to_string() {
-        if env::signer_account_id().to_string() != "lightency_contract.testnet".to_string() {
-            panic!("You're not authorised to execute this function");
+/// Charges the storage used since `initial_storage` to `attached_deposit` and refunds the rest to
+/// the caller, or refunds the storage released along with the deposit.
+pub(crate) fn settle_storage(initial_storage: u64, attached_deposit: Balance, item: &str) {
+    let final_storage = env::storage_usage();
+    if final_storage > initial_storage {
+        let cost = (final_storage - initial_storage) as Balance * env::storage_byte_cost();
+        assert!(
+            attached_deposit >= cost,
+            "Attach at least {} yoctoNEAR to cover the storage of the {}",
+            cost,
+            item
+        );
+        if attached_deposit > cost {
+            Promise::new(env::predecessor_account_id()).transfer(attached_deposit - cost);
+        }
+    } else {
+        let refund =
+            (initial_storage - final_storage) as Balance * env::storage_byte_cost() + attached_deposit;
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
         }
     }
 }
@@ -68,10 +108,11 @@ impl Contract {
     /// Initializes the contract with the given total supply owned by the given `owner_id` with
     /// default metadata (for example purposes only).
     #[init]
-    pub fn new_default_meta(owner_id: AccountId, total_supply: u128) -> Self {
+    pub fn new_default_meta(owner_id: AccountId, total_supply: u128, max_supply: u128) -> Self {
         Self::new(
             owner_id,
             total_supply.into(),
+            max_supply,
             FungibleTokenMetadata {
                 spec: FT_METADATA_SPEC.to_string(),
                 name: "Lights".to_string(),
@@ -85,17 +126,41 @@ impl Contract {
     }
 
     /// Initializes the contract with the given total supply owned by the given `owner_id` with
-    /// the given fungible token metadata.
+    /// the given fungible token metadata. No more than `max_supply` tokens can ever be minted.
     #[init]
-    pub fn new(owner_id: AccountId, total_supply: u128, metadata: FungibleTokenMetadata) -> Self {
+    pub fn new(
+        owner_id: AccountId,
+        total_supply: u128,
+        max_supply: u128,
+        metadata: FungibleTokenMetadata,
+    ) -> Self {
         assert!(!env::state_exists(), "Already initialized");
+        assert!(total_supply <= max_supply, "The total supply exceeds the maximum supply");
         metadata.assert_valid();
         let mut this = Self {
             token: FungibleToken::new(b"a".to_vec()),
             metadata: LazyOption::new(b"m".to_vec(), Some(&metadata)),
+            owner_id: owner_id.clone(),
+            max_supply,
+            minters: UnorderedSet::new(b"n".to_vec()),
+            burners: UnorderedSet::new(b"b".to_vec()),
+            minted_by: LookupMap::new(b"s".to_vec()),
+            allowances: LookupMap::new(b"l".to_vec()),
+            permit_keys: LookupMap::new(b"k".to_vec()),
+            permit_nonces: LookupMap::new(b"p".to_vec()),
+            checkpoints: LookupMap::new(b"c".to_vec()),
+            supply_checkpoints: Vector::new(b"t".to_vec()),
+            compliance: UnorderedSet::new(b"g".to_vec()),
+            frozen: UnorderedSet::new(b"f".to_vec()),
+            state_version: STATE_VERSION,
         };
+        this.minters.insert(&REWARDER_ACCOUNT.parse().unwrap());
+        this.internal_measure_checkpoint_storage();
         this.token.internal_register_account(&owner_id);
         this.token.internal_deposit(&owner_id, total_supply.into());
+        this.internal_checkpoint(&[&owner_id]);
+        this.internal_supply_checkpoint();
+        this.minted_by.insert(&owner_id, &total_supply);
         near_contract_standards::fungible_token::events::FtMint {
             owner_id: &owner_id,
             amount: &total_supply.into(),
@@ -107,9 +172,14 @@ impl Contract {
 
     fn on_account_closed(&mut self, account_id: AccountId, balance: Balance) {
         log!("Closed @{} with {}", account_id, balance);
+        self.internal_seed_checkpoint(&account_id, balance);
+        self.internal_checkpoint(&[&account_id]);
+        if balance > 0 {
+            self.internal_supply_checkpoint();
+        }
     }
 
-    pub fn on_tokens_burned(&mut self, account_id: AccountId, amount: U128) {
+    fn on_tokens_burned(&mut self, account_id: AccountId, amount: U128) {
         near_contract_standards::fungible_token::events::FtBurn {
             owner_id: &account_id,
             amount: &amount,
@@ -118,7 +188,7 @@ impl Contract {
         .emit();
     }
 
-    pub fn on_tokens_minted(&mut self, account_id: AccountId, amount: u128) {
+    fn on_tokens_minted(&mut self, account_id: AccountId, amount: u128) {
         near_contract_standards::fungible_token::events::FtMint {
             owner_id: &account_id,
             amount: &amount.into(),
@@ -127,18 +197,88 @@ impl Contract {
         .emit();
     }
 
+    fn assert_owner(&self) {
+        assert_eq!(
+            env::predecessor_account_id(),
+            self.owner_id,
+            "Can only be called by the owner"
+        );
+    }
+
+    /// Mints `amount` tokens to `account_id`. Only allowlisted minters can call it and the
+    /// total supply can never go above the maximum supply.
     pub fn mint_token(&mut self, account_id: AccountId, amount: u128) {
-        self.token.internal_deposit(&account_id, amount.into());
+        let minter = env::predecessor_account_id();
+        assert!(self.minters.contains(&minter), "You're not authorised to mint tokens");
+        self.assert_not_frozen(&[&account_id]);
+        assert!(
+            self.token.total_supply + amount <= self.max_supply,
+            "Minting would exceed the maximum supply"
+        );
+        self.internal_seed_checkpoints(&[&account_id]);
+        self.token.internal_deposit(&account_id, amount);
+        self.internal_checkpoint(&[&account_id]);
+        self.internal_supply_checkpoint();
+        let minted = self.minted_by.get(&minter).unwrap_or(0);
+        self.minted_by.insert(&minter, &(minted + amount));
         self.on_tokens_minted(account_id, amount);
     }
 
+    /// Burns `amount` tokens of `account_id`. Can be called by the holder or an authorized burner.
     pub fn burn_token(&mut self, account_id: AccountId, amount: u128) {
-        self.token.internal_withdraw(&account_id, amount.into());
+        let caller = env::predecessor_account_id();
+        assert!(
+            caller == account_id || self.burners.contains(&caller),
+            "You're not authorised to burn these tokens"
+        );
+        self.internal_seed_checkpoints(&[&account_id]);
+        self.token.internal_withdraw(&account_id, amount);
+        self.internal_checkpoint(&[&account_id]);
+        self.internal_supply_checkpoint();
+        self.on_tokens_burned(account_id, amount.into());
+    }
+
+    pub fn add_minter(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        self.minters.insert(&account_id);
+    }
+
+    pub fn remove_minter(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        self.minters.remove(&account_id);
+    }
+
+    pub fn add_burner(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        self.burners.insert(&account_id);
+    }
+
+    pub fn remove_burner(&mut self, account_id: AccountId) {
+        self.assert_owner();
+        self.burners.remove(&account_id);
+    }
+
+    pub fn get_minters(&self) -> Vec<AccountId> {
+        self.minters.to_vec()
+    }
+
+    pub fn get_burners(&self) -> Vec<AccountId> {
+        self.burners.to_vec()
+    }
+
+    pub fn get_max_supply(&self) -> U128 {
+        self.max_supply.into()
+    }
+
+    /// Total amount of tokens issued by `minter`, the initial supply is counted for the owner.
+    pub fn minted_by(&self, minter: AccountId) -> U128 {
+        self.minted_by.get(&minter).unwrap_or(0).into()
     }
 
     // stake function
     pub fn stake(&self, amount: u128) -> Promise {
-        let account_reward = "rewarder_contract.testnet".to_string().try_into().unwrap();
+        self.assert_not_frozen(&[&env::signer_account_id()]);
+        let account_reward: AccountId = REWARDER_ACCOUNT.parse().unwrap();
         let p = ext_ft::ext(account_reward)
             .with_static_gas(Gas(5 * TGAS))
             .add_staker(env::signer_account_id().to_string(), amount);
@@ -159,7 +299,63 @@ impl Contract {
     }
 }
 
-near_contract_standards::impl_fungible_token_core!(Contract, token);
+#[near_bindgen]
+impl FungibleTokenCore for Contract {
+    #[payable]
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>) {
+        let sender_id = env::predecessor_account_id();
+        self.assert_not_frozen(&[&sender_id, &receiver_id]);
+        self.internal_seed_checkpoints(&[&sender_id, &receiver_id]);
+        self.token.ft_transfer(receiver_id.clone(), amount, memo);
+        self.internal_checkpoint(&[&sender_id, &receiver_id]);
+    }
+
+    #[payable]
+    fn ft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let sender_id = env::predecessor_account_id();
+        self.assert_not_frozen(&[&sender_id, &receiver_id]);
+        self.internal_seed_checkpoints(&[&sender_id, &receiver_id]);
+        let promise = self.token.ft_transfer_call(receiver_id.clone(), amount, memo, msg);
+        self.internal_checkpoint(&[&sender_id, &receiver_id]);
+        promise
+    }
+
+    fn ft_total_supply(&self) -> U128 {
+        self.token.ft_total_supply()
+    }
+
+    fn ft_balance_of(&self, account_id: AccountId) -> U128 {
+        self.token.ft_balance_of(account_id)
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenResolver for Contract {
+    // Refunds of ft_transfer_call move tokens back to the sender, so both balances are recorded,
+    // a refund to a closed account burns the tokens
+    #[private]
+    fn ft_resolve_transfer(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> U128 {
+        let (used_amount, burned_amount) =
+            self.token.internal_ft_resolve_transfer(&sender_id, receiver_id.clone(), amount);
+        self.internal_checkpoint(&[&sender_id, &receiver_id]);
+        if burned_amount > 0 {
+            self.internal_supply_checkpoint();
+        }
+        used_amount.into()
+    }
+}
+
 near_contract_standards::impl_fungible_token_storage!(Contract, token, on_account_closed);
 
 #[near_bindgen]
@@ -168,10 +364,59 @@ impl FungibleTokenMetadataProvider for Contract {
         self.metadata.get().unwrap()
     }
 }
+
+#[near_bindgen]
+impl Contract {
+    /// Updates the token metadata, only the given fields are changed. `reference` and
+    /// `reference_hash` have to be set together.
+    pub fn set_metadata(
+        &mut self,
+        name: Option<String>,
+        symbol: Option<String>,
+        icon: Option<String>,
+        reference: Option<String>,
+        reference_hash: Option<Base64VecU8>,
+    ) {
+        self.assert_owner();
+        let mut metadata = self.metadata.get().unwrap();
+        if let Some(name) = name {
+            metadata.name = name;
+        }
+        if let Some(symbol) = symbol {
+            metadata.symbol = symbol;
+        }
+        let icon_updated = icon.is_some();
+        if icon.is_some() {
+            metadata.icon = icon;
+        }
+        if reference.is_some() {
+            metadata.reference = reference;
+        }
+        if reference_hash.is_some() {
+            metadata.reference_hash = reference_hash;
+        }
+        assert!(
+            !metadata.name.is_empty() && !metadata.symbol.is_empty(),
+            "Name and symbol can't be empty"
+        );
+        metadata.assert_valid();
+        self.metadata.set(&metadata);
+        LtsEvent::MetadataUpdate(&[FtMetadataUpdateData {
+            name: &metadata.name,
+            symbol: &metadata.symbol,
+            icon_updated,
+            reference: metadata.reference.as_deref(),
+            reference_hash: metadata.reference_hash.as_ref(),
+        }])
+        .emit();
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
+    use std::convert::TryFrom;
 
     //testing internal functions
     // start
@@ -181,7 +426,7 @@ mod tests {
 
         //initiliazing the contract
         let signer: AccountId = env::signer_account_id();
-        let contract = Contract::new_default_meta(signer, 100);
+        let contract = Contract::new_default_meta(signer, 100, 1000);
 
         //getting the balance of owner == total supply
         let balance = contract
@@ -197,7 +442,7 @@ mod tests {
 
         //initiliazing the contract
         let signer: AccountId = env::signer_account_id();
-        let mut contract = Contract::new_default_meta(signer, 100);
+        let mut contract = Contract::new_default_meta(signer, 100, 1000);
 
         //setting up the account
         let issam: AccountId = "issameths.testnet".parse().unwrap();
@@ -218,7 +463,7 @@ mod tests {
 
         //initiliazing the contract with 100 total supply
         let signer: AccountId = env::signer_account_id();
-        let mut contract = Contract::new_default_meta(signer, 100);
+        let mut contract = Contract::new_default_meta(signer, 100, 1000);
 
         //setting up the account Id
         let issam: AccountId = "issameths.testnet".parse().unwrap();
@@ -242,7 +487,7 @@ mod tests {
 
         //initiliazing the contract with 100 total supply
         let signer: AccountId = env::signer_account_id();
-        let mut contract = Contract::new_default_meta(signer, 100);
+        let mut contract = Contract::new_default_meta(signer, 100, 1000);
 
         //setting up the account Id
         let issam: AccountId = "issameths.testnet".parse().unwrap();
@@ -262,4 +507,485 @@ mod tests {
         //assertion
         assert_eq!(balance_issam, 20);
     }
+
+    fn context(predecessor: &str) -> near_sdk::test_utils::VMContextBuilder {
+        let mut builder = near_sdk::test_utils::VMContextBuilder::new();
+        builder
+            .current_account_id("light-token.testnet".parse().unwrap())
+            .predecessor_account_id(predecessor.parse().unwrap())
+            .signer_account_id(predecessor.parse().unwrap());
+        builder
+    }
+
+    fn contract_with_minter() -> Contract {
+        near_sdk::testing_env!(context("lightency.testnet").build());
+        let mut contract = Contract::new_default_meta("lightency.testnet".parse().unwrap(), 100, 1000);
+        contract.add_minter("vesting.testnet".parse().unwrap());
+        contract
+            .token
+            .internal_register_account(&"issameths.testnet".parse().unwrap());
+        contract
+    }
+
+    #[test]
+    fn test_mint_by_minter() {
+        let mut contract = contract_with_minter();
+        let issam: AccountId = "issameths.testnet".parse().unwrap();
+
+        //minting from the vesting contract
+        near_sdk::testing_env!(context("vesting.testnet").build());
+        contract.mint_token(issam.clone(), 50);
+
+        //the supply and the minter record follow the issuance
+        assert_eq!(contract.ft_total_supply(), U128(150));
+        assert_eq!(contract.minted_by("vesting.testnet".parse().unwrap()), U128(50));
+        assert_eq!(contract.minted_by("lightency.testnet".parse().unwrap()), U128(100));
+        assert_eq!(contract.ft_balance_of(issam), U128(50));
+    }
+
+    #[test]
+    #[should_panic(expected = "You're not authorised to mint tokens")]
+    fn test_mint_not_minter() {
+        let mut contract = contract_with_minter();
+        near_sdk::testing_env!(context("issameths.testnet").build());
+        contract.mint_token("issameths.testnet".parse().unwrap(), 50);
+    }
+
+    #[test]
+    #[should_panic(expected = "Minting would exceed the maximum supply")]
+    fn test_mint_above_max_supply() {
+        let mut contract = contract_with_minter();
+        near_sdk::testing_env!(context("vesting.testnet").build());
+        contract.mint_token("issameths.testnet".parse().unwrap(), 901);
+    }
+
+    #[test]
+    fn test_burn() {
+        let mut contract = contract_with_minter();
+
+        //the holder burns its own tokens
+        contract.burn_token("lightency.testnet".parse().unwrap(), 40);
+        assert_eq!(contract.ft_total_supply(), U128(60));
+
+        //an authorized burner burns tokens of a holder
+        contract.add_burner("treasurydao.testnet".parse().unwrap());
+        near_sdk::testing_env!(context("treasurydao.testnet").build());
+        contract.burn_token("lightency.testnet".parse().unwrap(), 10);
+        assert_eq!(contract.ft_total_supply(), U128(50));
+    }
+
+    #[test]
+    #[should_panic(expected = "You're not authorised to burn these tokens")]
+    fn test_burn_not_holder() {
+        let mut contract = contract_with_minter();
+        near_sdk::testing_env!(context("issameths.testnet").build());
+        contract.burn_token("lightency.testnet".parse().unwrap(), 40);
+    }
+
+    #[test]
+    fn test_approve_and_transfer_from() {
+        let mut contract = contract_with_minter();
+        let issam: AccountId = "issameths.testnet".parse().unwrap();
+        let payroll: AccountId = "payroll.testnet".parse().unwrap();
+
+        //the owner approves the payroll bot
+        near_sdk::testing_env!(context("lightency.testnet")
+            .attached_deposit(near_sdk::ONE_NEAR / 100)
+            .build());
+        contract.ft_approve(payroll.clone(), U128(30), None);
+        assert_eq!(contract.ft_allowance("lightency.testnet".parse().unwrap(), payroll.clone()), U128(30));
+
+        //the bot spends part of the allowance
+        near_sdk::testing_env!(context("payroll.testnet").attached_deposit(1).build());
+        contract.ft_transfer_from("lightency.testnet".parse().unwrap(), issam.clone(), U128(20), None);
+        assert_eq!(contract.ft_balance_of(issam), U128(20));
+        assert_eq!(contract.ft_allowance("lightency.testnet".parse().unwrap(), payroll), U128(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "The allowance is too low")]
+    fn test_transfer_from_above_allowance() {
+        let mut contract = contract_with_minter();
+        near_sdk::testing_env!(context("lightency.testnet")
+            .attached_deposit(near_sdk::ONE_NEAR / 100)
+            .build());
+        contract.ft_approve("payroll.testnet".parse().unwrap(), U128(30), None);
+        near_sdk::testing_env!(context("payroll.testnet").attached_deposit(1).build());
+        contract.ft_transfer_from("lightency.testnet".parse().unwrap(), "issameths.testnet".parse().unwrap(), U128(31), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "The allowance has expired")]
+    fn test_transfer_from_expired() {
+        let mut contract = contract_with_minter();
+        near_sdk::testing_env!(context("lightency.testnet")
+            .attached_deposit(near_sdk::ONE_NEAR / 100)
+            .block_timestamp(1_000)
+            .build());
+        contract.ft_approve("payroll.testnet".parse().unwrap(), U128(30), Some(2_000.into()));
+        near_sdk::testing_env!(context("payroll.testnet")
+            .attached_deposit(1)
+            .block_timestamp(3_000)
+            .build());
+        assert_eq!(contract.ft_allowance("lightency.testnet".parse().unwrap(), "payroll.testnet".parse().unwrap()), U128(0));
+        contract.ft_transfer_from("lightency.testnet".parse().unwrap(), "issameths.testnet".parse().unwrap(), U128(10), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "to cover the storage of the approval")]
+    fn test_approve_without_storage_deposit() {
+        let mut contract = contract_with_minter();
+        contract.ft_approve("payroll.testnet".parse().unwrap(), U128(30), None);
+    }
+
+    // signs `permit` with the test key
+    fn sign(secret: &ed25519_dalek::SecretKey, permit: &Permit) -> near_sdk::json_types::Base64VecU8 {
+        let public = ed25519_dalek::PublicKey::from(secret);
+        let digest = env::sha256(&near_sdk::borsh::BorshSerialize::try_to_vec(&crate::permit::PermitMessage {
+            domain: crate::permit::PERMIT_DOMAIN.to_string(),
+            token_id: env::current_account_id(),
+            owner_id: permit.owner_id.clone(),
+            spender_id: permit.spender_id.clone(),
+            amount: permit.amount.0,
+            nonce: permit.nonce.0,
+            deadline: permit.deadline.0,
+        }).unwrap());
+        let signature = ed25519_dalek::ExpandedSecretKey::from(secret).sign(&digest, &public);
+        signature.to_bytes().to_vec().into()
+    }
+
+    // contract where lightency.testnet registered a permit key, with the permit it signs
+    fn contract_with_permit() -> (Contract, Permit, ed25519_dalek::SecretKey) {
+        let mut contract = contract_with_minter();
+        let secret = ed25519_dalek::SecretKey::from_bytes(&[7u8; 32]).unwrap();
+        let public = ed25519_dalek::PublicKey::from(&secret);
+        let mut key = vec![0u8];
+        key.extend_from_slice(public.as_bytes());
+        let public_key = PublicKey::try_from(key).unwrap();
+        near_sdk::testing_env!(context("lightency.testnet")
+            .attached_deposit(near_sdk::ONE_NEAR / 100)
+            .build());
+        contract.ft_register_permit_key(public_key.clone());
+        let permit = Permit {
+            owner_id: "lightency.testnet".parse().unwrap(),
+            public_key,
+            spender_id: "relayer.testnet".parse().unwrap(),
+            amount: U128(25),
+            nonce: 0.into(),
+            deadline: 10_000.into(),
+        };
+        (contract, permit, secret)
+    }
+
+    #[test]
+    #[should_panic(expected = "to cover the storage of the permit key")]
+    fn test_register_permit_key_without_deposit() {
+        let mut contract = contract_with_minter();
+        let mut key = vec![0u8];
+        key.extend_from_slice(&[7u8; 32]);
+        contract.ft_register_permit_key(PublicKey::try_from(key).unwrap());
+    }
+
+    #[test]
+    fn test_permit() {
+        let (mut contract, permit, secret) = contract_with_permit();
+        let signature = sign(&secret, &permit);
+
+        //the relayer submits the permit and pays the storage
+        near_sdk::testing_env!(context("relayer.testnet")
+            .attached_deposit(near_sdk::ONE_NEAR / 100)
+            .block_timestamp(1_000)
+            .build());
+        contract.ft_permit(permit, signature);
+        assert_eq!(
+            contract.ft_allowance("lightency.testnet".parse().unwrap(), "relayer.testnet".parse().unwrap()),
+            U128(25)
+        );
+        assert_eq!(contract.ft_permit_nonce("lightency.testnet".parse().unwrap()), 1.into());
+    }
+
+    #[test]
+    #[should_panic(expected = "to cover the storage of the permit nonce")]
+    fn test_permit_nonce_without_deposit() {
+        let (mut contract, mut permit, secret) = contract_with_permit();
+        //a zero allowance stores no approval, the nonce still has to be paid
+        permit.amount = U128(0);
+        let signature = sign(&secret, &permit);
+        near_sdk::testing_env!(context("relayer.testnet").block_timestamp(1_000).build());
+        contract.ft_permit(permit, signature);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid permit nonce")]
+    fn test_permit_replay() {
+        let (mut contract, permit, secret) = contract_with_permit();
+        let signature = sign(&secret, &permit);
+        near_sdk::testing_env!(context("relayer.testnet")
+            .attached_deposit(near_sdk::ONE_NEAR / 100)
+            .build());
+        contract.ft_permit(permit.clone(), signature.clone());
+        contract.ft_permit(permit, signature);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid permit signature")]
+    fn test_permit_tampered() {
+        let (mut contract, mut permit, secret) = contract_with_permit();
+        let signature = sign(&secret, &permit);
+        permit.amount = U128(100);
+        near_sdk::testing_env!(context("relayer.testnet")
+            .attached_deposit(near_sdk::ONE_NEAR / 100)
+            .build());
+        contract.ft_permit(permit, signature);
+    }
+
+    #[test]
+    #[should_panic(expected = "The permit has expired")]
+    fn test_permit_expired() {
+        let (mut contract, permit, secret) = contract_with_permit();
+        let signature = sign(&secret, &permit);
+        near_sdk::testing_env!(context("relayer.testnet").block_timestamp(20_000).build());
+        contract.ft_permit(permit, signature);
+    }
+
+    #[test]
+    fn test_balance_checkpoints() {
+        let mut contract = contract_with_minter();
+        let owner: AccountId = "lightency.testnet".parse().unwrap();
+        let issam: AccountId = "issameths.testnet".parse().unwrap();
+
+        //minting at block 10, transferring twice at block 20
+        near_sdk::testing_env!(context("vesting.testnet").block_index(10).build());
+        contract.mint_token(issam.clone(), 50);
+        near_sdk::testing_env!(context("lightency.testnet").block_index(20).attached_deposit(1).build());
+        contract.ft_transfer(issam.clone(), U128(30), None);
+        contract.ft_transfer(issam.clone(), U128(10), None);
+
+        //the history is kept block by block
+        assert_eq!(contract.ft_balance_at(owner.clone(), 0.into()), U128(100));
+        assert_eq!(contract.ft_balance_at(owner, 20.into()), U128(60));
+        assert_eq!(contract.ft_balance_at(issam.clone(), 5.into()), U128(0));
+        assert_eq!(contract.ft_balance_at(issam.clone(), 15.into()), U128(50));
+        assert_eq!(contract.ft_balance_at(issam.clone(), 100.into()), U128(90));
+        assert_eq!(contract.checkpoints.get(&issam).unwrap().len(), 2);
+        assert_eq!(contract.total_supply_at(9.into()), U128(100));
+        assert_eq!(contract.total_supply_at(10.into()), U128(150));
+        //transfers don't change the supply, so they don't record it
+        assert_eq!(contract.supply_checkpoints.len(), 2);
+    }
+
+    #[test]
+    fn test_checkpoint_storage_covered_by_registration() {
+        let mut contract = contract_with_minter();
+        let account_id: AccountId = "b".repeat(64).parse().unwrap();
+
+        //the storage of the account and its first checkpoint fits in the registration deposit
+        let initial_storage = env::storage_usage();
+        contract.token.internal_register_account(&account_id);
+        contract.internal_checkpoint(&[&account_id]);
+        assert!(env::storage_usage() - initial_storage <= contract.token.account_storage_usage);
+    }
+
+    #[test]
+    fn test_checkpoint_history_capped() {
+        let mut contract = contract_with_minter();
+        let owner: AccountId = "lightency.testnet".parse().unwrap();
+        let issam: AccountId = "issameths.testnet".parse().unwrap();
+        let account_storage = env::storage_usage();
+
+        //one transfer per block from block 10 to block 21, the issam history wraps around
+        for block in 0..MAX_CHECKPOINTS + 4 {
+            near_sdk::testing_env!(context("lightency.testnet")
+                .block_index(10 + block)
+                .storage_usage(env::storage_usage())
+                .attached_deposit(1)
+                .build());
+            contract.ft_transfer(issam.clone(), U128(1), None);
+        }
+        assert_eq!(contract.checkpoints.get(&issam).unwrap().len(), MAX_CHECKPOINTS);
+        //the full histories of both accounts are paid by their registrations, and don't grow anymore
+        let full_storage = env::storage_usage();
+        assert!(full_storage - account_storage <= 2 * contract.token.account_storage_usage);
+        near_sdk::testing_env!(context("lightency.testnet")
+            .block_index(1000)
+            .storage_usage(full_storage)
+            .attached_deposit(1)
+            .build());
+        contract.ft_transfer(issam.clone(), U128(1), None);
+        assert_eq!(env::storage_usage(), full_storage);
+
+        //only the last MAX_CHECKPOINTS blocks are kept, from block 15 to block 1000
+        assert_eq!(contract.ft_balance_at(issam.clone(), 14.into()), U128(0));
+        assert_eq!(contract.ft_balance_at(issam.clone(), 15.into()), U128(6));
+        assert_eq!(contract.ft_balance_at(issam.clone(), 20.into()), U128(11));
+        assert_eq!(contract.ft_balance_at(issam.clone(), 999.into()), U128(12));
+        assert_eq!(contract.ft_balance_at(issam, 1000.into()), U128(13));
+        assert_eq!(contract.ft_balance_at(owner, 21.into()), U128(88));
+    }
+
+    #[test]
+    fn test_burn_checkpoints() {
+        let mut contract = contract_with_minter();
+        let owner: AccountId = "lightency.testnet".parse().unwrap();
+
+        //burning at block 30
+        near_sdk::testing_env!(context("lightency.testnet").block_index(30).build());
+        contract.burn_token(owner.clone(), 40);
+
+        assert_eq!(contract.ft_balance_at(owner.clone(), 29.into()), U128(100));
+        assert_eq!(contract.ft_balance_at(owner, 30.into()), U128(60));
+        assert_eq!(contract.total_supply_at(30.into()), U128(60));
+    }
+
+    #[test]
+    fn test_set_metadata() {
+        let mut contract = contract_with_minter();
+        near_sdk::testing_env!(context("lightency.testnet").build());
+        contract.set_metadata(
+            Some("Lightency".to_string()),
+            None,
+            Some("https://lightency.io/lts.svg".to_string()),
+            Some("https://lightency.io/lts.json".to_string()),
+            Some(Base64VecU8(vec![1; 32])),
+        );
+
+        let metadata = contract.ft_metadata();
+        assert_eq!(metadata.name, "Lightency");
+        assert_eq!(metadata.symbol, "LTS");
+        assert_eq!(metadata.icon.unwrap(), "https://lightency.io/lts.svg");
+        assert_eq!(metadata.reference.unwrap(), "https://lightency.io/lts.json");
+        assert!(near_sdk::test_utils::get_logs()[0].contains("ft_metadata_update"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by the owner")]
+    fn test_set_metadata_not_owner() {
+        let mut contract = contract_with_minter();
+        near_sdk::testing_env!(context("issameths.testnet").build());
+        contract.set_metadata(Some("Scam".to_string()), None, None, None, None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_set_metadata_reference_without_hash() {
+        let mut contract = contract_with_minter();
+        near_sdk::testing_env!(context("lightency.testnet").build());
+        let reference = Some("https://lightency.io/lts.json".to_string());
+        contract.set_metadata(None, None, None, reference, None);
+    }
+
+    fn contract_with_compliance() -> Contract {
+        let mut contract = contract_with_minter();
+        near_sdk::testing_env!(context("lightency.testnet").build());
+        contract.add_compliance("compliance.testnet".parse().unwrap());
+        near_sdk::testing_env!(context("compliance.testnet").build());
+        contract.freeze("issameths.testnet".parse().unwrap(), Some("sanctioned".to_string()));
+        contract
+    }
+
+    #[test]
+    fn test_freeze_and_unfreeze() {
+        let mut contract = contract_with_compliance();
+        let issam: AccountId = "issameths.testnet".parse().unwrap();
+        assert!(contract.is_frozen(issam.clone()));
+        assert!(near_sdk::test_utils::get_logs()[0].contains("ft_freeze"));
+
+        contract.unfreeze(issam.clone(), None);
+        assert!(!contract.is_frozen(issam.clone()));
+
+        //transfers work again once unfrozen
+        near_sdk::testing_env!(context("lightency.testnet").attached_deposit(1).build());
+        contract.ft_transfer(issam.clone(), U128(10), None);
+        assert_eq!(contract.ft_balance_of(issam), U128(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "The account issameths.testnet is frozen")]
+    fn test_transfer_to_frozen() {
+        let mut contract = contract_with_compliance();
+        near_sdk::testing_env!(context("lightency.testnet").attached_deposit(1).build());
+        contract.ft_transfer("issameths.testnet".parse().unwrap(), U128(10), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "The account issameths.testnet is frozen")]
+    fn test_mint_to_frozen() {
+        let mut contract = contract_with_compliance();
+        near_sdk::testing_env!(context("vesting.testnet").build());
+        contract.mint_token("issameths.testnet".parse().unwrap(), 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the compliance role can freeze accounts")]
+    fn test_freeze_not_compliance() {
+        let mut contract = contract_with_minter();
+        near_sdk::testing_env!(context("lightency.testnet").build());
+        contract.freeze("issameths.testnet".parse().unwrap(), None);
+    }
+
+    //testing the upgrade of the token deployed before versioning
+    #[test]
+    fn test_migrate_from_v0() {
+        near_sdk::testing_env!(context("light-token.testnet").block_index(7).build());
+        let issam: AccountId = "issameths.testnet".parse().unwrap();
+        let mut token = FungibleToken::new(b"a".to_vec());
+        token.internal_register_account(&issam);
+        token.internal_deposit(&issam, 40);
+        env::state_write(&ContractV0 {
+            token,
+            metadata: LazyOption::new(b"m".to_vec(), None),
+        });
+
+        let mut contract = Contract::migrate("lightency.testnet".parse().unwrap(), U128(1000));
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert_eq!(contract.ft_balance_of(issam), U128(40));
+        assert_eq!(contract.get_max_supply(), U128(1000));
+        assert_eq!(contract.minted_by("lightency.testnet".parse().unwrap()), U128(40));
+        assert_eq!(contract.total_supply_at(near_sdk::json_types::U64(7)), U128(40));
+
+        //the rewarder can mint right after the upgrade
+        near_sdk::testing_env!(context(REWARDER_ACCOUNT).build());
+        contract.mint_token("issameths.testnet".parse().unwrap(), 10);
+        assert_eq!(contract.ft_total_supply(), U128(50));
+    }
+
+    //testing the balances at past blocks of the holders from before the upgrade
+    #[test]
+    fn test_balance_at_after_migrate() {
+        near_sdk::testing_env!(context("light-token.testnet").block_index(7).build());
+        let issam: AccountId = "issameths.testnet".parse().unwrap();
+        let oussema: AccountId = "oussema.testnet".parse().unwrap();
+        let mut token = FungibleToken::new(b"a".to_vec());
+        token.internal_register_account(&issam);
+        token.internal_register_account(&oussema);
+        token.internal_deposit(&issam, 40);
+        env::state_write(&ContractV0 {
+            token,
+            metadata: LazyOption::new(b"m".to_vec(), None),
+        });
+        let mut contract = Contract::migrate("lightency.testnet".parse().unwrap(), U128(1000));
+
+        //without a history the balance is the one held since the upgrade
+        assert_eq!(contract.ft_balance_at(issam.clone(), 7.into()), U128(40));
+
+        //the first transfer keeps the balances before it
+        near_sdk::testing_env!(context("issameths.testnet").block_index(20).attached_deposit(1).build());
+        contract.ft_transfer(oussema.clone(), U128(15), None);
+        assert_eq!(contract.ft_balance_at(issam.clone(), 10.into()), U128(40));
+        assert_eq!(contract.ft_balance_at(issam, 20.into()), U128(25));
+        assert_eq!(contract.ft_balance_at(oussema.clone(), 10.into()), U128(0));
+        assert_eq!(contract.ft_balance_at(oussema, 20.into()), U128(15));
+    }
+
+    //testing that migrating the current layout keeps it as it is
+    #[test]
+    fn test_migrate_current() {
+        let contract = contract_with_minter();
+        env::state_write(&contract);
+        near_sdk::testing_env!(context("light-token.testnet").build());
+        let contract = Contract::migrate("oussema.testnet".parse().unwrap(), U128(1));
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert_eq!(contract.get_max_supply(), U128(1000));
+        assert!(contract.get_minters().contains(&"vesting.testnet".parse().unwrap()));
+    }
 }
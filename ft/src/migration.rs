@@ -0,0 +1,95 @@
+//! Upgrade of the LTS token deployed before the supply cap, the roles and the snapshots.
+
+use near_contract_standards::fungible_token::metadata::FungibleTokenMetadata;
+use near_contract_standards::fungible_token::FungibleToken;
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedSet, Vector};
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, AccountId};
+
+use crate::{Contract, ContractExt};
+
+/// Version of the layout written by this code, bumped with every change of the layout.
+pub const STATE_VERSION: u8 = 1;
+
+/// Contract minting the staking rewards, allowed to mint from the start.
+pub const REWARDER_ACCOUNT: &str = "rewarder_contract.testnet";
+
+/// Layout deployed before the state was versioned.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct ContractV0 {
+    pub token: FungibleToken,
+    pub metadata: LazyOption<FungibleTokenMetadata>,
+}
+
+pub enum VersionedContract {
+    V0(ContractV0),
+    V1(Box<Contract>),
+}
+
+impl VersionedContract {
+    /// Reads the state with the layout of its version. The versioned layout ends with
+    /// `state_version`, the unversioned one with the storage prefix of the metadata.
+    pub fn read() -> Self {
+        let bytes = env::storage_read(b"STATE").expect("The contract isn't initialized");
+        match bytes.last().copied() {
+            Some(STATE_VERSION) => VersionedContract::V1(Box::new(
+                Contract::try_from_slice(&bytes).expect("The state doesn't match its version"),
+            )),
+            _ => VersionedContract::V0(
+                ContractV0::try_from_slice(&bytes).expect("The state doesn't match its version"),
+            ),
+        }
+    }
+
+    /// Converts any layout to the current one. The unversioned token had no owner and no cap, the
+    /// supply it already issued is counted for `owner_id`.
+    pub fn into_current(self, owner_id: AccountId, max_supply: u128) -> Contract {
+        match self {
+            VersionedContract::V0(state) => {
+                assert!(
+                    state.token.total_supply <= max_supply,
+                    "The total supply exceeds the maximum supply"
+                );
+                let mut contract = Contract {
+                    token: state.token,
+                    metadata: state.metadata,
+                    owner_id: owner_id.clone(),
+                    max_supply,
+                    minters: UnorderedSet::new(b"n".to_vec()),
+                    burners: UnorderedSet::new(b"b".to_vec()),
+                    minted_by: LookupMap::new(b"s".to_vec()),
+                    allowances: LookupMap::new(b"l".to_vec()),
+                    permit_keys: LookupMap::new(b"k".to_vec()),
+                    permit_nonces: LookupMap::new(b"p".to_vec()),
+                    checkpoints: LookupMap::new(b"c".to_vec()),
+                    supply_checkpoints: Vector::new(b"t".to_vec()),
+                    compliance: UnorderedSet::new(b"g".to_vec()),
+                    frozen: UnorderedSet::new(b"f".to_vec()),
+                    state_version: STATE_VERSION,
+                };
+                contract.minters.insert(&REWARDER_ACCOUNT.parse().unwrap());
+                contract.minted_by.insert(&owner_id, &contract.token.total_supply);
+                contract.internal_measure_checkpoint_storage();
+                contract.internal_supply_checkpoint();
+                contract
+            }
+            VersionedContract::V1(state) => *state,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Upgrades the stored state after deploying a new version of the code. `owner_id` and
+    /// `max_supply` are only used by the unversioned layout.
+    #[init(ignore_state)]
+    #[private]
+    pub fn migrate(owner_id: AccountId, max_supply: U128) -> Self {
+        VersionedContract::read().into_current(owner_id, max_supply.0)
+    }
+
+    pub fn get_state_version(&self) -> u8 {
+        self.state_version
+    }
+}
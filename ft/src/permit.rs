@@ -0,0 +1,160 @@
+//! Gasless approvals.
+//!
+//! The owner signs a permit off-chain with an ed25519 key and a relayer submits it with
+//! `ft_permit`, which grants the allowance as if the owner had called `ft_approve`. NEAR
+//! contracts can't read the access keys of an account, so the signing key must either be the
+//! key of an implicit account (the account id is the hex encoded public key) or have been
+//! registered by the owner with `ft_register_permit_key`.
+//!
+//! The signed message is the sha256 of the borsh serialized [`PermitMessage`], see
+//! `ft_permit_digest`. Each permit consumes the next nonce of the owner, so it can only be used
+//! once, and can't be used after its deadline.
+
+use std::convert::TryFrom;
+
+use ed25519_dalek::Verifier;
+use near_sdk::borsh::{self, BorshSerialize};
+use near_sdk::json_types::{Base64VecU8, U128, U64};
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::{env, near_bindgen, AccountId, Balance, CurveType, PublicKey};
+
+use crate::events::{FtPermitData, LtsEvent};
+use crate::{settle_storage, Contract, ContractExt};
+
+pub const PERMIT_DOMAIN: &str = "lts-permit";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Permit {
+    pub owner_id: AccountId,
+    pub public_key: PublicKey,
+    pub spender_id: AccountId,
+    pub amount: U128,
+    pub nonce: U64,
+    /// Block timestamp (nanoseconds) after which the permit is rejected.
+    pub deadline: U64,
+}
+
+/// What the owner signs. Includes the token contract so a permit can't be replayed elsewhere.
+#[derive(BorshSerialize)]
+pub struct PermitMessage {
+    pub domain: String,
+    pub token_id: AccountId,
+    pub owner_id: AccountId,
+    pub spender_id: AccountId,
+    pub amount: u128,
+    pub nonce: u64,
+    pub deadline: u64,
+}
+
+impl Permit {
+    fn message(&self) -> PermitMessage {
+        PermitMessage {
+            domain: PERMIT_DOMAIN.to_string(),
+            token_id: env::current_account_id(),
+            owner_id: self.owner_id.clone(),
+            spender_id: self.spender_id.clone(),
+            amount: self.amount.0,
+            nonce: self.nonce.0,
+            deadline: self.deadline.0,
+        }
+    }
+
+    fn digest(&self) -> Vec<u8> {
+        env::sha256(&self.message().try_to_vec().unwrap())
+    }
+}
+
+/// Raw 32 bytes of an ed25519 key, `None` for other curves.
+fn ed25519_bytes(public_key: &PublicKey) -> Option<&[u8]> {
+    match public_key.curve_type() {
+        CurveType::ED25519 => Some(&public_key.as_bytes()[1..]),
+        _ => None,
+    }
+}
+
+fn is_implicit_account_of(account_id: &AccountId, key: &[u8]) -> bool {
+    let hex: String = key.iter().map(|b| format!("{:02x}", b)).collect();
+    account_id.as_str() == hex
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Registers the key the caller signs permits with, replacing the previous one. The caller
+    /// pays the storage of the key with the attached deposit, the rest is refunded.
+    #[payable]
+    pub fn ft_register_permit_key(&mut self, public_key: PublicKey) {
+        assert!(ed25519_bytes(&public_key).is_some(), "Only ed25519 keys are supported");
+        let initial_storage = env::storage_usage();
+        self.permit_keys.insert(&env::predecessor_account_id(), &public_key);
+        settle_storage(initial_storage, env::attached_deposit(), "permit key");
+    }
+
+    /// Removes the permit key of the caller and refunds its storage.
+    pub fn ft_unregister_permit_key(&mut self) {
+        let initial_storage = env::storage_usage();
+        self.permit_keys.remove(&env::predecessor_account_id());
+        settle_storage(initial_storage, 0, "permit key");
+    }
+
+    pub fn ft_permit_key(&self, account_id: AccountId) -> Option<PublicKey> {
+        self.permit_keys.get(&account_id)
+    }
+
+    /// Nonce the next permit of `account_id` must use.
+    pub fn ft_permit_nonce(&self, account_id: AccountId) -> U64 {
+        self.permit_nonces.get(&account_id).unwrap_or(0).into()
+    }
+
+    /// Hash the owner has to sign for `permit`.
+    pub fn ft_permit_digest(&self, permit: Permit) -> Base64VecU8 {
+        permit.digest().into()
+    }
+
+    /// Grants the allowance described by `permit` if `signature` is valid. Callable by anyone,
+    /// the caller pays the storage of the approval with the attached deposit.
+    #[payable]
+    pub fn ft_permit(&mut self, permit: Permit, signature: Base64VecU8) {
+        assert!(env::block_timestamp() <= permit.deadline.0, "The permit has expired");
+        let expected_nonce = self.permit_nonces.get(&permit.owner_id).unwrap_or(0);
+        assert_eq!(permit.nonce.0, expected_nonce, "Invalid permit nonce");
+
+        let key = ed25519_bytes(&permit.public_key).expect("Only ed25519 keys are supported");
+        let authorized = is_implicit_account_of(&permit.owner_id, key)
+            || self.permit_keys.get(&permit.owner_id).as_ref() == Some(&permit.public_key);
+        assert!(authorized, "The key is not allowed to sign permits for this account");
+
+        let public_key =
+            ed25519_dalek::PublicKey::from_bytes(key).expect("Invalid ed25519 public key");
+        let signature = ed25519_dalek::Signature::try_from(signature.0.as_slice())
+            .expect("Invalid ed25519 signature");
+        assert!(
+            public_key.verify(&permit.digest(), &signature).is_ok(),
+            "Invalid permit signature"
+        );
+
+        // The first permit of an owner stores its nonce, paid by the caller before the approval
+        let initial_storage = env::storage_usage();
+        self.permit_nonces.insert(&permit.owner_id, &(expected_nonce + 1));
+        let nonce_cost = (env::storage_usage() - initial_storage) as Balance * env::storage_byte_cost();
+        assert!(
+            env::attached_deposit() >= nonce_cost,
+            "Attach at least {} yoctoNEAR to cover the storage of the permit nonce",
+            nonce_cost
+        );
+        self.internal_approve(
+            &permit.owner_id,
+            &permit.spender_id,
+            permit.amount.0,
+            None,
+            env::attached_deposit() - nonce_cost,
+        );
+        LtsEvent::Permit(&[FtPermitData {
+            owner_id: &permit.owner_id,
+            spender_id: &permit.spender_id,
+            amount: permit.amount,
+            nonce: permit.nonce,
+        }])
+        .emit();
+    }
+}
@@ -0,0 +1,33 @@
+// Structured (NEP-297) logs of the staking rewarder.
+// Amounts are expressed in the smallest LTS unit (8 decimals), like the LTS token events.
+
+pub use lightency_events::Event;
+use near_sdk::json_types::U128;
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+pub struct StakingData<'a> {
+    pub account_id: &'a str,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum RewarderEvent<'a> {
+    Stake(&'a [StakingData<'a>]),
+    Unstake(&'a [StakingData<'a>]),
+    Withdraw(&'a [StakingData<'a>]),
+    RewardWithdraw(&'a [StakingData<'a>]),
+}
+
+impl Event for RewarderEvent<'_> {
+    // Schema version of each event
+    fn version(&self) -> &'static str {
+        match self {
+            RewarderEvent::Stake(_) => "1.0.0",
+            RewarderEvent::Unstake(_) => "1.0.0",
+            RewarderEvent::Withdraw(_) => "1.0.0",
+            RewarderEvent::RewardWithdraw(_) => "1.0.0",
+        }
+    }
+}
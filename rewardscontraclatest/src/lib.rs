@@ -1,11 +1,20 @@
 use near_sdk::{ext_contract};
 use serde::{Serialize, Deserialize};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::{env, near_bindgen, Gas};
+use near_sdk::{env, near_bindgen, Gas, PromiseError};
 use near_sdk::collections::{UnorderedMap};
+use near_sdk::json_types::U128;
+
+mod events;
+mod migration;
+use events::{Event, RewarderEvent, StakingData};
+pub use migration::{RewardercontractV0, VersionedRewardercontract, STATE_VERSION};
 
 pub const TGAS: u64 = 1_000_000_000_000;
 
+// 1 LTS expressed in the token smallest unit
+pub const ONE_LTS: u128 = 100_000_000;
+
 #[derive(BorshDeserialize, BorshSerialize)]
 #[derive(Serialize,Deserialize)]
 pub struct Data {
@@ -32,6 +41,7 @@ pub trait Treasury {
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct Rewardercontract {
     staker_data:UnorderedMap<String,Data>,
+    state_version: u8,
 }
 
 impl Default for Rewardercontract {
@@ -57,6 +67,7 @@ impl Rewardercontract {
         assert!(env::state_read::<Self>().is_none(), "Already initialized");
         Self {
             staker_data: UnorderedMap::new(b"m"),
+            state_version: STATE_VERSION,
         }
     }
 
@@ -72,6 +83,11 @@ impl Rewardercontract {
             "light-token.testnet".to_string(),
             "Can only be called by the LTS contract"
         );
+        RewarderEvent::Stake(&[StakingData {
+            account_id: &account,
+            amount: U128(amount * ONE_LTS),
+        }])
+        .emit();
         if self.staker_data.get(&account).is_none() {
             let data = Data {
                 amount : amount,
@@ -152,6 +168,11 @@ impl Rewardercontract {
             }else{
                 panic!("You don't have enough staked amount !!!");
             }
+            RewarderEvent::Unstake(&[StakingData {
+                account_id: &account,
+                amount: U128(amount * ONE_LTS),
+            }])
+            .emit();
         }else {
             panic!("You are not one of the stakers");
         }
@@ -166,6 +187,11 @@ impl Rewardercontract {
                     let mut data=self.get_data(account.clone());
                     data.unstaked_amount-=amount;
                     self.staker_data.insert(&account.clone(), &data);
+                    RewarderEvent::Withdraw(&[StakingData {
+                        account_id: &account,
+                        amount: U128(amount * ONE_LTS),
+                    }])
+                    .emit();
                 }
             }else {
                 panic!("You must wait 48 Hours after your last unstake");
@@ -178,11 +204,17 @@ impl Rewardercontract {
     pub fn withdraw_reward(&mut self,account:String){
         if self.check_staker(account.clone()){
             let mut data=self.get_data(account.clone());
+            let reward = (data.reward*100000000.0) as u128;
             let account_lts= "light-token.testnet".to_string().try_into().unwrap();
             ext_lts::ext(account_lts)
                 .with_static_gas(Gas(2 * TGAS))
                 .with_attached_deposit(1)
-                .ft_transfer(account.clone(),((data.reward*100000000.0) as u128).to_string(),"".to_string());
+                .ft_transfer(account.clone(),reward.to_string(),"".to_string())
+                .then(
+                    Self::ext(env::current_account_id())
+                    .with_static_gas(Gas(5 * TGAS))
+                    .withdraw_reward_callback(account.clone(), data.reward)
+                );
             data.reward=0.0;
             self.staker_data.insert(&account.clone(), &data);
         }else {
@@ -190,6 +222,23 @@ impl Rewardercontract {
         }
     }
 
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn withdraw_reward_callback(&mut self, #[callback_result] call_result: Result<(), PromiseError>, account: String, reward: f64) {
+        // Give the reward back to the staker if the transfer failed
+        if call_result.is_err() {
+            let mut data = self.get_data(account.clone());
+            data.reward += reward;
+            self.staker_data.insert(&account, &data);
+            env::log_str("There was an error contacting the token contract");
+        } else {
+            RewarderEvent::RewardWithdraw(&[StakingData {
+                account_id: &account,
+                amount: U128((reward*100000000.0) as u128),
+            }])
+            .emit();
+        }
+    }
+
     pub fn get_total_amount_per_wallet(&self, account:String) -> f64{
         self.get_data(account.clone()).amount as f64+ self.get_data(account.clone()).reward
     }
@@ -224,4 +273,94 @@ impl Rewardercontract {
             panic!("You have not earned reward yet");
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+
+    use super::*;
+
+    // context where the rewarder calls itself
+    fn owner_context() -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("rewarder_contract.testnet".parse().unwrap())
+            .predecessor_account_id("rewarder_contract.testnet".parse().unwrap());
+        builder
+    }
+
+    #[test]
+    fn test_migrate_from_v0(){
+        testing_env!(owner_context().build());
+        let mut staker_data = UnorderedMap::new(b"m");
+        staker_data.insert(&"issameths.testnet".to_string(), &Data {
+            amount: 10,
+            time: 0,
+            reward: 1.5,
+            next_reward_time: 86400000000000,
+            unstaked_amount: 0,
+            unstake_timestamp: 0
+        });
+        env::state_write(&RewardercontractV0 { staker_data });
+
+        let contract = Rewardercontract::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert!(contract.check_staker("issameths.testnet".to_string()));
+        assert_eq!(contract.get_data("issameths.testnet".to_string()).amount, 10);
+        assert_eq!(contract.get_totalstaked(), 11.5);
+    }
+
+    #[test]
+    fn test_migrate_current(){
+        testing_env!(owner_context().build());
+        env::state_write(&Rewardercontract::new());
+
+        let contract = Rewardercontract::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by owner")]
+    fn test_migrate_not_owner(){
+        testing_env!(owner_context().predecessor_account_id("issameths.testnet".parse().unwrap()).build());
+        Rewardercontract::migrate();
+    }
+
+    // staker with `reward` LTS to withdraw
+    fn contract_with_reward(reward: f64) -> Rewardercontract {
+        testing_env!(owner_context().build());
+        let mut contract = Rewardercontract::new();
+        contract.staker_data.insert(&"issameths.testnet".to_string(), &Data {
+            amount: 10,
+            time: 0,
+            reward,
+            next_reward_time: 0,
+            unstaked_amount: 0,
+            unstake_timestamp: 0
+        });
+        contract
+    }
+
+    //testing that the withdrawal is only logged once the transfer succeeded
+    #[test]
+    fn test_withdraw_reward(){
+        let mut contract = contract_with_reward(1.5);
+        contract.withdraw_reward("issameths.testnet".to_string());
+        assert_eq!(contract.get_data("issameths.testnet".to_string()).reward, 0.0);
+        assert!(near_sdk::test_utils::get_logs().is_empty());
+
+        contract.withdraw_reward_callback(Ok(()), "issameths.testnet".to_string(), 1.5);
+        assert!(near_sdk::test_utils::get_logs()[0].contains("\"amount\":\"150000000\""));
+    }
+
+    //testing that a failed transfer gives the reward back
+    #[test]
+    fn test_withdraw_reward_failed(){
+        let mut contract = contract_with_reward(1.5);
+        contract.withdraw_reward("issameths.testnet".to_string());
+        contract.withdraw_reward_callback(Err(PromiseError::Failed), "issameths.testnet".to_string(), 1.5);
+        assert_eq!(contract.get_data("issameths.testnet".to_string()).reward, 1.5);
+        assert!(!near_sdk::test_utils::get_logs().iter().any(|log| log.contains("reward_withdraw")));
+    }
+}
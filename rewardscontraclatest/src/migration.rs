@@ -0,0 +1,61 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::UnorderedMap;
+use near_sdk::{env, near_bindgen};
+
+use crate::{assert_self, Data, Rewardercontract, RewardercontractExt};
+
+// Version of the layout written by this code, bumped with every change of the layout
+pub const STATE_VERSION: u8 = 1;
+
+// Layout deployed before the state was versioned
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct RewardercontractV0 {
+    pub staker_data: UnorderedMap<String, Data>,
+}
+
+// Every layout the state has had
+pub enum VersionedRewardercontract {
+    V0(RewardercontractV0),
+    V1(Rewardercontract),
+}
+
+impl VersionedRewardercontract {
+    // Function to read the state with the layout of its version, stored in its last byte
+    // The unversioned layout ends with the prefix of the staker data instead
+    pub fn read() -> Self {
+        let bytes = env::storage_read(b"STATE").expect("The contract isn't initialized");
+        match bytes.last().copied() {
+            Some(STATE_VERSION) => VersionedRewardercontract::V1(
+                Rewardercontract::try_from_slice(&bytes).expect("The state doesn't match its version"),
+            ),
+            _ => VersionedRewardercontract::V0(
+                RewardercontractV0::try_from_slice(&bytes).expect("The state doesn't match its version"),
+            ),
+        }
+    }
+
+    // Function to convert any layout to the current one
+    pub fn into_current(self) -> Rewardercontract {
+        match self {
+            VersionedRewardercontract::V0(state) => Rewardercontract {
+                staker_data: state.staker_data,
+                state_version: STATE_VERSION,
+            },
+            VersionedRewardercontract::V1(state) => state,
+        }
+    }
+}
+
+#[near_bindgen]
+impl Rewardercontract {
+    // Function to upgrade the stored state after deploying a new version of the code
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        assert_self();
+        VersionedRewardercontract::read().into_current()
+    }
+
+    pub fn get_state_version(&self) -> u8 {
+        self.state_version
+    }
+}
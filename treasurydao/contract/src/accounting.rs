@@ -0,0 +1,165 @@
+//! Budgets and spending history of the treasury.
+//!
+//! Every outflow is taken from a budget category capped per period and is recorded with the
+//! proposal that authorized it once the transfer has succeeded.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, near_bindgen, PromiseError};
+use serde::{Deserialize, Serialize};
+
+use crate::{assert_self, TreasuryDao, TreasuryDaoExt};
+
+// 1 LTS expressed in the token smallest unit
+pub const ONE_LTS: u128 = 100_000_000;
+
+// Budget category with a spending cap per period
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Budget {
+    // "NEAR" or a whitelisted token, the cap is in its smallest unit
+    pub token: String,
+    pub cap: U128,
+    // Length of a period in nanoseconds
+    pub period: U64,
+    pub period_start: U64,
+    pub spent: U128,
+}
+
+impl Budget {
+    // Function to start a new period when the current one is over
+    fn refresh(&mut self) {
+        let now = env::block_timestamp();
+        if now >= self.period_start.0 + self.period.0 {
+            let elapsed = (now - self.period_start.0) / self.period.0;
+            self.period_start = U64(self.period_start.0 + elapsed * self.period.0);
+            self.spent = U128(0);
+        }
+    }
+
+    pub fn remaining(&self) -> u128 {
+        self.cap.0.saturating_sub(self.spent.0)
+    }
+}
+
+// Transfer out of the treasury
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Outflow {
+    pub proposal_id: Option<String>,
+    pub category: Option<String>,
+    pub receiver: String,
+    pub token: String,
+    pub amount: U128,
+    pub timestamp: U64,
+}
+
+impl TreasuryDao {
+    // Function to take `amount` out of the holdings and of the budget of `category`
+    pub(crate) fn internal_reserve(&mut self, category: Option<&String>, token: &str, amount: u128) {
+        if let Some(category) = category {
+            let mut budget = self.budgets.get(category).expect("This budget category doesn't exist");
+            assert_eq!(budget.token, token, "This budget category is for {}", budget.token);
+            budget.refresh();
+            assert!(amount <= budget.remaining(), "The budget of {} is exceeded", category);
+            budget.spent = U128(budget.spent.0 + amount);
+            self.budgets.insert(category, &budget);
+        }
+        self.internal_withdraw(token, amount);
+    }
+
+    // Function to give back a reservation whose transfer failed
+    pub(crate) fn internal_release(&mut self, category: Option<&String>, token: &str, amount: u128) {
+        if let Some(category) = category {
+            if let Some(mut budget) = self.budgets.get(category) {
+                budget.spent = U128(budget.spent.0.saturating_sub(amount));
+                self.budgets.insert(category, &budget);
+            }
+        }
+        self.internal_deposit(token, amount);
+    }
+
+    // Function to add a successful transfer to the history
+    pub(crate) fn internal_record_outflow(
+        &mut self,
+        proposal_id: Option<String>,
+        category: Option<String>,
+        receiver: String,
+        token: &str,
+        amount: u128,
+    ) {
+        self.outflows.push(&Outflow {
+            proposal_id,
+            category,
+            receiver,
+            token: token.to_string(),
+            amount: U128(amount),
+            timestamp: U64(env::block_timestamp()),
+        });
+    }
+}
+
+#[near_bindgen]
+impl TreasuryDao {
+    // Function to create or change a budget category, `cap` is spendable every `period` nanoseconds
+    pub fn set_budget(&mut self, category: String, token: String, cap: U128, period: U64) {
+        assert_self();
+        self.assert_known_token(&token);
+        assert!(period.0 > 0, "The period can't be empty");
+        let budget = match self.budgets.get(&category) {
+            Some(budget) if budget.token == token => Budget { cap, period, ..budget },
+            _ => Budget {
+                token,
+                cap,
+                period,
+                period_start: U64(env::block_timestamp()),
+                spent: U128(0),
+            },
+        };
+        self.budgets.insert(&category, &budget);
+    }
+
+    pub fn remove_budget(&mut self, category: String) {
+        assert_self();
+        self.budgets.remove(&category);
+    }
+
+    pub fn get_budgets(&self) -> Vec<(String, Budget)> {
+        self.budgets.to_vec()
+    }
+
+    // Function to get what is left to spend in the current period of a category
+    pub fn get_remaining_budget(&self, category: String) -> U128 {
+        let mut budget = self.budgets.get(&category).expect("This budget category doesn't exist");
+        budget.refresh();
+        U128(budget.remaining())
+    }
+
+    // Function to get the spending history, oldest first
+    pub fn get_outflows(&self, from_index: u64, limit: u64) -> Vec<Outflow> {
+        (from_index..std::cmp::min(from_index + limit, self.outflows.len()))
+            .map(|i| self.outflows.get(i).unwrap())
+            .collect()
+    }
+
+    pub fn get_number_of_outflows(&self) -> u64 {
+        self.outflows.len()
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn fund_callback(
+        &mut self,
+        proposal_id: String,
+        category: String,
+        account: String,
+        token: String,
+        amount: U128,
+        #[callback_result] call_result: Result<(), PromiseError>,
+    ) {
+        if call_result.is_err() {
+            self.internal_release(Some(&category), &token, amount.0);
+            self.funded_proposals.remove(&proposal_id);
+            env::log_str("The transfer failed, the proposal can be funded again");
+        } else {
+            self.internal_record_outflow(Some(proposal_id), Some(category), account, &token, amount.0);
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Transfers between the treasury and Ethereum through the Rainbow Bridge.
+//!
+//! A passed bridge proposal sends its amount to an Ethereum address with `migrate_to_ethereum`.
+//! Every transfer is kept in a history with its status: pending while the bridge call is running,
+//! then sent, or failed when the bridge refused it and the NEAR came back to the treasury.
+//!
+//! Bridged tokens come back through `ft_on_transfer` from a whitelisted bridge token contract,
+//! with a msg `bridge:<ethereum sender>[:<reference>]` attributing the deposit to its origin.
+
+// Bridge proposals take the arguments of create_proposal plus the Ethereum recipient
+#![allow(clippy::too_many_arguments)]
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, near_bindgen, AccountId, Gas, Promise, PromiseError};
+use serde::{Deserialize, Serialize};
+
+use crate::events::{BridgeDepositData, BridgeStatusData, BridgeTransferData, Event, TreasuryEvent};
+use crate::{assert_self, ext_rainbow, CouncilProposal, TreasuryDao, TreasuryDaoExt, NEAR_TOKEN, TGAS};
+
+// Proposal type sending NEAR to Ethereum
+pub const BRIDGE_PROPOSAL: u8 = 7;
+
+// Bridge used until the owner sets another one
+pub const RAINBOW_BRIDGE: &str = "enear.goerli.testnet";
+
+// Gas given to `migrate_to_ethereum`
+pub const GAS_FOR_BRIDGE: u64 = 10 * TGAS;
+
+// Prefix of the msg of a bridged deposit
+pub const BRIDGE_MSG_PREFIX: &str = "bridge:";
+
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BridgeStatus {
+    Pending,
+    Sent,
+    Failed,
+}
+
+// Outbound transfer to Ethereum
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BridgeTransfer {
+    pub id: u64,
+    pub proposal_id: String,
+    // Lowercase hex address without the 0x prefix, as the bridge expects it
+    pub eth_recipient: String,
+    // yoctoNEAR
+    pub amount: U128,
+    pub status: BridgeStatus,
+    pub timestamp: U64,
+}
+
+// Transfer asked by a bridge proposal, stored apart from the proposal so only its creation writes it
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BridgeRequest {
+    // Lowercase hex address without the 0x prefix
+    pub eth_recipient: String,
+    // yoctoNEAR
+    pub amount: U128,
+}
+
+// Bridged tokens received from Ethereum
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InboundDeposit {
+    pub id: u64,
+    pub token: String,
+    // NEAR account that forwarded the tokens to the treasury
+    pub sender_id: String,
+    pub eth_sender: String,
+    pub reference: Option<String>,
+    // Smallest unit of the token
+    pub amount: U128,
+    pub timestamp: U64,
+}
+
+// Function to check an Ethereum address and return it in the format of the bridge
+// The 0x prefix is optional and mixed case addresses must match their EIP-55 checksum
+pub fn validate_eth_address(address: &str) -> String {
+    let hex = address
+        .strip_prefix("0x")
+        .or_else(|| address.strip_prefix("0X"))
+        .unwrap_or(address);
+    assert_eq!(hex.len(), 40, "The Ethereum address must have 40 hex characters");
+    assert!(hex.chars().all(|c| c.is_ascii_hexdigit()), "The Ethereum address isn't hex");
+    let lower = hex.to_ascii_lowercase();
+    let mixed_case = hex.chars().any(|c| c.is_ascii_lowercase()) && hex.chars().any(|c| c.is_ascii_uppercase());
+    if mixed_case {
+        let hash = env::keccak256(lower.as_bytes());
+        let checksummed = hex.chars().enumerate().all(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            !c.is_ascii_alphabetic() || c.is_ascii_uppercase() == (nibble >= 8)
+        });
+        assert!(checksummed, "The Ethereum address checksum is wrong");
+    }
+    lower
+}
+
+impl TreasuryDao {
+    fn internal_set_bridge_status(&mut self, transfer_id: u64, status: BridgeStatus) -> BridgeTransfer {
+        let mut transfer = self.bridge_transfers.get(transfer_id).expect("There is no bridge transfer with this id");
+        transfer.status = status;
+        self.bridge_transfers.replace(transfer_id, &transfer);
+        TreasuryEvent::BridgeStatus(&[BridgeStatusData { transfer_id, status }]).emit();
+        transfer
+    }
+
+    // Function to credit tokens coming from Ethereum, attributed by the msg of the transfer
+    pub(crate) fn internal_bridge_deposit(&mut self, token: String, sender_id: AccountId, amount: u128, msg: &str) {
+        let attribution = msg
+            .strip_prefix(BRIDGE_MSG_PREFIX)
+            .expect("A bridged deposit needs a msg like bridge:<ethereum sender>[:<reference>]");
+        let (eth_sender, reference) = match attribution.split_once(':') {
+            Some((eth_sender, reference)) => (eth_sender, Some(reference.to_string()).filter(|r| !r.is_empty())),
+            None => (attribution, None),
+        };
+        let deposit = InboundDeposit {
+            id: self.inbound_deposits.len(),
+            token,
+            sender_id: sender_id.to_string(),
+            eth_sender: validate_eth_address(eth_sender),
+            reference,
+            amount: U128(amount),
+            timestamp: U64(env::block_timestamp()),
+        };
+        self.internal_deposit(&deposit.token, amount);
+        self.inbound_deposits.push(&deposit);
+        TreasuryEvent::BridgeDeposit(&[BridgeDepositData {
+            deposit_id: deposit.id,
+            token: &deposit.token,
+            sender_id: &deposit.sender_id,
+            eth_sender: &deposit.eth_sender,
+            reference: deposit.reference.as_deref(),
+            amount: deposit.amount,
+        }])
+        .emit();
+    }
+}
+
+#[near_bindgen]
+impl TreasuryDao {
+    // Function to propose sending `amount` yoctoNEAR to `eth_recipient` on Ethereum
+    pub fn create_bridge_proposal(
+        &mut self,
+        id: String,
+        description: String,
+        eth_recipient: String,
+        amount: U128,
+        duration_days: u64,
+        duration_hours: u64,
+        duration_min: u64,
+    ) {
+        assert!(amount.0 > 0, "The transfer must send something");
+        let eth_recipient = validate_eth_address(&eth_recipient);
+        let mut proposal = CouncilProposal::new();
+        proposal.id = id.clone();
+        proposal.proposal_type = BRIDGE_PROPOSAL;
+        proposal.proposal_name = "Bridge to Ethereum".to_string();
+        proposal.description = description;
+        proposal.amount = amount.0;
+        proposal.duration_days = duration_days;
+        proposal.duration_hours = duration_hours;
+        proposal.duration_min = duration_min;
+        proposal.receiver = eth_recipient.clone();
+        proposal.token = Some(NEAR_TOKEN.to_string());
+        self.internal_push_proposal(proposal);
+        self.bridge_requests.insert(&id, &BridgeRequest { eth_recipient, amount });
+    }
+
+    // Function to send the NEAR of a passed bridge proposal, callable by anyone
+    // A proposal is bridged once, it can be bridged again only if the transfer failed
+    pub fn bridge(&mut self, proposal_id: String) -> Promise {
+        let request = self.bridge_requests.get(&proposal_id).expect("There is no bridge proposal with this id");
+        let proposal = self.get_specific_proposal(proposal_id.clone());
+        assert_eq!(proposal.proposal_type, BRIDGE_PROPOSAL, "There is no bridge proposal with this id");
+        assert!(proposal.check_proposal(), "The proposal hasn't passed");
+        assert!(self.funded_proposals.insert(&proposal_id), "The proposal is already bridged");
+        self.internal_reserve(None, NEAR_TOKEN, request.amount.0);
+        let transfer = BridgeTransfer {
+            id: self.bridge_transfers.len(),
+            proposal_id,
+            eth_recipient: request.eth_recipient,
+            amount: request.amount,
+            status: BridgeStatus::Pending,
+            timestamp: U64(env::block_timestamp()),
+        };
+        self.bridge_transfers.push(&transfer);
+        TreasuryEvent::BridgeTransfer(&[BridgeTransferData {
+            transfer_id: transfer.id,
+            proposal_id: &transfer.proposal_id,
+            eth_recipient: &transfer.eth_recipient,
+            amount: transfer.amount,
+        }])
+        .emit();
+        ext_rainbow::ext(self.bridge_account.clone())
+            .with_static_gas(Gas(GAS_FOR_BRIDGE))
+            .with_attached_deposit(transfer.amount.0)
+            .migrate_to_ethereum(transfer.eth_recipient)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas(10 * TGAS))
+                    .bridge_callback(transfer.id),
+            )
+    }
+
+    // Function to change the bridge contract, for another network or a mock in the sandbox
+    pub fn set_bridge_account(&mut self, account: AccountId) {
+        assert_self();
+        self.bridge_account = account;
+    }
+
+    pub fn get_bridge_account(&self) -> AccountId {
+        self.bridge_account.clone()
+    }
+
+    // Function to get the recipient and amount a bridge proposal was created with
+    pub fn get_bridge_request(&self, proposal_id: String) -> Option<BridgeRequest> {
+        self.bridge_requests.get(&proposal_id)
+    }
+
+    // Function to accept the deposits of a token bridged from Ethereum
+    pub fn add_bridge_token(&mut self, token: AccountId) {
+        assert_self();
+        self.bridge_tokens.insert(&token.to_string());
+    }
+
+    // Function to stop accepting a bridged token, its balance stays spendable
+    pub fn remove_bridge_token(&mut self, token: AccountId) {
+        assert_self();
+        self.bridge_tokens.remove(&token.to_string());
+    }
+
+    pub fn get_bridge_tokens(&self) -> Vec<String> {
+        self.bridge_tokens.to_vec()
+    }
+
+    // Function to get the deposits bridged from Ethereum, oldest first
+    pub fn get_inbound_deposits(&self, from_index: u64, limit: u64) -> Vec<InboundDeposit> {
+        (from_index..std::cmp::min(from_index + limit, self.inbound_deposits.len()))
+            .map(|i| self.inbound_deposits.get(i).unwrap())
+            .collect()
+    }
+
+    pub fn get_number_of_inbound_deposits(&self) -> u64 {
+        self.inbound_deposits.len()
+    }
+
+    pub fn get_bridge_transfer(&self, transfer_id: u64) -> Option<BridgeTransfer> {
+        self.bridge_transfers.get(transfer_id)
+    }
+
+    // Function to get the transfers to Ethereum, oldest first
+    pub fn get_bridge_transfers(&self, from_index: u64, limit: u64) -> Vec<BridgeTransfer> {
+        (from_index..std::cmp::min(from_index + limit, self.bridge_transfers.len()))
+            .map(|i| self.bridge_transfers.get(i).unwrap())
+            .collect()
+    }
+
+    pub fn get_number_of_bridge_transfers(&self) -> u64 {
+        self.bridge_transfers.len()
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn bridge_callback(&mut self, transfer_id: u64, #[callback_result] call_result: Result<(), PromiseError>) {
+        if call_result.is_err() {
+            // The attached NEAR came back, the callback must not panic to keep it counted
+            let transfer = self.internal_set_bridge_status(transfer_id, BridgeStatus::Failed);
+            self.internal_release(None, NEAR_TOKEN, transfer.amount.0);
+            self.funded_proposals.remove(&transfer.proposal_id);
+            env::log_str("The bridge refused the transfer, the NEAR is back in the treasury");
+            return;
+        }
+        let transfer = self.internal_set_bridge_status(transfer_id, BridgeStatus::Sent);
+        self.internal_record_outflow(
+            Some(transfer.proposal_id),
+            None,
+            transfer.eth_recipient,
+            NEAR_TOKEN,
+            transfer.amount.0,
+        );
+    }
+}
@@ -0,0 +1,143 @@
+// NEP-297 events emitted by the Treasury DAO, logged with the `EVENT_JSON:` prefix.
+// LTS amounts are in the token smallest unit and NEAR amounts in yoctoNEAR.
+
+pub use lightency_events::Event;
+use near_sdk::json_types::{Base58CryptoHash, U128, U64};
+use serde::Serialize;
+
+use crate::{BridgeStatus, Role};
+
+#[derive(Serialize, Debug)]
+pub struct ProposalCreateData<'a> {
+    pub id: &'a str,
+    pub proposal_type: u8,
+    pub proposal_name: &'a str,
+    pub creator: &'a str,
+    pub amount: U128,
+    pub receiver: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<&'a str>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct VoteData<'a> {
+    pub proposal_id: &'a str,
+    pub voter: &'a str,
+    pub vote: u8,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PayoutData<'a> {
+    pub receiver_id: &'a str,
+    pub token: &'a str,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BridgeTransferData<'a> {
+    pub transfer_id: u64,
+    pub proposal_id: &'a str,
+    pub eth_recipient: &'a str,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BridgeStatusData {
+    pub transfer_id: u64,
+    pub status: BridgeStatus,
+}
+
+#[derive(Serialize, Debug)]
+pub struct BridgeDepositData<'a> {
+    pub deposit_id: u64,
+    pub token: &'a str,
+    pub sender_id: &'a str,
+    pub eth_sender: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<&'a str>,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+pub struct UpgradeData<'a> {
+    pub proposal_id: &'a str,
+    pub code_hash: Base58CryptoHash,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MemberRemoveData<'a> {
+    pub account_id: &'a str,
+    pub role: Role,
+}
+
+#[derive(Serialize, Debug)]
+pub struct DepositData<'a> {
+    pub sender_id: &'a str,
+    pub token: &'a str,
+    pub amount: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<&'a str>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct StreamStartData<'a> {
+    pub stream_id: u64,
+    pub proposal_id: &'a str,
+    pub receiver_id: &'a str,
+    pub token: &'a str,
+    pub amount: U128,
+    pub rate: U128,
+    pub start: U64,
+    pub end: U64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct StreamWithdrawData<'a> {
+    pub stream_id: u64,
+    pub receiver_id: &'a str,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+pub struct StreamCancelData<'a> {
+    pub stream_id: u64,
+    pub proposal_id: &'a str,
+    pub refunded: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum TreasuryEvent<'a> {
+    ProposalCreate(&'a [ProposalCreateData<'a>]),
+    Vote(&'a [VoteData<'a>]),
+    Payout(&'a [PayoutData<'a>]),
+    BridgeTransfer(&'a [BridgeTransferData<'a>]),
+    BridgeStatus(&'a [BridgeStatusData]),
+    BridgeDeposit(&'a [BridgeDepositData<'a>]),
+    Upgrade(&'a [UpgradeData<'a>]),
+    MemberRemove(&'a [MemberRemoveData<'a>]),
+    Deposit(&'a [DepositData<'a>]),
+    StreamStart(&'a [StreamStartData<'a>]),
+    StreamWithdraw(&'a [StreamWithdrawData<'a>]),
+    StreamCancel(&'a [StreamCancelData<'a>]),
+}
+
+impl Event for TreasuryEvent<'_> {
+    // Each event keeps its own schema version
+    fn version(&self) -> &'static str {
+        match self {
+            TreasuryEvent::ProposalCreate(_) => "1.0.0",
+            TreasuryEvent::Vote(_) => "1.0.0",
+            TreasuryEvent::Payout(_) => "1.0.0",
+            TreasuryEvent::BridgeTransfer(_) => "1.1.0",
+            TreasuryEvent::BridgeStatus(_) => "1.0.0",
+            TreasuryEvent::BridgeDeposit(_) => "1.0.0",
+            TreasuryEvent::Upgrade(_) => "1.0.0",
+            TreasuryEvent::MemberRemove(_) => "1.0.0",
+            TreasuryEvent::Deposit(_) => "1.0.0",
+            TreasuryEvent::StreamStart(_) => "1.0.0",
+            TreasuryEvent::StreamWithdraw(_) => "1.0.0",
+            TreasuryEvent::StreamCancel(_) => "1.0.0",
+        }
+    }
+}
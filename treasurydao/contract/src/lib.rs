@@ -1,16 +1,37 @@
-use std::ops::Div;
-
-use ext_rainbow::RainbowExt;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{UnorderedMap};
-use near_sdk::{env, near_bindgen, ext_contract,Gas,log, PromiseError,Promise, };
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::{env, near_bindgen, ext_contract,Gas,log, Promise, CryptoHash, AccountId};
+use near_sdk::json_types::U128;
 use serde::{Serialize,Deserialize};
 
+mod accounting;
+mod bridge;
+mod events;
+mod membership;
+mod migration;
+mod streams;
+mod tokens;
+mod upgrade;
+use events::{Event, PayoutData, ProposalCreateData, TreasuryEvent, VoteData};
+pub use accounting::{Budget, Outflow, ONE_LTS};
+pub use bridge::{
+    validate_eth_address, BridgeRequest, BridgeStatus, BridgeTransfer, InboundDeposit, BRIDGE_MSG_PREFIX, BRIDGE_PROPOSAL,
+    RAINBOW_BRIDGE,
+};
+pub use membership::{Role, ENERGY_FUND, MIN_COUNCIL_SIZE, REMOVE_COUNCIL_PROPOSAL};
+pub use migration::{CouncilProposalV0, TreasuryDaoV0, VersionedTreasuryDao, STATE_VERSION};
+pub use streams::{Stream, CANCEL_STREAM_PROPOSAL, STREAM_PROPOSAL};
+pub use tokens::{LTS_TOKEN, NEAR_TOKEN};
+pub use upgrade::UPGRADE_PROPOSAL;
+
 pub const TGAS: u64 = 1_000_000_000_000;
 
-#[ext_contract(ext_lts)]
-pub trait Lts {
-    fn ft_transfer (&mut self, receiver_id:String, amount:String, memo:String);
+//Define NEP-141 token contracts
+#[ext_contract(ext_ft)]
+pub trait FungibleToken {
+    fn ft_transfer (&mut self, receiver_id:AccountId, amount:U128, memo:Option<String>);
+    fn storage_deposit (&mut self, account_id:Option<AccountId>, registration_only:Option<bool>);
+    fn ft_balance_of (&self, account_id:AccountId) -> U128;
 }
 
 //Define Rainbow Bridge contract
@@ -23,7 +44,7 @@ pub trait Rainbow {
 
 // VOTE
 // Vote structor 
-#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize)]
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
  pub struct Vote{
     pub address: String,
     pub vote:u8,
@@ -44,7 +65,7 @@ pub trait Rainbow {
 
  // Council Proposal
 // Proposal structor
-#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Serialize, Deserialize)]
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct CouncilProposal{
     pub id:String,
     pub proposal_type: u8,
@@ -60,7 +81,9 @@ pub struct CouncilProposal{
     pub duration_min:u64,
     pub list_voters:Vec<String>,
     pub votes:Vec<Vote>,
-    pub receiver:String
+    pub receiver:String,
+    // Token paid by the proposal, LTS in whole tokens when not set
+    pub token:Option<String>,
 }
 
 impl CouncilProposal{
@@ -81,6 +104,7 @@ impl CouncilProposal{
             list_voters:Vec::new(),
             votes:Vec::new(),
             receiver: String::new(),
+            token: None,
         }
     }
 
@@ -120,7 +144,8 @@ impl CouncilProposal{
             duration_min: self.duration_min, 
             list_voters: self.list_voters.clone(),
             votes: self.votes.clone(),
-            receiver: self.receiver.clone()
+            receiver: self.receiver.clone(),
+            token: self.token.clone(),
         }
     }
 
@@ -145,8 +170,35 @@ impl CouncilProposal{
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct TreasuryDao {
     stakers: Vec<String>,
-    members: UnorderedMap<String,u8>,
+    members: UnorderedMap<String,Role>,
     proposals: Vec<CouncilProposal>,
+    // Code hashes proposed for an upgrade and the id of their proposal
+    upgrades: LookupMap<CryptoHash, String>,
+    // Councils proposed for removal, by proposal id
+    removals: LookupMap<String, String>,
+    // Accepted NEP-141 tokens and the holdings per token in their smallest unit
+    tokens: UnorderedSet<String>,
+    balances: UnorderedMap<String, u128>,
+    budgets: UnorderedMap<String, Budget>,
+    outflows: Vector<Outflow>,
+    // Proposals already paid out by fund or start_stream
+    funded_proposals: LookupSet<String>,
+    // Length in seconds of the stream of each stream proposal
+    stream_durations: LookupMap<String, u64>,
+    // Streams proposed for cancellation, by proposal id
+    stream_cancellations: LookupMap<String, u64>,
+    streams: Vector<Stream>,
+    // Contract receiving the NEAR bridged to Ethereum and the history of the transfers
+    bridge_account: AccountId,
+    bridge_transfers: Vector<BridgeTransfer>,
+    // Token contracts of the bridge and the tokens received from Ethereum
+    bridge_tokens: UnorderedSet<String>,
+    inbound_deposits: Vector<InboundDeposit>,
+    // Recipient and amount of each bridge proposal as validated when it was created
+    bridge_requests: LookupMap<String, BridgeRequest>,
+    // Councils seated by the Energy Fund, which it can unseat when their shares fall
+    fund_councils: LookupSet<String>,
+    state_version: u8,
 }
 
 // Define the default, which automatically initializes the contract
@@ -165,6 +217,23 @@ fn assert_self() {
     );
 }
 
+impl TreasuryDao {
+    // Replace a proposal whith a new one, not exposed so votes and results can't be forged
+    pub(crate) fn replace_proposal(&mut self, proposal: CouncilProposal){
+        let mut index =0;
+        for i in 0..self.proposals.len(){
+            match self.proposals.get(i){
+                Some(p) => if p.id==proposal.id {
+                    index=i;
+                },
+                None => panic!("There is no PROPOSALs"),
+            }
+        }
+        self.proposals.swap_remove(index);
+        self.proposals.insert(index, proposal);
+    }
+}
+
 // Implement the contract structure
 // To be implemented in the front end
 #[near_bindgen]
@@ -172,16 +241,35 @@ impl TreasuryDao {
     #[init]
     pub fn new() -> Self {
         assert!(env::state_read::<Self>().is_none(), "Already initialized");
-        Self {
+        let mut this = Self {
             stakers: Vec::new(),
             members : UnorderedMap::new(b"m"),
             proposals : Vec::new(),
-        }
+            upgrades: LookupMap::new(b"u"),
+            removals: LookupMap::new(b"x"),
+            tokens: UnorderedSet::new(b"w"),
+            balances: UnorderedMap::new(b"t"),
+            budgets: UnorderedMap::new(b"b"),
+            outflows: Vector::new(b"h"),
+            funded_proposals: LookupSet::new(b"f"),
+            stream_durations: LookupMap::new(b"d"),
+            stream_cancellations: LookupMap::new(b"c"),
+            streams: Vector::new(b"s"),
+            bridge_account: RAINBOW_BRIDGE.parse().unwrap(),
+            bridge_transfers: Vector::new(b"e"),
+            bridge_tokens: UnorderedSet::new(b"k"),
+            inbound_deposits: Vector::new(b"i"),
+            bridge_requests: LookupMap::new(b"q"),
+            fund_councils: LookupSet::new(b"n"),
+            state_version: STATE_VERSION,
+        };
+        this.tokens.insert(&LTS_TOKEN.to_string());
+        this
     }
 
     pub fn init(&mut self) {
         assert_self();
-        self.members.insert(&env::current_account_id().to_string(), &0);
+        self.members.insert(&env::current_account_id().to_string(), &Role::Council);
     }
 
     // delete all members 
@@ -192,47 +280,33 @@ impl TreasuryDao {
 
     // get all councils
     pub fn get_councils(&self) -> Vec<String> {
-        let mut vec = Vec::new();
-        for i in self.members.keys() {
-            if self.members.get(&i).unwrap() == 0 {
-                vec.push(i);
-            }
-        }
-        vec
+        self.members
+            .iter()
+            .filter(|(_, role)| *role == Role::Council)
+            .map(|(account, _)| account)
+            .collect()
     }
 
     // get all communities
     pub fn get_communities(&self) -> Vec<String> {
-        let mut vec = Vec::new();
-        for i in self.members.keys() {
-            if self.members.get(&i).unwrap() == 1 {
-                vec.push(i);
-            }
-        }
-        vec
+        self.members
+            .iter()
+            .filter(|(_, role)| *role == Role::Community)
+            .map(|(account, _)| account)
+            .collect()
+    }
+
+    // get the role of a member
+    pub fn get_role(&self, account:String) -> Option<Role> {
+        self.members.get(&account)
     }
 
     pub fn check_member(&self, account:String) -> bool {
-        let mut result=false;
-        for i in 0..self.members.keys_as_vector().len() {
-            if self.members.keys_as_vector().get(i).unwrap() == account {
-                result = true;
-                break;
-            } 
-        }
-        result
+        self.members.get(&account).is_some()
     }
 
     pub fn check_council (&self, account:String) -> bool {
-        if self.check_member(account.clone()) == false {
-            return false ;
-        }else {
-            if self.members.get(&account).unwrap() == 0 {
-                return true;
-            }else {
-                return false;
-            }
-        }
+        self.members.get(&account) == Some(Role::Council)
     }
 
     // Create a new proposal 
@@ -247,12 +321,21 @@ impl TreasuryDao {
         duration_hours: u64,
         duration_min: u64,
         receiver:String,
+        token:Option<String>,
     ){
         assert_eq!(
             self.check_council(env::signer_account_id().to_string()),
             true,
             "Proposals can be created only by the councils"
         );
+        // streams, removals and bridges look their proposal up by id, a second one would shadow it
+        assert!(
+            self.get_proposals().iter().all(|p| p.id != id),
+            "A proposal with this id already exists"
+        );
+        if let Some(token) = &token {
+            self.assert_known_token(token);
+        }
         let proposal=CouncilProposal{
             id:id,
             proposal_type:proposal_type,
@@ -268,31 +351,22 @@ impl TreasuryDao {
             duration_min:duration_min,
             list_voters:Vec::new(),
             votes:Vec::new(),
-            receiver:receiver
+            receiver:receiver,
+            token:token
         };
+        TreasuryEvent::ProposalCreate(&[ProposalCreateData {
+            id: &proposal.id,
+            proposal_type: proposal.proposal_type,
+            proposal_name: &proposal.proposal_name,
+            creator: &proposal.proposal_creator,
+            amount: U128(proposal.amount),
+            receiver: &proposal.receiver,
+            token: proposal.token.as_deref(),
+        }])
+        .emit();
         self.proposals.push(proposal);
     }
 
-    // Replace a proposal whith a new one 
-    pub fn replace_proposal(&mut self, proposal: CouncilProposal){
-        assert_eq!(
-            self.check_member(env::signer_account_id().to_string()),
-            true,
-            "Proposals can be created only by members"
-        );
-        let mut index =0;
-        for i in 0..self.proposals.len(){
-            match self.proposals.get(i){
-                Some(p) => if p.id==proposal.id {
-                    index=i;
-                },
-                None => panic!("There is no PROPOSALs"),
-            }
-        }
-        self.proposals.swap_remove(index);
-        self.proposals.insert(index, proposal);
-    }
-
     // Get all proposals 
     pub fn get_proposals(&self) -> Vec<CouncilProposal>{
         self.proposals.clone()
@@ -326,6 +400,12 @@ impl TreasuryDao {
             );
             let proposal =self.get_specific_proposal(id.clone()).create_vote(vote);
             self.replace_proposal(proposal);
+            TreasuryEvent::Vote(&[VoteData {
+                proposal_id: &id,
+                voter: env::signer_account_id().as_str(),
+                vote,
+            }])
+            .emit();
         }else {
             panic!("Proposal has been expired");
         }
@@ -342,7 +422,7 @@ impl TreasuryDao {
             true,
             "To add a council you must be one of the councils"
         );
-        self.members.insert(&account, &0);
+        self.members.insert(&account, &Role::Council);
     }
 
     // delete all stakers 
@@ -390,10 +470,11 @@ impl TreasuryDao {
         }
     }
 
-    // add community
+    // add community, a member keeps its role until it leaves or is removed
     pub fn add_community (&mut self,account:String) {
+        assert!(!self.check_member(account.clone()), "This account is already a member");
         if self.check_staker(account.clone()) == true {
-            self.members.insert(&account, &1);
+            self.members.insert(&account, &Role::Community);
         }else {
             panic!("You must be a staker to join community");
         }
@@ -413,58 +494,63 @@ impl TreasuryDao {
     }
 
     // fund function 
-    pub fn fund (&mut self,account:String,amount:u128){
+    // Pays a passed funding proposal from the budget of `category`
+    // Amounts of proposals without a token are in whole LTS, the others in the smallest unit of their token
+    pub fn fund (&mut self,account:String,amount:u128,proposal_id:String,category:String) -> Promise {
         assert_eq!(
             env::signer_account_id().to_string(),
             "alach.testnet".to_string(),
             "You are not authorized to execute this function"
         );
-        let account_lts= "light-token.testnet".to_string().try_into().unwrap();
-        ext_lts::ext(account_lts)
-        .with_static_gas(Gas(2 * TGAS))
-        .with_attached_deposit(1)
-        .ft_transfer(account,(amount*100000000).to_string(),"".to_string());
-    }
-
-    #[payable]
-    pub fn process_borrow(&mut self,eth_recipient:String)->Promise{
-        
-        let mut eth_addr=eth_recipient.clone();
-        if(eth_addr.len()==42){
-            eth_addr.remove(0);
-            eth_addr.remove(0);
-        }
-        let rainbow_account= "enear.goerli.testnet".to_string().try_into().unwrap();
-        let promise =ext_rainbow::ext(rainbow_account)
-        .with_static_gas(Gas(2 * TGAS))
-        .with_attached_deposit(12000000000000000000000000)
-        .migrate_to_ethereum(eth_addr);
-        return promise.then( // Create a promise to callback withdraw_callback
+        let proposal = self.get_specific_proposal(proposal_id.clone());
+        assert!(proposal.check_proposal(), "The proposal hasn't passed");
+        assert!(
+            proposal.proposal_type == 0 && proposal.receiver == account && proposal.amount == amount,
+            "The payment doesn't match the proposal"
+        );
+        assert!(self.funded_proposals.insert(&proposal_id), "The proposal is already funded");
+        let (token, amount) = match proposal.token {
+            Some(token) => (token, amount),
+            None => (LTS_TOKEN.to_string(), amount*ONE_LTS),
+        };
+        self.internal_reserve(Some(&category), &token, amount);
+        TreasuryEvent::Payout(&[PayoutData {
+            receiver_id: &account,
+            token: &token,
+            amount: U128(amount),
+        }])
+        .emit();
+        self.internal_transfer(&token, account.parse().unwrap(), amount)
+        .then(
             Self::ext(env::current_account_id())
-            .with_static_gas(Gas(10 * TGAS))
-            .process_callback()
-            )
-
-
-        
+            .with_static_gas(Gas(5 * TGAS))
+            .fund_callback(proposal_id, category, account, token, U128(amount))
+        )
     }
-
-        
-    #[private] // Public - but only callable by env::current_account_id()
-    pub fn process_callback(&mut self, #[callback_result] call_result: Result<(), PromiseError> ) {
-            if call_result.is_err() {
-            panic!("There was an error contacting the pool contract");
-            }
-        }
 }
 #[cfg(test)]
 mod tests {
-    use std::{ptr::null, arch::x86_64::_mm_undefined_pd};
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+    use near_sdk::json_types::U64;
+    use near_sdk::PromiseError;
 
     use super::*;
+
+    // context where the treasury calls itself
+    fn owner_context() -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("treasurydao.testnet".parse().unwrap())
+            .predecessor_account_id("treasurydao.testnet".parse().unwrap())
+            .signer_account_id("treasurydao.testnet".parse().unwrap());
+        builder
+    }
+
     //testing init function to initialize the smart contract after deployemnt
     #[test]
     fn test_init(){
+        testing_env!(owner_context().build());
         let mut contract = TreasuryDao::new();
         contract.init();
         assert_eq!(contract.check_council(env::current_account_id().to_string()), true);
@@ -472,6 +558,7 @@ mod tests {
     // testing delete all members function 
     #[test]
     fn test_delete_all(){
+        testing_env!(owner_context().build());
         let mut contract = TreasuryDao::new();
         contract.init();
         contract.delete_all();
@@ -482,19 +569,22 @@ mod tests {
     //testing create proposal function
     #[test]
     fn test_create_proposal(){
+        testing_env!(owner_context().build());
         let mut contract = TreasuryDao::new();
         contract.init();
-        contract.create_proposal("id".to_string(), 0,"azerty".to_string(), "description".to_string(), 1, 0, 0, 1, env::current_account_id());
-        assert_ne!(contract.get_specific_proposal("id".to_string()),null());
+        contract.create_proposal("id".to_string(), 0,"azerty".to_string(), "description".to_string(), 1, 0, 0, 1, env::current_account_id().to_string(), None);
+        assert_eq!(contract.get_specific_proposal("id".to_string()).id, "id");
     }
     
     //testing replace proposal function
 
     #[test]
     fn test_replace_proposal(){
+        testing_env!(owner_context().build());
         let mut contract = TreasuryDao::new();
-        contract.create_proposal("azerty".to_string(), 1,"qwerty".to_string(), "description".to_string(), 1, 0, 0, 1, env::current_account_id());
-        let mut proposal = contract::CouncilProposal{
+        contract.init();
+        contract.create_proposal("azerty".to_string(), 1,"qwerty".to_string(), "description".to_string(), 1, 0, 0, 1, env::current_account_id().to_string(), None);
+        let proposal = CouncilProposal{
             id:"azerty".to_string(),
             proposal_type:0,
             proposal_name: String::new(),
@@ -510,29 +600,559 @@ mod tests {
             list_voters:Vec::new(),
             votes:Vec::new(),
             receiver: String::new(),
+            token: None,
 
         };
-        contract.replace_proposal(proposal);
+        contract.replace_proposal(proposal.clone());
         assert_eq!(contract.get_specific_proposal("azerty".to_string()), proposal);
     }
 
     //testing add vote function
     #[test]
     fn test_add_vote(){
+        testing_env!(owner_context().build());
         let mut contract = TreasuryDao::new();
         contract.init();
-        contract.create_proposal("azerty".to_string(), 1,"qwerty".to_string(), "description".to_string(), 1, 0, 0, 1, env::current_account_id());
+        contract.create_proposal("azerty".to_string(), 1,"qwerty".to_string(), "description".to_string(), 1, 0, 0, 1, env::current_account_id().to_string(), None);
         let proposal = contract.get_specific_proposal("azerty".to_string());
-        contract.vote(&proposal.id, 1);
-        assert_eq!(&proposal.votes.len(), 1);
+        contract.add_vote(proposal.id.clone(), 1);
+        assert_eq!(contract.get_specific_proposal(proposal.id).votes.len(), 1);
     }
     //testing add council function 
     #[test]
     fn test_add_council(){
+        testing_env!(owner_context().build());
+        let mut contract = TreasuryDao::new();
+        contract.init();
+        contract.add_council("oussema.testnet".to_string());
+        assert!(contract.check_council("oussema.testnet".to_string()));
+    }
+
+    //testing the migration of a state written before versioning
+    #[test]
+    fn test_migrate_from_v0(){
+        testing_env!(owner_context().build());
+        let mut members = UnorderedMap::new(b"m");
+        members.insert(&"oussema.testnet".to_string(), &Role::Council);
+        let proposal = CouncilProposalV0 { id: "azerty".to_string(), ..Default::default() };
+        env::state_write(&TreasuryDaoV0 {
+            stakers: vec!["issameths.testnet".to_string()],
+            members,
+            proposals: vec![proposal],
+        });
+
+        let storage_before = env::storage_usage();
+        let mut contract = TreasuryDao::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert!(contract.check_council("oussema.testnet".to_string()));
+        assert!(contract.check_staker("issameths.testnet".to_string()));
+        assert_eq!(contract.get_specific_proposal("azerty".to_string()).id, "azerty");
+
+        //the NEAR above the storage is counted and the LTS is read from the token
+        let near = contract.get_token_balance(NEAR_TOKEN.to_string()).0;
+        assert!(near <= env::account_balance() - storage_before as u128 * env::storage_byte_cost());
+        assert!(near >= env::account_balance() - env::storage_usage() as u128 * env::storage_byte_cost());
+        assert!(near_sdk::test_utils::get_created_receipts().iter().any(|receipt| receipt.actions.iter().any(|action| matches!(action,
+            near_sdk::mock::VmAction::FunctionCall { function_name, .. } if function_name == "ft_balance_of"))));
+        contract.sync_lts_callback(Ok(U128(500 * ONE_LTS)));
+        assert_eq!(contract.get_token_balance(LTS_TOKEN.to_string()), U128(500 * ONE_LTS));
+    }
+
+    //testing that migrating the current layout keeps it as it is
+    #[test]
+    fn test_migrate_current(){
+        testing_env!(owner_context().build());
+        let mut contract = TreasuryDao::new();
+        contract.init();
+        env::state_write(&contract);
+
+        let contract = TreasuryDao::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert!(contract.check_council("treasurydao.testnet".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by owner")]
+    fn test_migrate_not_owner(){
+        testing_env!(owner_context().predecessor_account_id("oussema.testnet".parse().unwrap()).build());
+        TreasuryDao::migrate();
+    }
+
+    // proposal to deploy `code`, voted for by the owner council
+    fn contract_with_upgrade_proposal(code: &[u8]) -> TreasuryDao {
+        testing_env!(owner_context().build());
+        let mut contract = TreasuryDao::new();
+        contract.init();
+        contract.create_upgrade_proposal("upgrade".to_string(), "v2".to_string(), env::sha256_array(code).into(), 0, 0, 1);
+        contract.add_vote("upgrade".to_string(), 1);
+        contract
+    }
+
+    //testing the deployment of a passed upgrade
+    #[test]
+    fn test_upgrade(){
+        let mut contract = contract_with_upgrade_proposal(b"new code");
+        assert_eq!(contract.get_upgrade_proposal(env::sha256_array(b"new code").into()), Some("upgrade".to_string()));
+        let mut context = owner_context().predecessor_account_id("oussema.testnet".parse().unwrap()).block_timestamp(60000000001).build();
+        context.input = b"new code".to_vec();
+        testing_env!(context);
+        contract.upgrade();
+        assert_eq!(contract.get_upgrade_proposal(env::sha256_array(b"new code").into()), None);
+        assert!(near_sdk::test_utils::get_logs().iter().any(|log| log.contains("\"event\":\"upgrade\"")));
+    }
+
+    #[test]
+    #[should_panic(expected = "The upgrade proposal hasn't passed")]
+    fn test_upgrade_before_end(){
+        let mut contract = contract_with_upgrade_proposal(b"new code");
+        let mut context = owner_context().build();
+        context.input = b"new code".to_vec();
+        testing_env!(context);
+        contract.upgrade();
+    }
+
+    #[test]
+    #[should_panic(expected = "This code hasn't been proposed")]
+    fn test_upgrade_other_code(){
+        let mut contract = contract_with_upgrade_proposal(b"new code");
+        let mut context = owner_context().block_timestamp(60000000001).build();
+        context.input = b"other code".to_vec();
+        testing_env!(context);
+        contract.upgrade();
+    }
+
+    //testing that community votes alone can't deploy new code
+    #[test]
+    #[should_panic(expected = "The upgrade needs the votes of more than half of the councils")]
+    fn test_upgrade_without_council_majority(){
+        let mut contract = contract_with_members();
+        contract.create_upgrade_proposal("upgrade".to_string(), "v2".to_string(), env::sha256_array(b"new code").into(), 0, 0, 1);
+        testing_env!(owner_context().signer_account_id("issameths.testnet".parse().unwrap()).build());
+        contract.add_vote("upgrade".to_string(), 1);
+        let mut context = owner_context().block_timestamp(60000000001).build();
+        context.input = b"new code".to_vec();
+        testing_env!(context);
+        contract.upgrade();
+    }
+
+    // dao with the owner and oussema as councils and issam as a community member
+    fn contract_with_members() -> TreasuryDao {
+        testing_env!(owner_context().build());
+        let mut contract = TreasuryDao::new();
+        contract.init();
+        contract.add_council("oussema.testnet".to_string());
+        contract.members.insert(&"issameths.testnet".to_string(), &Role::Community);
+        contract
+    }
+
+    //testing the removal of a community member by a council
+    #[test]
+    fn test_remove_member(){
+        let mut contract = contract_with_members();
+        contract.remove_member("issameths.testnet".to_string());
+        assert!(!contract.check_member("issameths.testnet".to_string()));
+        assert_eq!(contract.get_role("oussema.testnet".to_string()), Some(Role::Council));
+    }
+
+    //testing the seat given by the Energy Fund to an investor
+    #[test]
+    fn test_add_fund_council(){
+        let mut contract = contract_with_members();
+        testing_env!(owner_context().predecessor_account_id(ENERGY_FUND.parse().unwrap()).build());
+        contract.add_fund_council("alach.testnet".to_string());
+        assert!(contract.check_council("alach.testnet".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "You are not authorized to execute this function")]
+    fn test_add_fund_council_not_fund(){
+        let mut contract = contract_with_members();
+        testing_env!(owner_context().predecessor_account_id("alach.testnet".parse().unwrap()).build());
+        contract.add_fund_council("alach.testnet".to_string());
+    }
+
+    //testing that the fund can't turn a member into a fund seat
+    #[test]
+    #[should_panic(expected = "This account is already a member")]
+    fn test_add_fund_council_member(){
+        let mut contract = contract_with_members();
+        testing_env!(owner_context().predecessor_account_id(ENERGY_FUND.parse().unwrap()).build());
+        contract.add_fund_council("oussema.testnet".to_string());
+    }
+
+    //testing that the fund unseats only the councils it seated
+    #[test]
+    fn test_remove_fund_council(){
+        let mut contract = contract_with_members();
+        testing_env!(owner_context().predecessor_account_id(ENERGY_FUND.parse().unwrap()).build());
+        contract.add_fund_council("investor.testnet".to_string());
+        assert!(contract.check_council("investor.testnet".to_string()));
+        contract.remove_fund_council("investor.testnet".to_string());
+        assert!(!contract.check_member("investor.testnet".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "This account wasn't seated by the Energy Fund")]
+    fn test_remove_fund_council_elected(){
+        let mut contract = contract_with_members();
+        testing_env!(owner_context().predecessor_account_id(ENERGY_FUND.parse().unwrap()).build());
+        contract.remove_fund_council("oussema.testnet".to_string());
+    }
+
+    //testing that a council can't be demoted by joining the community
+    #[test]
+    #[should_panic(expected = "This account is already a member")]
+    fn test_add_community_council(){
+        let mut contract = contract_with_members();
+        contract.stakers.push("oussema.testnet".to_string());
+        contract.add_community("oussema.testnet".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Councils can only be removed through a proposal")]
+    fn test_remove_council_directly(){
+        let mut contract = contract_with_members();
+        contract.remove_member("oussema.testnet".to_string());
+    }
+
+    //testing that a member can leave but not the last council
+    #[test]
+    #[should_panic(expected = "The DAO must keep at least 1 council(s)")]
+    fn test_leave_dao(){
+        let mut contract = contract_with_members();
+        testing_env!(owner_context().predecessor_account_id("oussema.testnet".parse().unwrap()).build());
+        contract.leave_dao();
+        assert_eq!(contract.get_councils(), vec!["treasurydao.testnet".to_string()]);
+        testing_env!(owner_context().build());
+        contract.leave_dao();
+    }
+
+    //testing the removal of a council voted by the dao
+    #[test]
+    fn test_removal_proposal(){
+        let mut contract = contract_with_members();
+        contract.create_removal_proposal("remove".to_string(), "inactive".to_string(), "oussema.testnet".to_string(), 0, 0, 1);
+        contract.add_vote("remove".to_string(), 1);
+        testing_env!(owner_context().predecessor_account_id("issameths.testnet".parse().unwrap()).block_timestamp(60000000001).build());
+        contract.execute_removal("remove".to_string());
+        assert!(!contract.check_member("oussema.testnet".to_string()));
+    }
+
+    //testing that a passed proposal reusing the id of a rejected removal can't remove the council
+    #[test]
+    #[should_panic(expected = "A proposal with this id already exists")]
+    fn test_removal_shadowed_by_another_proposal(){
+        let mut contract = contract_with_members();
+        contract.create_removal_proposal("remove".to_string(), "inactive".to_string(), "oussema.testnet".to_string(), 0, 0, 1);
+        contract.add_vote("remove".to_string(), 0);
+        contract.create_proposal("remove".to_string(), 0, "grant".to_string(), "description".to_string(), 1, 0, 0, 1, "issameths.testnet".to_string(), None);
+        contract.add_vote("remove".to_string(), 1);
+        testing_env!(owner_context().predecessor_account_id("issameths.testnet".parse().unwrap()).block_timestamp(60000000001).build());
+        contract.execute_removal("remove".to_string());
+    }
+
+    // treasury holding 100 LTS with a passed proposal paying 10 LTS to issam and a 50 LTS daily budget
+    fn contract_with_budget() -> TreasuryDao {
+        testing_env!(owner_context().build());
+        let mut contract = TreasuryDao::new();
+        contract.init();
+        contract.create_proposal("grant".to_string(), 0, "grant".to_string(), "description".to_string(), 10, 0, 0, 1, "issameths.testnet".to_string(), None);
+        contract.add_vote("grant".to_string(), 1);
+        contract.set_budget("grants".to_string(), LTS_TOKEN.to_string(), U128(50 * ONE_LTS), 86400000000000.into());
+        testing_env!(owner_context().predecessor_account_id("light-token.testnet".parse().unwrap()).build());
+        contract.ft_on_transfer("alach.testnet".parse().unwrap(), U128(100 * ONE_LTS), "".to_string());
+        testing_env!(owner_context().signer_account_id("alach.testnet".parse().unwrap()).block_timestamp(60000000001).build());
+        contract
+    }
+
+    //testing a payment taken from a budget and recorded once transferred
+    #[test]
+    fn test_fund_from_budget(){
+        let mut contract = contract_with_budget();
+        contract.fund("issameths.testnet".to_string(), 10, "grant".to_string(), "grants".to_string());
+        assert_eq!(contract.get_token_balance(LTS_TOKEN.to_string()), U128(90 * ONE_LTS));
+        assert_eq!(contract.get_remaining_budget("grants".to_string()), U128(40 * ONE_LTS));
+
+        contract.fund_callback("grant".to_string(), "grants".to_string(), "issameths.testnet".to_string(), LTS_TOKEN.to_string(), U128(10 * ONE_LTS), Ok(()));
+        let outflows = contract.get_outflows(0, 10);
+        assert_eq!(outflows.len(), 1);
+        assert_eq!(outflows[0].proposal_id, Some("grant".to_string()));
+        assert_eq!(outflows[0].amount, U128(10 * ONE_LTS));
+
+        //the budget is available again in the next period
+        testing_env!(owner_context().block_timestamp(86400000000001).build());
+        assert_eq!(contract.get_remaining_budget("grants".to_string()), U128(50 * ONE_LTS));
+    }
+
+    //testing that a failed transfer gives the reservation back
+    #[test]
+    fn test_fund_failed_transfer(){
+        let mut contract = contract_with_budget();
+        contract.fund("issameths.testnet".to_string(), 10, "grant".to_string(), "grants".to_string());
+        contract.fund_callback("grant".to_string(), "grants".to_string(), "issameths.testnet".to_string(), LTS_TOKEN.to_string(), U128(10 * ONE_LTS), Err(PromiseError::Failed));
+        assert_eq!(contract.get_token_balance(LTS_TOKEN.to_string()), U128(100 * ONE_LTS));
+        assert_eq!(contract.get_remaining_budget("grants".to_string()), U128(50 * ONE_LTS));
+        assert_eq!(contract.get_number_of_outflows(), 0);
+        contract.fund("issameths.testnet".to_string(), 10, "grant".to_string(), "grants".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "The proposal is already funded")]
+    fn test_fund_twice(){
+        let mut contract = contract_with_budget();
+        contract.fund("issameths.testnet".to_string(), 10, "grant".to_string(), "grants".to_string());
+        contract.fund("issameths.testnet".to_string(), 10, "grant".to_string(), "grants".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "The budget of grants is exceeded")]
+    fn test_fund_over_budget(){
+        let mut contract = contract_with_budget();
+        testing_env!(owner_context().build());
+        contract.set_budget("grants".to_string(), LTS_TOKEN.to_string(), U128(5 * ONE_LTS), 86400000000000.into());
+        testing_env!(owner_context().signer_account_id("alach.testnet".parse().unwrap()).block_timestamp(60000000001).build());
+        contract.fund("issameths.testnet".to_string(), 10, "grant".to_string(), "grants".to_string());
+    }
+
+    //testing a proposal paying a whitelisted stablecoin in its smallest unit
+    #[test]
+    fn test_fund_other_token(){
+        let mut contract = contract_with_budget();
+        testing_env!(owner_context().build());
+        contract.add_token("usdc.testnet".parse().unwrap());
+        contract.set_budget("stable".to_string(), "usdc.testnet".to_string(), U128(1_000_000_000), 86400000000000.into());
+        contract.create_proposal("usdc".to_string(), 0, "grant".to_string(), "description".to_string(), 500_000_000, 0, 0, 1, "issameths.testnet".to_string(), Some("usdc.testnet".to_string()));
+        contract.add_vote("usdc".to_string(), 1);
+        testing_env!(owner_context().predecessor_account_id("usdc.testnet".parse().unwrap()).build());
+        contract.ft_on_transfer("alach.testnet".parse().unwrap(), U128(2_000_000_000), "".to_string());
+
+        testing_env!(owner_context().signer_account_id("alach.testnet".parse().unwrap()).block_timestamp(60000000001).build());
+        contract.fund("issameths.testnet".to_string(), 500_000_000, "usdc".to_string(), "stable".to_string());
+        assert_eq!(contract.get_token_balance("usdc.testnet".to_string()), U128(1_500_000_000));
+        assert_eq!(contract.get_token_balance(LTS_TOKEN.to_string()), U128(100 * ONE_LTS));
+        assert_eq!(contract.get_remaining_budget("stable".to_string()), U128(500_000_000));
+    }
+
+    #[test]
+    #[should_panic(expected = "The token scam.testnet isn't whitelisted")]
+    fn test_deposit_unknown_token(){
+        let mut contract = contract_with_budget();
+        testing_env!(owner_context().predecessor_account_id("scam.testnet".parse().unwrap()).build());
+        contract.ft_on_transfer("alach.testnet".parse().unwrap(), U128(1), "".to_string());
+    }
+
+    // Starts a 20 LTS stream to issameths.testnet over `stream_duration` seconds, at 2 minutes
+    fn contract_with_stream(stream_duration: u64) -> TreasuryDao {
+        let mut contract = contract_with_budget();
+        testing_env!(owner_context().build());
+        contract.create_stream_proposal("stream".to_string(), "description".to_string(), "issameths.testnet".to_string(), 20, None, U64(stream_duration), 0, 0, 1);
+        contract.add_vote("stream".to_string(), 1);
+        testing_env!(owner_context().block_timestamp(120000000000).build());
+        assert_eq!(contract.start_stream("stream".to_string(), "grants".to_string()), 0);
+        contract
+    }
+
+    //testing withdrawals from a stream
+    #[test]
+    fn test_stream_withdraw(){
+        let mut contract = contract_with_stream(100);
+        assert_eq!(contract.get_remaining_budget("grants".to_string()), U128(30 * ONE_LTS));
+        assert_eq!(contract.get_token_balance(LTS_TOKEN.to_string()), U128(80 * ONE_LTS));
+
+        testing_env!(owner_context().predecessor_account_id("issameths.testnet".parse().unwrap()).block_timestamp(145000000000).build());
+        assert_eq!(contract.get_withdrawable(0), U128(5 * ONE_LTS));
+        contract.withdraw_from_stream(0);
+        assert_eq!(contract.get_withdrawable(0), U128(0));
+        testing_env!(owner_context().block_timestamp(145000000000).build());
+        contract.stream_withdraw_callback(0, U128(5 * ONE_LTS), Ok(()));
+        assert_eq!(contract.get_outflows(0, 10)[0].amount, U128(5 * ONE_LTS));
+
+        testing_env!(owner_context().block_timestamp(300000000000).build());
+        assert_eq!(contract.get_withdrawable(0), U128(15 * ONE_LTS));
+    }
+
+    #[test]
+    fn test_stream_withdraw_failed(){
+        let mut contract = contract_with_stream(100);
+        testing_env!(owner_context().predecessor_account_id("issameths.testnet".parse().unwrap()).block_timestamp(145000000000).build());
+        contract.withdraw_from_stream(0);
+        testing_env!(owner_context().block_timestamp(145000000000).build());
+        contract.stream_withdraw_callback(0, U128(5 * ONE_LTS), Err(PromiseError::Failed));
+        assert_eq!(contract.get_withdrawable(0), U128(5 * ONE_LTS));
+        assert_eq!(contract.get_number_of_outflows(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the receiver can withdraw from the stream")]
+    fn test_stream_withdraw_not_receiver(){
+        let mut contract = contract_with_stream(100);
+        testing_env!(owner_context().predecessor_account_id("alach.testnet".parse().unwrap()).block_timestamp(145000000000).build());
+        contract.withdraw_from_stream(0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The stream is already started")]
+    fn test_stream_start_twice(){
+        let mut contract = contract_with_stream(100);
+        contract.start_stream("stream".to_string(), "grants".to_string());
+    }
+
+    //testing that a refused stream proposal can't be shadowed by a passing proposal
+    #[test]
+    #[should_panic(expected = "A proposal with this id already exists")]
+    fn test_stream_proposal_shadowed(){
+        let mut contract = contract_with_budget();
+        testing_env!(owner_context().build());
+        contract.create_stream_proposal("stream".to_string(), "description".to_string(), "issameths.testnet".to_string(), 20, None, U64(100), 0, 0, 1);
+        contract.add_vote("stream".to_string(), 0);
+        contract.create_proposal("stream".to_string(), STREAM_PROPOSAL, "Stream grant".to_string(), "description".to_string(), 50, 0, 0, 1, "issameths.testnet".to_string(), None);
+    }
+
+    //testing the cancellation of a stream through a proposal
+    #[test]
+    fn test_cancel_stream(){
+        let mut contract = contract_with_stream(1000);
+        testing_env!(owner_context().block_timestamp(170000000000).build());
+        contract.create_cancel_stream_proposal("cancel".to_string(), "description".to_string(), 0, 0, 0, 1);
+        contract.add_vote("cancel".to_string(), 1);
+
+        // 110 seconds after the start, 2.2 LTS have streamed
+        testing_env!(owner_context().block_timestamp(230000000001).build());
+        contract.cancel_stream("cancel".to_string());
+        let stream = contract.get_stream(0).unwrap();
+        assert!(stream.cancelled);
+        assert_eq!(stream.amount, U128(220_000_000));
+        assert_eq!(contract.get_token_balance(LTS_TOKEN.to_string()), U128(100 * ONE_LTS - 220_000_000));
+        assert_eq!(contract.get_remaining_budget("grants".to_string()), U128(50 * ONE_LTS - 220_000_000));
+
+        testing_env!(owner_context().block_timestamp(2000000000000).build());
+        assert_eq!(contract.get_withdrawable(0), U128(220_000_000));
+    }
+
+    //testing the Ethereum address validation with the EIP-55 examples
+    #[test]
+    fn test_validate_eth_address(){
+        testing_env!(owner_context().build());
+        assert_eq!(validate_eth_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"), "5aaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        assert_eq!(validate_eth_address("fB6916095ca1df60bB79Ce92cE3Ea74c37c5d359"), "fb6916095ca1df60bb79ce92ce3ea74c37c5d359");
+        assert_eq!(validate_eth_address("0xdbf03b407c01e7cd3cbea99509d93f8dddc8c6fb"), "dbf03b407c01e7cd3cbea99509d93f8dddc8c6fb");
+    }
+
+    #[test]
+    #[should_panic(expected = "The Ethereum address checksum is wrong")]
+    fn test_validate_eth_address_checksum(){
+        testing_env!(owner_context().build());
+        validate_eth_address("0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed");
+    }
+
+    #[test]
+    #[should_panic(expected = "The Ethereum address must have 40 hex characters")]
+    fn test_validate_eth_address_length(){
+        testing_env!(owner_context().build());
+        validate_eth_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1bea");
+    }
+
+    #[test]
+    #[should_panic(expected = "The Ethereum address isn't hex")]
+    fn test_validate_eth_address_hex(){
+        testing_env!(owner_context().build());
+        validate_eth_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beazz");
+    }
+
+    // Bridges 5 NEAR of a treasury holding 20 NEAR at 2 minutes
+    fn contract_with_bridge_transfer() -> TreasuryDao {
+        testing_env!(owner_context().attached_deposit(20 * near_sdk::ONE_NEAR).build());
         let mut contract = TreasuryDao::new();
         contract.init();
-        contract.add_council("oussema.testnet".to_string);
-        assert!(contract.check_council("oussema.testnet".to_string());)
+        contract.deposit_near();
+        contract.create_bridge_proposal("bridge".to_string(), "description".to_string(), "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".to_string(), U128(5 * near_sdk::ONE_NEAR), 0, 0, 1);
+        contract.add_vote("bridge".to_string(), 1);
+        testing_env!(owner_context().block_timestamp(120000000000).build());
+        contract.bridge("bridge".to_string());
+        contract
+    }
+
+    //testing a transfer accepted by the bridge
+    #[test]
+    fn test_bridge_sent(){
+        let mut contract = contract_with_bridge_transfer();
+        let transfer = contract.get_bridge_transfer(0).unwrap();
+        assert_eq!(transfer.status, BridgeStatus::Pending);
+        assert_eq!(transfer.eth_recipient, "5aaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        assert_eq!(contract.get_token_balance(NEAR_TOKEN.to_string()), U128(15 * near_sdk::ONE_NEAR));
+
+        contract.bridge_callback(0, Ok(()));
+        assert_eq!(contract.get_bridge_transfers(0, 10)[0].status, BridgeStatus::Sent);
+        assert_eq!(contract.get_outflows(0, 10)[0].amount, U128(5 * near_sdk::ONE_NEAR));
+    }
+
+    //testing the refund of a transfer refused by the bridge
+    #[test]
+    fn test_bridge_failed(){
+        let mut contract = contract_with_bridge_transfer();
+        contract.bridge_callback(0, Err(PromiseError::Failed));
+        assert_eq!(contract.get_bridge_transfer(0).unwrap().status, BridgeStatus::Failed);
+        assert_eq!(contract.get_token_balance(NEAR_TOKEN.to_string()), U128(20 * near_sdk::ONE_NEAR));
+        assert_eq!(contract.get_number_of_outflows(), 0);
+
+        contract.bridge("bridge".to_string());
+        assert_eq!(contract.get_number_of_bridge_transfers(), 2);
     }
 
+    //testing that the transfer follows the request stored at creation, not the proposal record
+    #[test]
+    fn test_bridge_request_kept_apart(){
+        testing_env!(owner_context().attached_deposit(20 * near_sdk::ONE_NEAR).build());
+        let mut contract = TreasuryDao::new();
+        contract.init();
+        contract.deposit_near();
+        contract.create_bridge_proposal("bridge".to_string(), "description".to_string(), "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".to_string(), U128(5 * near_sdk::ONE_NEAR), 0, 0, 1);
+        contract.add_vote("bridge".to_string(), 1);
+        let mut proposal = contract.get_specific_proposal("bridge".to_string());
+        proposal.receiver = "fb6916095ca1df60bb79ce92ce3ea74c37c5d359".to_string();
+        proposal.amount = 20 * near_sdk::ONE_NEAR;
+        contract.replace_proposal(proposal);
+
+        testing_env!(owner_context().block_timestamp(120000000000).build());
+        contract.bridge("bridge".to_string());
+        let transfer = contract.get_bridge_transfer(0).unwrap();
+        assert_eq!(transfer.eth_recipient, "5aaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        assert_eq!(transfer.amount, U128(5 * near_sdk::ONE_NEAR));
+    }
+
+    #[test]
+    #[should_panic(expected = "The proposal is already bridged")]
+    fn test_bridge_twice(){
+        let mut contract = contract_with_bridge_transfer();
+        contract.bridge("bridge".to_string());
+    }
+
+    //testing a deposit bridged from Ethereum
+    #[test]
+    fn test_bridge_deposit(){
+        testing_env!(owner_context().build());
+        let mut contract = TreasuryDao::new();
+        contract.add_bridge_token("usdc.bridge.testnet".parse().unwrap());
+        testing_env!(owner_context().predecessor_account_id("usdc.bridge.testnet".parse().unwrap()).build());
+        contract.ft_on_transfer("alach.testnet".parse().unwrap(), U128(3_000_000), "bridge:0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed:series-a".to_string());
+        contract.ft_on_transfer("alach.testnet".parse().unwrap(), U128(1_000_000), "bridge:fb6916095ca1df60bb79ce92ce3ea74c37c5d359".to_string());
+
+        let deposits = contract.get_inbound_deposits(0, 10);
+        assert_eq!(deposits.len(), 2);
+        assert_eq!(deposits[0].eth_sender, "5aaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        assert_eq!(deposits[0].reference, Some("series-a".to_string()));
+        assert_eq!(deposits[1].reference, None);
+        assert_eq!(contract.get_token_balance("usdc.bridge.testnet".to_string()), U128(4_000_000));
+        assert!(near_sdk::test_utils::get_logs()[0].contains("\"event\":\"bridge_deposit\""));
+    }
+
+    #[test]
+    #[should_panic(expected = "A bridged deposit needs a msg like bridge:<ethereum sender>[:<reference>]")]
+    fn test_bridge_deposit_without_attribution(){
+        testing_env!(owner_context().build());
+        let mut contract = TreasuryDao::new();
+        contract.add_bridge_token("usdc.bridge.testnet".parse().unwrap());
+        testing_env!(owner_context().predecessor_account_id("usdc.bridge.testnet".parse().unwrap()).build());
+        contract.ft_on_transfer("alach.testnet".parse().unwrap(), U128(3_000_000), "".to_string());
+    }
 }
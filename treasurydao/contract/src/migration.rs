@@ -0,0 +1,164 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap, UnorderedSet, Vector};
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, Gas, PromiseError};
+
+use crate::{
+    assert_self, ext_ft, CouncilProposal, Role, TreasuryDao, TreasuryDaoExt, Vote, LTS_TOKEN, NEAR_TOKEN, RAINBOW_BRIDGE,
+    TGAS,
+};
+
+// Version of the layout written by this code, bumped with every change of a deployed layout
+pub const STATE_VERSION: u8 = 1;
+
+// Proposal deployed before the state was versioned, it only paid LTS
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, Default)]
+pub struct CouncilProposalV0 {
+    pub id: String,
+    pub proposal_type: u8,
+    pub proposal_name: String,
+    pub description: String,
+    pub amount: u128,
+    pub proposal_creator: String,
+    pub votes_for: u32,
+    pub votes_against: u32,
+    pub time_of_creation: u64,
+    pub duration_days: u64,
+    pub duration_hours: u64,
+    pub duration_min: u64,
+    pub list_voters: Vec<String>,
+    pub votes: Vec<Vote>,
+    pub receiver: String,
+}
+
+impl From<CouncilProposalV0> for CouncilProposal {
+    fn from(proposal: CouncilProposalV0) -> Self {
+        CouncilProposal {
+            id: proposal.id,
+            proposal_type: proposal.proposal_type,
+            proposal_name: proposal.proposal_name,
+            description: proposal.description,
+            amount: proposal.amount,
+            proposal_creator: proposal.proposal_creator,
+            votes_for: proposal.votes_for,
+            votes_against: proposal.votes_against,
+            time_of_creation: proposal.time_of_creation,
+            duration_days: proposal.duration_days,
+            duration_hours: proposal.duration_hours,
+            duration_min: proposal.duration_min,
+            list_voters: proposal.list_voters,
+            votes: proposal.votes,
+            receiver: proposal.receiver,
+            token: None,
+        }
+    }
+}
+
+// Layout deployed before the state was versioned
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct TreasuryDaoV0 {
+    pub stakers: Vec<String>,
+    // Roles were stored as the u8 they replaced, 0 for councils and 1 for communities
+    pub members: UnorderedMap<String, Role>,
+    pub proposals: Vec<CouncilProposalV0>,
+}
+
+impl From<TreasuryDaoV0> for TreasuryDao {
+    fn from(state: TreasuryDaoV0) -> Self {
+        let mut next = TreasuryDao {
+            stakers: state.stakers,
+            members: state.members,
+            proposals: state.proposals.into_iter().map(CouncilProposal::from).collect(),
+            upgrades: LookupMap::new(b"u"),
+            removals: LookupMap::new(b"x"),
+            tokens: UnorderedSet::new(b"w"),
+            balances: UnorderedMap::new(b"t"),
+            budgets: UnorderedMap::new(b"b"),
+            outflows: Vector::new(b"h"),
+            funded_proposals: LookupSet::new(b"f"),
+            stream_durations: LookupMap::new(b"d"),
+            stream_cancellations: LookupMap::new(b"c"),
+            streams: Vector::new(b"s"),
+            bridge_account: RAINBOW_BRIDGE.parse().unwrap(),
+            bridge_transfers: Vector::new(b"e"),
+            bridge_tokens: UnorderedSet::new(b"k"),
+            inbound_deposits: Vector::new(b"i"),
+            bridge_requests: LookupMap::new(b"q"),
+            fund_councils: LookupSet::new(b"n"),
+            state_version: STATE_VERSION,
+        };
+        next.tokens.insert(&LTS_TOKEN.to_string());
+        // The unversioned treasury kept no balances, its NEAR is what the account holds above its storage
+        let storage_cost = env::storage_usage() as u128 * env::storage_byte_cost();
+        next.balances.insert(&NEAR_TOKEN.to_string(), &env::account_balance().saturating_sub(storage_cost));
+        next
+    }
+}
+
+// Layouts the state can be stored with
+pub enum VersionedTreasuryDao {
+    V0(Box<TreasuryDaoV0>),
+    Current(Box<TreasuryDao>),
+}
+
+// Function to deserialize the state with the layout of its version
+fn parse<T: BorshDeserialize>(bytes: &[u8]) -> Box<T> {
+    Box::new(T::try_from_slice(bytes).expect("The state doesn't match its version"))
+}
+
+impl VersionedTreasuryDao {
+    // Function to read the stored state with the layout given by its version
+    // The versioned layout ends with `state_version`, the unversioned one ends with an account id
+    // or the length of an empty list, whose last byte is never a version
+    pub fn read() -> Self {
+        let bytes = env::storage_read(b"STATE").expect("The contract isn't initialized");
+        match bytes.last().copied() {
+            Some(STATE_VERSION) => VersionedTreasuryDao::Current(parse(&bytes)),
+            _ => VersionedTreasuryDao::V0(parse(&bytes)),
+        }
+    }
+
+    // Function to convert the stored layout to the current one
+    pub fn into_current(self) -> TreasuryDao {
+        match self {
+            VersionedTreasuryDao::V0(state) => (*state).into(),
+            VersionedTreasuryDao::Current(state) => *state,
+        }
+    }
+}
+
+#[near_bindgen]
+impl TreasuryDao {
+    // Function to upgrade the stored state after deploying a new version of the code
+    // The LTS of the unversioned treasury is read from the token once the state is migrated
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        assert_self();
+        let state = VersionedTreasuryDao::read();
+        if let VersionedTreasuryDao::V0(_) = state {
+            ext_ft::ext(LTS_TOKEN.parse().unwrap())
+                .with_static_gas(Gas(5 * TGAS))
+                .ft_balance_of(env::current_account_id())
+                .then(
+                    Self::ext(env::current_account_id())
+                        .with_static_gas(Gas(5 * TGAS))
+                        .sync_lts_callback(),
+                );
+        }
+        state.into_current()
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn sync_lts_callback(&mut self, #[callback_result] call_result: Result<U128, PromiseError>) {
+        match call_result {
+            Ok(balance) => {
+                self.balances.insert(&LTS_TOKEN.to_string(), &balance.0);
+            }
+            Err(_) => env::log_str("The LTS balance couldn't be read, it stays at 0"),
+        }
+    }
+
+    pub fn get_state_version(&self) -> u8 {
+        self.state_version
+    }
+}
@@ -0,0 +1,286 @@
+//! Grants paid as a stream.
+//!
+//! A passed stream proposal pays its amount at a constant rate per second between the start and
+//! the end of the stream, and the receiver withdraws what has accrued whenever they like. A passed
+//! cancel proposal stops the flow and gives what hasn't streamed yet back to the budget.
+
+// Stream proposals take the arguments of create_proposal plus the length of the stream
+#![allow(clippy::too_many_arguments)]
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{U128, U64};
+use near_sdk::{env, near_bindgen, Gas, Promise, PromiseError};
+use serde::{Deserialize, Serialize};
+
+use crate::events::{Event, ProposalCreateData, StreamCancelData, StreamStartData, StreamWithdrawData, TreasuryEvent};
+use crate::{CouncilProposal, TreasuryDao, TreasuryDaoExt, LTS_TOKEN, ONE_LTS, TGAS};
+
+// Proposal type paying a grant as a stream
+pub const STREAM_PROPOSAL: u8 = 5;
+
+// Proposal type cancelling what is left of a stream
+pub const CANCEL_STREAM_PROPOSAL: u8 = 6;
+
+const ONE_SECOND: u64 = 1_000_000_000;
+
+// Grant flowing to its receiver
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stream {
+    pub id: u64,
+    pub proposal_id: String,
+    pub category: String,
+    pub receiver: String,
+    pub token: String,
+    // Total paid by the stream in the smallest unit of the token
+    pub amount: U128,
+    // Amount accrued every second
+    pub rate: U128,
+    // Start and end of the flow in nanoseconds
+    pub start: U64,
+    pub end: U64,
+    pub withdrawn: U128,
+    pub cancelled: bool,
+}
+
+impl Stream {
+    // Function to get what has flowed to the receiver at `timestamp`
+    pub fn streamed(&self, timestamp: u64) -> u128 {
+        if timestamp >= self.end.0 {
+            return self.amount.0;
+        }
+        let seconds = (timestamp.saturating_sub(self.start.0) / ONE_SECOND) as u128;
+        std::cmp::min(self.rate.0 * seconds, self.amount.0)
+    }
+
+    pub fn withdrawable(&self, timestamp: u64) -> u128 {
+        self.streamed(timestamp) - self.withdrawn.0
+    }
+}
+
+impl TreasuryDao {
+    fn internal_get_stream(&self, stream_id: u64) -> Stream {
+        self.streams.get(stream_id).expect("There is no stream with this id")
+    }
+
+    // Function to create a proposal whose fields are set by the caller
+    pub(crate) fn internal_push_proposal(&mut self, mut proposal: CouncilProposal) {
+        assert!(
+            self.check_council(env::signer_account_id().to_string()),
+            "Proposals can be created only by the councils"
+        );
+        assert!(
+            self.get_proposals().iter().all(|p| p.id != proposal.id),
+            "A proposal with this id already exists"
+        );
+        proposal.proposal_creator = env::signer_account_id().to_string();
+        proposal.time_of_creation = env::block_timestamp();
+        TreasuryEvent::ProposalCreate(&[ProposalCreateData {
+            id: &proposal.id,
+            proposal_type: proposal.proposal_type,
+            proposal_name: &proposal.proposal_name,
+            creator: &proposal.proposal_creator,
+            amount: U128(proposal.amount),
+            receiver: &proposal.receiver,
+            token: proposal.token.as_deref(),
+        }])
+        .emit();
+        self.proposals.push(proposal);
+    }
+}
+
+#[near_bindgen]
+impl TreasuryDao {
+    // Function to propose paying `amount` to `receiver` over `stream_duration` seconds
+    // The amount is in whole LTS without a token, in the smallest unit of the token otherwise
+    pub fn create_stream_proposal(
+        &mut self,
+        id: String,
+        description: String,
+        receiver: String,
+        amount: u128,
+        token: Option<String>,
+        stream_duration: U64,
+        duration_days: u64,
+        duration_hours: u64,
+        duration_min: u64,
+    ) {
+        assert!(stream_duration.0 > 0, "The stream can't be empty");
+        assert!(amount > 0, "The stream must pay something");
+        if let Some(token) = &token {
+            self.assert_known_token(token);
+        }
+        let mut proposal = CouncilProposal::new();
+        proposal.id = id.clone();
+        proposal.proposal_type = STREAM_PROPOSAL;
+        proposal.proposal_name = "Stream grant".to_string();
+        proposal.description = description;
+        proposal.amount = amount;
+        proposal.duration_days = duration_days;
+        proposal.duration_hours = duration_hours;
+        proposal.duration_min = duration_min;
+        proposal.receiver = receiver;
+        proposal.token = token;
+        self.internal_push_proposal(proposal);
+        self.stream_durations.insert(&id, &stream_duration.0);
+    }
+
+    // Function to start the stream of a passed proposal from the budget of `category`
+    pub fn start_stream(&mut self, proposal_id: String, category: String) -> u64 {
+        assert!(
+            self.check_council(env::signer_account_id().to_string()),
+            "Streams can be started only by the councils"
+        );
+        let duration = self.stream_durations.get(&proposal_id).expect("There is no stream proposal with this id");
+        let proposal = self.get_specific_proposal(proposal_id.clone());
+        assert!(proposal.check_proposal(), "The proposal hasn't passed");
+        assert!(self.funded_proposals.insert(&proposal_id), "The stream is already started");
+        let (token, amount) = match proposal.token {
+            Some(token) => (token, proposal.amount),
+            None => (LTS_TOKEN.to_string(), proposal.amount * ONE_LTS),
+        };
+        self.internal_reserve(Some(&category), &token, amount);
+        let start = env::block_timestamp();
+        let stream = Stream {
+            id: self.streams.len(),
+            proposal_id,
+            category,
+            receiver: proposal.receiver,
+            token,
+            amount: U128(amount),
+            rate: U128(amount / duration as u128),
+            start: U64(start),
+            end: U64(start + duration * ONE_SECOND),
+            withdrawn: U128(0),
+            cancelled: false,
+        };
+        TreasuryEvent::StreamStart(&[StreamStartData {
+            stream_id: stream.id,
+            proposal_id: &stream.proposal_id,
+            receiver_id: &stream.receiver,
+            token: &stream.token,
+            amount: stream.amount,
+            rate: stream.rate,
+            start: stream.start,
+            end: stream.end,
+        }])
+        .emit();
+        self.streams.push(&stream);
+        stream.id
+    }
+
+    // Function to send the receiver what has accrued on its stream
+    pub fn withdraw_from_stream(&mut self, stream_id: u64) -> Promise {
+        let mut stream = self.internal_get_stream(stream_id);
+        assert_eq!(
+            env::predecessor_account_id().to_string(),
+            stream.receiver,
+            "Only the receiver can withdraw from the stream"
+        );
+        let amount = stream.withdrawable(env::block_timestamp());
+        assert!(amount > 0, "Nothing to withdraw yet");
+        stream.withdrawn = U128(stream.withdrawn.0 + amount);
+        self.streams.replace(stream_id, &stream);
+        TreasuryEvent::StreamWithdraw(&[StreamWithdrawData {
+            stream_id,
+            receiver_id: &stream.receiver,
+            amount: U128(amount),
+        }])
+        .emit();
+        self.internal_transfer(&stream.token, stream.receiver.parse().unwrap(), amount)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(Gas(5 * TGAS))
+                    .stream_withdraw_callback(stream_id, U128(amount)),
+            )
+    }
+
+    // Function to propose stopping a stream
+    pub fn create_cancel_stream_proposal(
+        &mut self,
+        id: String,
+        description: String,
+        stream_id: u64,
+        duration_days: u64,
+        duration_hours: u64,
+        duration_min: u64,
+    ) {
+        let stream = self.internal_get_stream(stream_id);
+        assert!(!stream.cancelled, "The stream is already cancelled");
+        let mut proposal = CouncilProposal::new();
+        proposal.id = id.clone();
+        proposal.proposal_type = CANCEL_STREAM_PROPOSAL;
+        proposal.proposal_name = "Cancel stream".to_string();
+        proposal.description = description;
+        proposal.duration_days = duration_days;
+        proposal.duration_hours = duration_hours;
+        proposal.duration_min = duration_min;
+        proposal.receiver = stream.receiver;
+        self.internal_push_proposal(proposal);
+        self.stream_cancellations.insert(&id, &stream_id);
+    }
+
+    // Function to stop the stream of a passed cancel proposal, callable by anyone
+    // What has accrued stays withdrawable and the rest goes back to the budget
+    pub fn cancel_stream(&mut self, id: String) {
+        let stream_id = self.stream_cancellations.get(&id).expect("There is no cancellation with this id");
+        assert!(
+            self.get_specific_proposal(id.clone()).check_proposal(),
+            "The cancel proposal hasn't passed"
+        );
+        self.stream_cancellations.remove(&id);
+        let mut stream = self.internal_get_stream(stream_id);
+        assert!(!stream.cancelled, "The stream is already cancelled");
+        let now = env::block_timestamp();
+        let streamed = stream.streamed(now);
+        let refunded = stream.amount.0 - streamed;
+        self.internal_release(Some(&stream.category), &stream.token, refunded);
+        stream.amount = U128(streamed);
+        stream.end = U64(std::cmp::min(now, stream.end.0));
+        stream.cancelled = true;
+        self.streams.replace(stream_id, &stream);
+        TreasuryEvent::StreamCancel(&[StreamCancelData {
+            stream_id,
+            proposal_id: &id,
+            refunded: U128(refunded),
+        }])
+        .emit();
+    }
+
+    pub fn get_stream(&self, stream_id: u64) -> Option<Stream> {
+        self.streams.get(stream_id)
+    }
+
+    pub fn get_streams(&self, from_index: u64, limit: u64) -> Vec<Stream> {
+        (from_index..std::cmp::min(from_index + limit, self.streams.len()))
+            .map(|i| self.streams.get(i).unwrap())
+            .collect()
+    }
+
+    // Function to get what the receiver of a stream can withdraw now
+    pub fn get_withdrawable(&self, stream_id: u64) -> U128 {
+        U128(self.internal_get_stream(stream_id).withdrawable(env::block_timestamp()))
+    }
+
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn stream_withdraw_callback(
+        &mut self,
+        stream_id: u64,
+        amount: U128,
+        #[callback_result] call_result: Result<(), PromiseError>,
+    ) {
+        let mut stream = self.internal_get_stream(stream_id);
+        if call_result.is_err() {
+            stream.withdrawn = U128(stream.withdrawn.0 - amount.0);
+            self.streams.replace(stream_id, &stream);
+            env::log_str("The transfer failed, the amount can be withdrawn again");
+        } else {
+            self.internal_record_outflow(
+                Some(stream.proposal_id),
+                Some(stream.category),
+                stream.receiver,
+                &stream.token,
+                amount.0,
+            );
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Tokens held by the treasury.
+//!
+//! NEAR is identified as "NEAR" and fungible tokens by the account of their NEP-141 contract.
+//! Only whitelisted tokens are accepted through `ft_on_transfer`, and receivers are registered on
+//! the token contract before each transfer so payouts don't fail on unregistered accounts.
+
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen, AccountId, Gas, Promise, PromiseOrValue};
+
+use crate::events::{DepositData, Event, TreasuryEvent};
+use crate::{assert_self, ext_ft, TreasuryDao, TreasuryDaoExt, TGAS};
+
+pub const NEAR_TOKEN: &str = "NEAR";
+pub const LTS_TOKEN: &str = "light-token.testnet";
+
+// Deposit attached to register a receiver on a token, the token refunds what isn't used
+pub const STORAGE_DEPOSIT: u128 = 12_500_000_000_000_000_000_000;
+
+impl TreasuryDao {
+    pub(crate) fn assert_known_token(&self, token: &str) {
+        assert!(
+            token == NEAR_TOKEN
+                || self.tokens.contains(&token.to_string())
+                || self.bridge_tokens.contains(&token.to_string()),
+            "The token {} isn't whitelisted",
+            token
+        );
+    }
+
+    pub(crate) fn internal_deposit(&mut self, token: &str, amount: u128) {
+        let balance = self.balances.get(&token.to_string()).unwrap_or(0);
+        self.balances.insert(&token.to_string(), &(balance + amount));
+    }
+
+    pub(crate) fn internal_withdraw(&mut self, token: &str, amount: u128) {
+        let balance = self.balances.get(&token.to_string()).unwrap_or(0);
+        assert!(balance >= amount, "The treasury doesn't hold enough {}", token);
+        self.balances.insert(&token.to_string(), &(balance - amount));
+    }
+
+    // Function to send `amount` of `token` to `receiver`, registering it on the token first
+    pub(crate) fn internal_transfer(&self, token: &str, receiver: AccountId, amount: u128) -> Promise {
+        if token == NEAR_TOKEN {
+            return Promise::new(receiver).transfer(amount);
+        }
+        let token_id: AccountId = token.parse().unwrap();
+        ext_ft::ext(token_id.clone())
+            .with_static_gas(Gas(5 * TGAS))
+            .with_attached_deposit(STORAGE_DEPOSIT)
+            .storage_deposit(Some(receiver.clone()), Some(true))
+            .then(
+                ext_ft::ext(token_id)
+                    .with_static_gas(Gas(5 * TGAS))
+                    .with_attached_deposit(1)
+                    .ft_transfer(receiver, U128(amount), None),
+            )
+    }
+}
+
+#[near_bindgen]
+impl TreasuryDao {
+    // Function receiving the whitelisted tokens sent to the treasury
+    // Tokens of a bridge token contract are credited as bridged deposits
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        let token = env::predecessor_account_id().to_string();
+        if self.bridge_tokens.contains(&token) {
+            self.internal_bridge_deposit(token, sender_id, amount.0, &msg);
+            return PromiseOrValue::Value(U128(0));
+        }
+        assert!(self.tokens.contains(&token), "The token {} isn't whitelisted", token);
+        self.internal_deposit(&token, amount.0);
+        TreasuryEvent::Deposit(&[DepositData {
+            sender_id: sender_id.as_str(),
+            token: &token,
+            amount,
+            memo: Some(msg.as_str()).filter(|msg| !msg.is_empty()),
+        }])
+        .emit();
+        PromiseOrValue::Value(U128(0))
+    }
+
+    // Function to deposit NEAR to the treasury
+    #[payable]
+    pub fn deposit_near(&mut self) {
+        let amount = env::attached_deposit();
+        assert!(amount > 0, "Attach the NEAR to deposit");
+        self.internal_deposit(NEAR_TOKEN, amount);
+        TreasuryEvent::Deposit(&[DepositData {
+            sender_id: env::predecessor_account_id().as_str(),
+            token: NEAR_TOKEN,
+            amount: U128(amount),
+            memo: None,
+        }])
+        .emit();
+    }
+
+    // Function to accept a NEP-141 token
+    pub fn add_token(&mut self, token: AccountId) {
+        assert_self();
+        self.tokens.insert(&token.to_string());
+    }
+
+    // Function to stop accepting a token, its balance stays spendable
+    pub fn remove_token(&mut self, token: AccountId) {
+        assert_self();
+        self.tokens.remove(&token.to_string());
+    }
+
+    pub fn get_tokens(&self) -> Vec<String> {
+        self.tokens.to_vec()
+    }
+
+    pub fn get_token_balance(&self, token: String) -> U128 {
+        U128(self.balances.get(&token).unwrap_or(0))
+    }
+
+    pub fn get_balances(&self) -> Vec<(String, U128)> {
+        self.balances.iter().map(|(token, balance)| (token, U128(balance))).collect()
+    }
+}
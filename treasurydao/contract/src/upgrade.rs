@@ -0,0 +1,106 @@
+//! Upgrades of the contract code governed by the council.
+//!
+//! A council member proposes the sha256 hash of the new wasm. Once the proposal has passed with
+//! more than half of the councils voting for it, anyone can call `upgrade` with the matching code
+//! as the raw input of the call: the contract deploys it to itself and calls `migrate` to convert
+//! the state to the new layout.
+
+use near_sdk::json_types::Base58CryptoHash;
+use near_sdk::{env, near_bindgen, CryptoHash, Gas, Promise};
+
+use crate::events::{Event, ProposalCreateData, TreasuryEvent, UpgradeData};
+use crate::{CouncilProposal, TreasuryDao, TreasuryDaoExt, TGAS};
+
+// Proposal type of code upgrades (0: fund project, 1: buy LTS, 2: sell LTS)
+pub const UPGRADE_PROPOSAL: u8 = 3;
+
+// Gas given to the `migrate` call following the deployment
+pub const GAS_FOR_MIGRATE: u64 = 50 * TGAS;
+
+impl TreasuryDao {
+    // Function to check that more than half of the current councils voted for a proposal
+    fn internal_council_majority(&self, proposal: &CouncilProposal) -> bool {
+        let councils = self.get_councils();
+        let votes_for = proposal
+            .votes
+            .iter()
+            .filter(|v| v.vote != 0 && councils.contains(&v.address))
+            .count();
+        votes_for * 2 > councils.len()
+    }
+}
+
+#[near_bindgen]
+impl TreasuryDao {
+    // Function to propose deploying the code whose sha256 hash is `code_hash`
+    pub fn create_upgrade_proposal(
+        &mut self,
+        id: String,
+        description: String,
+        code_hash: Base58CryptoHash,
+        duration_days: u64,
+        duration_hours: u64,
+        duration_min: u64,
+    ) {
+        assert!(
+            self.check_council(env::signer_account_id().to_string()),
+            "Proposals can be created only by the councils"
+        );
+        assert!(
+            self.get_proposals().iter().all(|p| p.id != id),
+            "A proposal with this id already exists"
+        );
+        let code_hash: CryptoHash = code_hash.into();
+        assert!(self.upgrades.get(&code_hash).is_none(), "This code is already proposed");
+        let mut proposal = CouncilProposal::new();
+        proposal.id = id;
+        proposal.proposal_type = UPGRADE_PROPOSAL;
+        proposal.proposal_name = "Upgrade".to_string();
+        proposal.description = description;
+        proposal.proposal_creator = env::signer_account_id().to_string();
+        proposal.time_of_creation = env::block_timestamp();
+        proposal.duration_days = duration_days;
+        proposal.duration_hours = duration_hours;
+        proposal.duration_min = duration_min;
+        proposal.receiver = env::current_account_id().to_string();
+        TreasuryEvent::ProposalCreate(&[ProposalCreateData {
+            id: &proposal.id,
+            proposal_type: proposal.proposal_type,
+            proposal_name: &proposal.proposal_name,
+            creator: &proposal.proposal_creator,
+            amount: 0.into(),
+            receiver: &proposal.receiver,
+            token: None,
+        }])
+        .emit();
+        self.upgrades.insert(&code_hash, &proposal.id);
+        self.proposals.push(proposal);
+    }
+
+    // Function to get the proposal id of a proposed code hash
+    pub fn get_upgrade_proposal(&self, code_hash: Base58CryptoHash) -> Option<String> {
+        self.upgrades.get(&code_hash.into())
+    }
+
+    // Function to deploy the code given as raw input once its proposal has passed
+    pub fn upgrade(&mut self) -> Promise {
+        let code = env::input().expect("The code is missing");
+        let code_hash: CryptoHash = env::sha256_array(&code);
+        let id = self.upgrades.get(&code_hash).expect("This code hasn't been proposed");
+        let proposal = self.get_specific_proposal(id.clone());
+        assert!(proposal.check_proposal(), "The upgrade proposal hasn't passed");
+        assert!(
+            self.internal_council_majority(&proposal),
+            "The upgrade needs the votes of more than half of the councils"
+        );
+        self.upgrades.remove(&code_hash);
+        TreasuryEvent::Upgrade(&[UpgradeData {
+            proposal_id: &id,
+            code_hash: Base58CryptoHash::from(code_hash),
+        }])
+        .emit();
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call("migrate".to_string(), Vec::new(), 0, Gas(GAS_FOR_MIGRATE))
+    }
+}
@@ -3,14 +3,22 @@ use near_units::parse_near;
 use serde_json::json;
 use workspaces::{Account, Contract};
 
+// Checksummed address from EIP-55 and its format on the bridge
+const ETH_RECIPIENT: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+const BRIDGE_RECIPIENT: &str = "5aaeb6053f3e94c9b9a09f33669435e7ef1beaed";
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let wasm_arg: &str = &(env::args().nth(1).unwrap());
     let wasm_filepath = fs::canonicalize(env::current_dir()?.join(wasm_arg))?;
+    let bridge_wasm_arg: &str = &(env::args().nth(2).unwrap());
+    let bridge_wasm_filepath = fs::canonicalize(env::current_dir()?.join(bridge_wasm_arg))?;
 
     let worker = workspaces::sandbox().await?;
     let wasm = std::fs::read(wasm_filepath)?;
     let contract = worker.dev_deploy(&wasm).await?;
+    let bridge_wasm = std::fs::read(bridge_wasm_filepath)?;
+    let bridge = worker.dev_deploy(&bridge_wasm).await?;
 
     // create accounts
     let account = worker.dev_create_account().await?;
@@ -21,46 +29,89 @@ async fn main() -> anyhow::Result<()> {
         .await?
         .into_result()?;
 
+    // the treasury is its own council and holds 20 NEAR
+    contract.call("new").args_json(json!({})).transact().await?.into_result()?;
+    contract.call("init").args_json(json!({})).transact().await?.into_result()?;
+    contract
+        .call("set_bridge_account")
+        .args_json(json!({"account": bridge.id()}))
+        .transact()
+        .await?
+        .into_result()?;
+    contract
+        .call("deposit_near")
+        .deposit(parse_near!("20 N"))
+        .transact()
+        .await?
+        .into_result()?;
+
     // begin tests
-    test_default_message(&alice, &contract).await?;
-    test_changes_message(&alice, &contract).await?;
+    test_bridge_transfers(&alice, &contract, &bridge).await?;
     Ok(())
 }
 
-async fn test_default_message(
-    user: &Account,
-    contract: &Contract,
-) -> anyhow::Result<()> {
-    let message: String = user
-        .call( contract.id(), "get_greeting")
-        .args_json(json!({}))
+async fn create_bridge_proposal(contract: &Contract, id: &str) -> anyhow::Result<()> {
+    contract
+        .call("create_bridge_proposal")
+        .args_json(json!({
+            "id": id,
+            "description": "Bridge to the Ethereum treasury",
+            "eth_recipient": ETH_RECIPIENT,
+            "amount": parse_near!("5 N").to_string(),
+            "duration_days": 0,
+            "duration_hours": 0,
+            "duration_min": 1,
+        }))
         .transact()
         .await?
-        .json()?;
-
-    assert_eq!(message, "Hello".to_string());
-    println!("      Passed ✅ gets default message");
+        .into_result()?;
+    contract
+        .call("add_vote")
+        .args_json(json!({"id": id, "vote": 1}))
+        .transact()
+        .await?
+        .into_result()?;
     Ok(())
 }
 
-async fn test_changes_message(
+async fn test_bridge_transfers(
     user: &Account,
     contract: &Contract,
+    bridge: &Contract,
 ) -> anyhow::Result<()> {
-    user.call(contract.id(), "set_greeting")
-        .args_json(json!({"message": "Howdy"}))
+    create_bridge_proposal(contract, "accepted").await?;
+    create_bridge_proposal(contract, "refused").await?;
+    // wait for the end of the votes
+    tokio::time::sleep(std::time::Duration::from_secs(61)).await;
+
+    user.call(contract.id(), "bridge")
+        .args_json(json!({"proposal_id": "accepted"}))
+        .max_gas()
         .transact()
         .await?
         .into_result()?;
+    let transfers: serde_json::Value = bridge.view("get_transfers", json!({}).to_string().into_bytes()).await?.json()?;
+    assert_eq!(transfers, json!([[BRIDGE_RECIPIENT, parse_near!("5 N").to_string()]]));
 
-    let message: String = user
-        .call(contract.id(), "get_greeting")
-        .args_json(json!({}))
+    bridge.call("set_refuse").args_json(json!({"refuse": true})).transact().await?.into_result()?;
+    user.call(contract.id(), "bridge")
+        .args_json(json!({"proposal_id": "refused"}))
+        .max_gas()
         .transact()
         .await?
-        .json()?;
+        .into_result()?;
 
-    assert_eq!(message, "Howdy".to_string());
-    println!("      Passed ✅ changes message");
+    let history: serde_json::Value = contract
+        .view("get_bridge_transfers", json!({"from_index": 0, "limit": 10}).to_string().into_bytes())
+        .await?
+        .json()?;
+    assert_eq!(history[0]["status"], "sent");
+    assert_eq!(history[1]["status"], "failed");
+    let balance: String = contract
+        .view("get_token_balance", json!({"token": "NEAR"}).to_string().into_bytes())
+        .await?
+        .json()?;
+    assert_eq!(balance, parse_near!("15 N").to_string());
+    println!("      Passed ✅ bridges to Ethereum and refunds the refused transfers");
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,34 @@
+//! Stand-in for the `migrate_to_ethereum` method of the Rainbow Bridge, used by the sandbox tests.
+//!
+//! It keeps the transfers it receives and refuses them while `refuse` is set, so the treasury can
+//! be tested on both outcomes of a bridge transfer.
+
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::U128;
+use near_sdk::{env, near_bindgen};
+
+#[near_bindgen]
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct MockBridge {
+    // Ethereum recipient and yoctoNEAR of each transfer
+    transfers: Vec<(String, U128)>,
+    refuse: bool,
+}
+
+#[near_bindgen]
+impl MockBridge {
+    #[payable]
+    pub fn migrate_to_ethereum(&mut self, eth_recipient: String) {
+        assert!(!self.refuse, "The bridge refuses the transfers");
+        assert_eq!(eth_recipient.len(), 40, "The recipient must be 40 hex characters");
+        self.transfers.push((eth_recipient, U128(env::attached_deposit())));
+    }
+
+    pub fn set_refuse(&mut self, refuse: bool) {
+        self.refuse = refuse;
+    }
+
+    pub fn get_transfers(&self) -> Vec<(String, U128)> {
+        self.transfers.clone()
+    }
+}
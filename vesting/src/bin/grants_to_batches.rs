@@ -0,0 +1,173 @@
+// Offline helper for token generation events
+// Converts a CSV of vesting grants into the arguments of `add_lockups`, one batch per line
+//
+// Usage: grants_to_batches <grants.csv> [batch_size]
+//
+// CSV columns: id,owner_id,amount_of_token,duration,payment_interval,revocable
+// (amount_of_token in LTS, payment_interval in milliseconds, revocable is true/false)
+//
+// Each output line can be passed to near-cli:
+// near call <vesting> add_lockups "$line" --accountId <vesting> --gas 300000000000000
+
+use std::collections::HashSet;
+use std::{env, fs, process};
+
+use vesting::LockupEntry;
+
+const DEFAULT_BATCH_SIZE: usize = 15;
+
+fn parse_line(number: usize, line: &str) -> Result<LockupEntry, String> {
+    let columns: Vec<&str> = line.split(',').map(|c| c.trim()).collect();
+    if columns.len() != 6 {
+        return Err(format!("line {}: expected 6 columns, found {}", number, columns.len()));
+    }
+    let error = |column: &str| format!("line {}: invalid {} `{}`", number, column, line);
+    Ok(LockupEntry {
+        id: columns[0].to_string(),
+        owner_id: columns[1].parse().map_err(|_| error("owner_id"))?,
+        amount_of_token: columns[2].parse().map_err(|_| error("amount_of_token"))?,
+        duration: columns[3].parse().map_err(|_| error("duration"))?,
+        payment_interval: columns[4].parse().map_err(|_| error("payment_interval"))?,
+        revocable: columns[5].parse().map_err(|_| error("revocable"))?,
+    })
+}
+
+fn parse_grants(csv: &str) -> Result<Vec<LockupEntry>, String> {
+    let mut entries = Vec::new();
+    let mut ids = HashSet::new();
+    for (index, line) in csv.lines().enumerate() {
+        let number = index + 1;
+        if line.trim().is_empty() || (index == 0 && line.starts_with("id,")) {
+            continue;
+        }
+        let entry = parse_line(number, line)?;
+        if entry.amount_of_token == 0 {
+            return Err(format!("line {}: the amount must be greater than 0", number));
+        }
+        if entry.duration == 0 || entry.duration > u8::MAX as u64 {
+            return Err(format!("line {}: the number of payments must be between 1 and 255", number));
+        }
+        if entry.payment_interval == 0 {
+            return Err(format!("line {}: the payment interval must be greater than 0", number));
+        }
+        if !ids.insert(entry.id.clone()) {
+            return Err(format!("line {}: duplicated id {}", number, entry.id));
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+// One line of `add_lockups` arguments per batch of at most `batch_size` grants
+fn batches(entries: &[LockupEntry], batch_size: usize) -> Vec<String> {
+    entries
+        .chunks(batch_size.max(1))
+        .map(|batch| serde_json::json!({ "entries": batch }).to_string())
+        .collect()
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        eprintln!("Usage: {} <grants.csv> [batch_size]", args[0]);
+        process::exit(1);
+    }
+    let batch_size: usize = match args.get(2) {
+        Some(size) => size.parse().unwrap_or_else(|_| {
+            eprintln!("Invalid batch size {}", size);
+            process::exit(1);
+        }),
+        None => DEFAULT_BATCH_SIZE,
+    };
+    let csv = fs::read_to_string(&args[1]).unwrap_or_else(|e| {
+        eprintln!("Cannot read {}: {}", args[1], e);
+        process::exit(1);
+    });
+    let entries = parse_grants(&csv).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+
+    let lines = batches(&entries, batch_size);
+    for line in lines.iter() {
+        println!("{}", line);
+    }
+    let total: u128 = entries.iter().map(|e| e.amount_of_token).sum();
+    eprintln!(
+        "{} grants in {} batches, {} LTS must be deposited in the vesting contract",
+        entries.len(),
+        lines.len(),
+        total
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "id,owner_id,amount_of_token,duration,payment_interval,revocable
+v1,issameths.testnet,40,4,120000,true
+
+v2, oussema.testnet , 10, 2, 60000, false
+v3,alach.testnet,25,1,60000,true
+";
+
+    #[test]
+    fn test_parse_grants() {
+        let entries = parse_grants(CSV).unwrap();
+        assert_eq!(entries.len(), 3);
+        //the header and the empty lines are skipped, the columns are trimmed
+        assert_eq!(entries[1].id, "v2");
+        assert_eq!(entries[1].owner_id.as_str(), "oussema.testnet");
+        assert_eq!(entries[1].amount_of_token, 10);
+        assert_eq!(entries[1].duration, 2);
+        assert_eq!(entries[1].payment_interval, 60000);
+        assert!(!entries[1].revocable);
+    }
+
+    #[test]
+    fn test_malformed_rows() {
+        let error = |csv: &str| parse_grants(csv).unwrap_err();
+        assert_eq!(error("v1,issameths.testnet,40,4,120000"), "line 1: expected 6 columns, found 5");
+        assert_eq!(
+            error("v1,Issameths!,40,4,120000,true"),
+            "line 1: invalid owner_id `v1,Issameths!,40,4,120000,true`"
+        );
+        assert_eq!(
+            error("v1,issameths.testnet,forty,4,120000,true"),
+            "line 1: invalid amount_of_token `v1,issameths.testnet,forty,4,120000,true`"
+        );
+        assert_eq!(
+            error("v1,issameths.testnet,40,4,120000,yes"),
+            "line 1: invalid revocable `v1,issameths.testnet,40,4,120000,yes`"
+        );
+        assert_eq!(error("v1,issameths.testnet,0,4,120000,true"), "line 1: the amount must be greater than 0");
+        assert_eq!(
+            error("v1,issameths.testnet,40,256,120000,true"),
+            "line 1: the number of payments must be between 1 and 255"
+        );
+        assert_eq!(
+            error("v1,issameths.testnet,40,4,0,true"),
+            "line 1: the payment interval must be greater than 0"
+        );
+        assert_eq!(
+            error("v1,issameths.testnet,40,4,120000,true\nv1,alach.testnet,10,4,120000,true"),
+            "line 2: duplicated id v1"
+        );
+    }
+
+    #[test]
+    fn test_batches() {
+        let entries = parse_grants(CSV).unwrap();
+        let lines = batches(&entries, 2);
+        assert_eq!(lines.len(), 2);
+        let first: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(first["entries"].as_array().unwrap().len(), 2);
+        assert_eq!(first["entries"][0]["id"], "v1");
+        let last: serde_json::Value = serde_json::from_str(&lines[1]).unwrap();
+        assert_eq!(last["entries"][0]["id"], "v3");
+        //a batch size of 0 still sends one grant per batch
+        assert_eq!(batches(&entries, 0).len(), 3);
+        assert!(batches(&[], 15).is_empty());
+    }
+}
@@ -0,0 +1,64 @@
+// NEP-297 events of the vesting contract
+// Every event is logged as `EVENT_JSON:{"standard":"lightency","version":..,"event":..,"data":[..]}`
+// so the indexer can rebuild the history of each vesting.
+
+pub use lightency_events::Event;
+use near_sdk::json_types::U128;
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+pub struct VestingCreateData<'a> {
+    pub id: &'a str,
+    pub owner_id: &'a str,
+    pub amount: U128,
+    pub duration: u64,
+    pub payment_interval: u64,
+    pub revocable: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct VestingClaimData<'a> {
+    pub id: &'a str,
+    pub owner_id: &'a str,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+pub struct VestingRevokeData<'a> {
+    pub id: &'a str,
+    pub owner_id: &'a str,
+    pub unvested_amount: U128,
+    pub treasury_id: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+pub struct VestingTransferData<'a> {
+    pub id: &'a str,
+    pub old_owner_id: &'a str,
+    pub new_owner_id: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", content = "data")]
+pub enum VestingEvent<'a> {
+    #[serde(rename = "vesting_create")]
+    Create(&'a [VestingCreateData<'a>]),
+    #[serde(rename = "vesting_claim")]
+    Claim(&'a [VestingClaimData<'a>]),
+    #[serde(rename = "vesting_revoke")]
+    Revoke(&'a [VestingRevokeData<'a>]),
+    #[serde(rename = "vesting_transfer")]
+    Transfer(&'a [VestingTransferData<'a>]),
+}
+
+impl Event for VestingEvent<'_> {
+    // Version of the data schema of each event, bumped when its fields change
+    fn version(&self) -> &'static str {
+        match self {
+            VestingEvent::Create(_) => "1.0.0",
+            VestingEvent::Claim(_) => "1.0.0",
+            VestingEvent::Revoke(_) => "1.0.0",
+            VestingEvent::Transfer(_) => "1.0.0",
+        }
+    }
+}
@@ -1,14 +1,36 @@
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::Vector;
-use near_sdk::{ext_contract, Promise, PromiseError};
+use near_sdk::collections::UnorderedMap;
+use near_sdk::json_types::U128;
+use near_sdk::{ext_contract, Promise, PromiseError, PromiseOrValue};
 use near_sdk::{env, near_bindgen, Gas, AccountId};
 use serde::{Serialize,Deserialize};
 
+mod events;
+mod migration;
+pub use migration::{VersionedVestingContract, VestingContractV0, VestorsV0, STATE_VERSION};
+use events::{Event, VestingClaimData, VestingCreateData, VestingEvent, VestingRevokeData, VestingTransferData};
+
 pub const TGAS: u64 = 1_000_000_000_000;
 
+// 1 LTS expressed in the token smallest unit (LTS has 8 decimals)
+pub const ONE_LTS: u128 = 100_000_000;
+
+// Default schedule: 4 payments, one every 2 minutes
+pub const DEFAULT_DURATION: u64 = 4;
+pub const DEFAULT_PAYMENT_INTERVAL: u64 = 2 * 60000;
+
+// Deposit attached to register a new beneficiary in the LTS contract, at least
+// storage_balance_bounds().min of the token which refunds what isn't used
+pub const STORAGE_DEPOSIT: u128 = 12_500_000_000_000_000_000_000;
+pub const GAS_FOR_STORAGE_DEPOSIT: u64 = 5 * TGAS;
+pub const GAS_FOR_STORAGE_CALLBACK: u64 = 3 * TGAS;
+
+// Gas kept aside for each lockup of a batch: its storage, the storage deposit promise and its callback
+pub const GAS_PER_LOCKUP: u64 = 7 * TGAS + GAS_FOR_STORAGE_DEPOSIT + GAS_FOR_STORAGE_CALLBACK;
+
 #[ext_contract(ext_ft)]
 pub trait Lighttoken {
-    fn mint_token(&mut self, account_id: AccountId, amount: u128);
+    fn ft_transfer (&mut self, receiver_id:String, amount:String, memo:String);
     fn storage_deposit (&mut self, account_id: String);
 }
 
@@ -22,9 +44,16 @@ pub struct Vestors {
     pub amount_of_token: u128,
     pub locked_amount: u128,
     pub unlocked_amount: u128,
+    pub claimed_amount: u128,
     pub duration: u64,
     pub timestamp: u64,
+    // Time between two payments in milliseconds
+    pub payment_interval: u64,
     pub nb_time_payment: u8,
+    pub revocable: bool,
+    pub revoked: bool,
+    // Account the vesting is being transferred to, until it accepts it
+    pub pending_owner_id: Option<String>,
 }
 
 // Vestors implementation
@@ -36,19 +65,57 @@ impl Vestors {
             amount_of_token: 0,
             locked_amount: 0,
             unlocked_amount: 0,
-            duration: 4,
+            claimed_amount: 0,
+            duration: DEFAULT_DURATION,
             timestamp: 0,
+            payment_interval: DEFAULT_PAYMENT_INTERVAL,
             nb_time_payment: 0,
+            revocable: false,
+            revoked: false,
+            pending_owner_id: None,
         }
     }
 }
 
 
+// LOCKUP ENTRY
+// A grant of a batch import
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LockupEntry {
+    pub id: String,
+    pub owner_id: AccountId,
+    pub amount_of_token: u128,
+    pub duration: u64,
+    pub payment_interval: u64,
+    pub revocable: bool,
+}
+
+// Result of a batch import
+// The entries from `next_index` were not created and must be sent again in another call
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BatchSummary {
+    pub created: u64,
+    pub amount_of_token: u128,
+    pub next_index: Option<u64>,
+}
+
+
 // Define the contract structure
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct VestingContract {
-    records: Vector<Vestors>,
+    records: UnorderedMap<String, Vestors>,
+    // Vesting ids of each beneficiary
+    vestors_by_owner: UnorderedMap<String, Vec<String>>,
+    total_locked_amount: u128,
+    total_unlocked_amount: u128,
+    // LTS received through ft_on_transfer and not yet committed to a grant
+    available_amount: u128,
+    // Account receiving the unvested tokens of revoked grants
+    treasury_id: AccountId,
+    // Account allowed to fund, create and revoke grants besides the owner, the treasury DAO at first
+    admin_id: AccountId,
+    state_version: u8,
 }
 
 // Define the default, which automatically initializes the contract
@@ -67,24 +134,55 @@ fn assert_self() {
     );
 }
 
+impl VestingContract {
+    // Make sure that the caller is allowed to create grants:
+    // the owner or the admin, the treasury DAO executing a passed proposal
+    fn assert_admin(&self) {
+        let caller = env::predecessor_account_id();
+        if caller != env::current_account_id() && caller != self.admin_id {
+            panic!("Only the admin or the DAO can create vesting grants");
+        }
+    }
+}
+
 // Implement the contract structure
 // To be implemented in the front end
 #[near_bindgen]
 impl VestingContract {
     #[init]
-    pub fn new() -> Self {
+    pub fn new(admin_id: AccountId) -> Self {
         assert!(env::state_read::<Self>().is_none(), "Already initialized");
         Self {
-            records: Vector::new(b"a"),
+            records: UnorderedMap::new(b"r"),
+            vestors_by_owner: UnorderedMap::new(b"o"),
+            total_locked_amount: 0,
+            total_unlocked_amount: 0,
+            available_amount: 0,
+            treasury_id: "treasurydao.testnet".parse().unwrap(),
+            admin_id,
+            state_version: STATE_VERSION,
         }
     }
 
     // Owner's methods. Can only be called by the owner
     pub fn delete_all(&mut self){
         assert_self();
-        for _i in 0..self.records.len(){
-            self.records.pop();
-        }
+        self.records.clear();
+        self.vestors_by_owner.clear();
+        self.total_locked_amount = 0;
+        self.total_unlocked_amount = 0;
+    }
+
+    // Set the account receiving the unvested tokens of revoked grants
+    pub fn set_treasury(&mut self, treasury_id: AccountId) {
+        assert_self();
+        self.treasury_id = treasury_id;
+    }
+
+    // Set the account allowed to fund, create and revoke grants besides the owner
+    pub fn set_admin(&mut self, admin_id: AccountId) {
+        assert_self();
+        self.admin_id = admin_id;
     }
 
     // Function that delete a specific vestor 
@@ -108,128 +206,328 @@ impl VestingContract {
 
     // Function to get the list of all the vestors
     pub fn get_all_vestors (&self) -> Vec<Vestors> {
-        let mut vec = Vec::new();
-        for i in 0..self.records.len() {
-            vec.push(self.records.get(i).unwrap());
-        }
-        vec
-    } 
+        self.records.values().collect()
+    }
 
-    // Function to get id
+    // Function to get a page of the vestors
+    pub fn get_vestors (&self, from_index: u64, limit: u64) -> Vec<Vestors> {
+        self.records
+            .values()
+            .skip(from_index as usize)
+            .take(limit as usize)
+            .collect()
+    }
+
+    // Function to get the number of vestors
+    pub fn get_number_of_vestors (&self) -> u64 {
+        self.records.len()
+    }
 
     // Function to get a specific vestor
     pub fn get_vestor(&self, id:&String) -> Vestors {
-        let mut vestor = Vestors::new();
-        for i in 0..self.records.len() {
-            if &self.records.get(i).unwrap().id == id {
-                vestor = self.records.get(i).unwrap();
-            }
+        match self.records.get(id) {
+            Some(vestor) => vestor,
+            None => panic!("Vestor {} not found", id),
         }
-        vestor
+    }
+
+    // Function to get all the vestings of a beneficiary
+    pub fn get_vestors_by_owner(&self, account: AccountId) -> Vec<Vestors> {
+        self.vestors_by_owner
+            .get(&account.to_string())
+            .unwrap_or_default()
+            .iter()
+            .map(|id| self.get_vestor(id))
+            .collect()
     }
 
     // Function to get all locked amount of all accounts
     pub fn get_total_locked_amount(&self) -> u128{
-        let mut total_locked_amount = 0;
-        for i in 0..self.records.len() {
-            total_locked_amount += self.records.get(i).unwrap().locked_amount;
-        }
-        total_locked_amount
+        self.total_locked_amount
     }
 
     // Function to get all unlocked amount of all accounts
     pub fn get_total_unlocked_amount(&self) -> u128{
-        let mut total_unlocked_amount = 0;
-        for i in 0..self.records.len() {
-            total_unlocked_amount += self.records.get(i).unwrap().unlocked_amount;
-        }
-        total_unlocked_amount
+        self.total_unlocked_amount
+    }
+
+    // Function to get the LTS deposited in the contract that is not allocated to any grant yet
+    pub fn get_available_amount(&self) -> U128 {
+        U128(self.available_amount)
+    }
+
+    // Function to get the treasury account
+    pub fn get_treasury(&self) -> AccountId {
+        self.treasury_id.clone()
+    }
+
+    // Function to get the admin account
+    pub fn get_admin(&self) -> AccountId {
+        self.admin_id.clone()
+    }
+
+    // Function to get the amount a vestor can claim right now
+    pub fn get_claimable_amount(&self, id: String) -> u128 {
+        let vestor = self.get_vestor(&id);
+        vestor.unlocked_amount - vestor.claimed_amount
     }
 
 
     /****** SET FUNCTIONS ******/
 
+    // Function to fund the contract with LTS
+    // Called by the LTS contract when the admin sends tokens with ft_transfer_call
+    pub fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String) -> PromiseOrValue<U128> {
+        assert_eq!(
+            env::predecessor_account_id().to_string(),
+            "light-token.testnet".to_string(),
+            "Only LTS can be deposited"
+        );
+        if sender_id != env::current_account_id() && sender_id != self.admin_id {
+            // Refund the deposit, only the admin funds the grants
+            return PromiseOrValue::Value(amount);
+        }
+        self.available_amount += amount.0;
+        env::log_str(&format!("{} LTS deposited by {} {}", amount.0, sender_id, msg));
+        PromiseOrValue::Value(U128(0))
+    }
+
     // Function to add a lockup
+    // Can only be created by the admin for a beneficiary, with LTS already deposited in the contract
     pub fn add_lockup(
         &mut self,
         id: String,
+        owner_id: AccountId,
         amount_of_token: u128,
+        revocable: bool,
     ) {
-        let vestor = Vestors {
-            id:id,
-            owner_id: env::signer_account_id().to_string(),
-            amount_of_token: amount_of_token,
-            locked_amount: 3 * (amount_of_token/4),
-            unlocked_amount: amount_of_token/4,
-            duration: 4,
-            timestamp: env::block_timestamp_ms(),
-            nb_time_payment: 1,
+        self.assert_admin();
+        let entry = LockupEntry {
+            id,
+            owner_id,
+            amount_of_token,
+            duration: DEFAULT_DURATION,
+            payment_interval: DEFAULT_PAYMENT_INTERVAL,
+            revocable,
         };
-        self.records.push(&vestor);
-        self.add_storage_deposit();
-        self.mint_lts(amount_of_token/4);
+        self.assert_valid_entry(&entry);
+        assert!(
+            amount_of_token * ONE_LTS <= self.available_amount,
+            "Not enough LTS deposited to fund this grant"
+        );
+        self.internal_add_lockup(entry);
     }
 
-    pub fn refresh (&mut self,v_id: String) {
-        if self.get_vestor(&v_id).nb_time_payment == 1 && env::block_timestamp_ms() > self.get_vestor(&v_id).timestamp + (2 * 60000) {
-            self.change_data(&self.get_vestor(&v_id).id);
-            self.mint_lts(self.get_vestor(&v_id).amount_of_token/4);
-            env::log_str("second payment done");
+    // Function to add many lockups at once (token generation event)
+    // Stops before running out of gas, the summary tells from which entry to continue
+    pub fn add_lockups(&mut self, entries: Vec<LockupEntry>) -> BatchSummary {
+        self.assert_admin();
+        let mut ids: Vec<&String> = Vec::new();
+        let mut total = 0;
+        for entry in entries.iter() {
+            self.assert_valid_entry(entry);
+            assert!(!ids.contains(&&entry.id), "Duplicated vestor id {}", entry.id);
+            ids.push(&entry.id);
+            total += entry.amount_of_token;
         }
-        if self.get_vestor(&v_id).nb_time_payment == 2 && env::block_timestamp_ms() > self.get_vestor(&v_id).timestamp + (4 * 60000) {
-            self.change_data(&self.get_vestor(&v_id).id);
-            self.mint_lts(self.get_vestor(&v_id).amount_of_token/4);
-            env::log_str("third payment done");
+        assert!(
+            total * ONE_LTS <= self.available_amount,
+            "Not enough LTS deposited to fund this batch"
+        );
+
+        let mut summary = BatchSummary {
+            created: 0,
+            amount_of_token: 0,
+            next_index: None,
+        };
+        for (index, entry) in entries.into_iter().enumerate() {
+            if env::used_gas().0 + GAS_PER_LOCKUP > env::prepaid_gas().0 {
+                summary.next_index = Some(index as u64);
+                break;
+            }
+            summary.created += 1;
+            summary.amount_of_token += entry.amount_of_token;
+            self.internal_add_lockup(entry);
         }
-        if self.get_vestor(&v_id).nb_time_payment == 3 && env::block_timestamp_ms() > self.get_vestor(&v_id).timestamp + (6 * 60000) {
-            self.change_data(&self.get_vestor(&v_id).id);
-            self.mint_lts(self.get_vestor(&v_id).amount_of_token/4);
-            env::log_str("fourth payment done");
+        env::log_str(&format!(
+            "{} vestings created for {} LTS",
+            summary.created, summary.amount_of_token
+        ));
+        summary
+    }
+
+    // Function to unlock the payments that are due
+    pub fn refresh (&mut self,v_id: String) {
+        let vestor = self.get_vestor(&v_id);
+        if vestor.revoked {
+            env::log_str("vesting revoked");
+            return;
         }
-        if self.get_vestor(&v_id).nb_time_payment == 4 { 
+        if vestor.nb_time_payment as u64 >= vestor.duration {
             env::log_str("already paid");
+            return;
+        }
+        for nb_time_payment in vestor.nb_time_payment as u64..vestor.duration {
+            if env::block_timestamp_ms() > vestor.timestamp + (nb_time_payment * vestor.payment_interval) {
+                self.change_data(&v_id);
+                env::log_str(&format!("payment {} unlocked", nb_time_payment + 1));
+            }
         }
-        
     }
 
+    // Function to claim the unlocked tokens of a vesting
+    // Can only be called by the beneficiary
+    pub fn claim (&mut self, id: String) -> Promise {
+        self.refresh(id.clone());
+        let mut vestor = self.get_vestor(&id);
+        assert_eq!(
+            env::predecessor_account_id().to_string(),
+            vestor.owner_id,
+            "Only the beneficiary can claim"
+        );
+        let amount = vestor.unlocked_amount - vestor.claimed_amount;
+        assert!(amount > 0, "Nothing to claim");
+        vestor.claimed_amount += amount;
+        self.replace_vestor(vestor.clone());
+        self.transfer_lts(vestor.owner_id, amount).then(
+            Self::ext(env::current_account_id())
+            .with_static_gas(Gas(5 * TGAS))
+            .claim_callback(id, amount)
+        )
+    }
 
-    /****** BACKUP FUNCTIONS ******/
+    #[private] // Public - but only callable by env::current_account_id()
+    pub fn claim_callback(&mut self, #[callback_result] call_result: Result<(), PromiseError>, id: String, amount: u128) {
+        // Restore the claimable amount if the transfer failed
+        let mut vestor = self.get_vestor(&id);
+        if call_result.is_err() {
+            vestor.claimed_amount -= amount;
+            self.replace_vestor(vestor);
+            env::log_str("There was an error contacting the token contract");
+            return;
+        }
+        VestingEvent::Claim(&[VestingClaimData {
+            id: &id,
+            owner_id: &vestor.owner_id,
+            amount: U128(amount * ONE_LTS),
+        }])
+        .emit();
+    }
 
-    // Function to mint LTS 
-    pub fn mint_lts (&mut self, amount:u128) -> Promise {
-        let contract_account = "light-token.testnet".to_string().try_into().unwrap();
+    // Function to start the transfer of a vesting to another account
+    // Can only be called by the beneficiary, the new owner has to accept it
+    pub fn transfer_vesting (&mut self, id: String, new_owner: AccountId) {
+        let mut vestor = self.get_vestor(&id);
+        assert_eq!(
+            env::predecessor_account_id().to_string(),
+            vestor.owner_id,
+            "Only the beneficiary can transfer the vesting"
+        );
+        assert!(new_owner.to_string() != vestor.owner_id, "The vesting already belongs to this account");
+        vestor.pending_owner_id = Some(new_owner.to_string());
+        self.replace_vestor(vestor);
+        env::log_str(&format!("vesting {} offered to {}", id, new_owner));
+    }
 
-        let promise=ext_ft::ext(contract_account)
-            .with_static_gas(Gas(5_000_000_000_000))
-            .mint_token(env::signer_account_id(), amount*100000000);
+    // Function to cancel a transfer that was not accepted yet
+    pub fn cancel_vesting_transfer (&mut self, id: String) {
+        let mut vestor = self.get_vestor(&id);
+        assert_eq!(
+            env::predecessor_account_id().to_string(),
+            vestor.owner_id,
+            "Only the beneficiary can cancel the transfer"
+        );
+        assert!(vestor.pending_owner_id.is_some(), "There is no pending transfer");
+        vestor.pending_owner_id = None;
+        self.replace_vestor(vestor);
+    }
 
-        return promise.then( // Create a promise to callback withdraw_callback
-            Self::ext(env::current_account_id())
-            .with_static_gas(Gas(3 * TGAS))
-            .mint_lts_callback()
-            )
+    // Function to accept a vesting transferred to the caller
+    // The schedule and the unclaimed tokens move with the vesting
+    pub fn accept_vesting (&mut self, id: String) {
+        let mut vestor = self.get_vestor(&id);
+        let new_owner = env::predecessor_account_id();
+        assert_eq!(
+            vestor.pending_owner_id,
+            Some(new_owner.to_string()),
+            "The vesting was not transferred to you"
+        );
+        self.remove_from_owner(&vestor.owner_id, &id);
+        self.add_to_owner(&new_owner.to_string(), &id);
+        VestingEvent::Transfer(&[VestingTransferData {
+            id: &id,
+            old_owner_id: &vestor.owner_id,
+            new_owner_id: new_owner.as_str(),
+        }])
+        .emit();
+        vestor.owner_id = new_owner.to_string();
+        vestor.pending_owner_id = None;
+        self.replace_vestor(vestor);
+        self.add_storage_deposit(new_owner);
+    }
+
+    // Function to revoke a vesting
+    // What is vested up to now stays claimable by the beneficiary, the rest goes back to the treasury
+    pub fn revoke (&mut self, id: String) {
+        self.assert_admin();
+        self.refresh(id.clone());
+        let mut vestor = self.get_vestor(&id);
+        assert!(vestor.revocable, "This vesting is not revocable");
+        assert!(!vestor.revoked, "This vesting is already revoked");
+        let unvested = vestor.locked_amount;
+        vestor.locked_amount = 0;
+        vestor.revoked = true;
+        VestingEvent::Revoke(&[VestingRevokeData {
+            id: &id,
+            owner_id: &vestor.owner_id,
+            unvested_amount: U128(unvested * ONE_LTS),
+            treasury_id: self.treasury_id.as_str(),
+        }])
+        .emit();
+        self.replace_vestor(vestor);
+        // A fully vested grant is just closed, the token rejects transfers of 0
+        if unvested > 0 {
+            self.transfer_lts(self.treasury_id.to_string(), unvested).then(
+                Self::ext(env::current_account_id())
+                .with_static_gas(Gas(5 * TGAS))
+                .revoke_callback(unvested)
+            );
+        }
     }
 
     #[private] // Public - but only callable by env::current_account_id()
-    pub fn mint_lts_callback(&mut self, #[callback_result] call_result: Result<(), PromiseError> ) {
-        // Check if the promise succeeded
+    pub fn revoke_callback(&mut self, #[callback_result] call_result: Result<(), PromiseError>, amount: u128) {
+        // Keep the tokens available for new grants if the treasury could not receive them
         if call_result.is_err() {
-        panic!("There was an error contacting the token contract");
+            self.available_amount += amount * ONE_LTS;
+            env::log_str("There was an error contacting the token contract");
         }
     }
 
+    /****** BACKUP FUNCTIONS ******/
+
+    // Function to send LTS from the contract balance
+    fn transfer_lts (&self, receiver_id: String, amount:u128) -> Promise {
+        let contract_account = "light-token.testnet".to_string().try_into().unwrap();
+
+        ext_ft::ext(contract_account)
+            .with_attached_deposit(1)
+            .with_static_gas(Gas(5 * TGAS))
+            .ft_transfer(receiver_id, (amount*ONE_LTS).to_string(), "vesting".to_string())
+    }
+
     // Function to add the vestor in the storage of the LTS token
-    pub fn add_storage_deposit (&mut self) -> Promise{
+    fn add_storage_deposit (&mut self, account_id: AccountId) -> Promise{
         let contract_account = "light-token.testnet".to_string().try_into().unwrap();
 
         let promise=ext_ft::ext(contract_account)
-            .with_attached_deposit(1000000000000000000000000)
-            .with_static_gas(Gas(5_000_000_000_000))
-            .storage_deposit(env::signer_account_id().to_string());
+            .with_attached_deposit(STORAGE_DEPOSIT)
+            .with_static_gas(Gas(GAS_FOR_STORAGE_DEPOSIT))
+            .storage_deposit(account_id.to_string());
 
             return promise.then( // Create a promise to callback withdraw_callback
                 Self::ext(env::current_account_id())
-                .with_static_gas(Gas(3 * TGAS))
+                .with_static_gas(Gas(GAS_FOR_STORAGE_CALLBACK))
                 .add_storage_callback()
                 )
     }
@@ -242,29 +540,478 @@ impl VestingContract {
         }
     }
 
-    // Function to replace a vestor by the new one
-    pub fn replace_vestor (&mut self, vestor:Vestors) {
-        for i in 0..self.records.len() {
-            if self.records.get(i).unwrap().id == vestor.id {
-                self.records.replace(i, &vestor);
-            }
+    // Function to check a grant before creating it
+    fn assert_valid_entry (&self, entry: &LockupEntry) {
+        assert!(self.records.get(&entry.id).is_none(), "A vestor with this id already exists");
+        assert!(entry.amount_of_token > 0, "The amount must be greater than 0");
+        assert!(
+            entry.duration > 0 && entry.duration <= u8::MAX as u64,
+            "The number of payments must be between 1 and 255"
+        );
+        assert!(entry.payment_interval > 0, "The payment interval must be greater than 0");
+    }
+
+    // Function to create a grant, the first payment is unlocked right away
+    fn internal_add_lockup (&mut self, entry: LockupEntry) {
+        self.available_amount -= entry.amount_of_token * ONE_LTS;
+        let first_payment = if entry.duration == 1 {
+            entry.amount_of_token
+        } else {
+            entry.amount_of_token/entry.duration as u128
+        };
+        let vestor = Vestors {
+            id: entry.id,
+            owner_id: entry.owner_id.to_string(),
+            amount_of_token: entry.amount_of_token,
+            locked_amount: entry.amount_of_token - first_payment,
+            unlocked_amount: first_payment,
+            claimed_amount: 0,
+            duration: entry.duration,
+            timestamp: env::block_timestamp_ms(),
+            payment_interval: entry.payment_interval,
+            nb_time_payment: 1,
+            revocable: entry.revocable,
+            revoked: false,
+            pending_owner_id: None,
+        };
+        VestingEvent::Create(&[VestingCreateData {
+            id: &vestor.id,
+            owner_id: &vestor.owner_id,
+            amount: U128(vestor.amount_of_token * ONE_LTS),
+            duration: vestor.duration,
+            payment_interval: vestor.payment_interval,
+            revocable: vestor.revocable,
+        }])
+        .emit();
+        // Beneficiaries with a vesting are already registered in the LTS contract
+        let registered = self.vestors_by_owner.get(&vestor.owner_id).is_some();
+        self.add_to_owner(&vestor.owner_id, &vestor.id);
+        self.replace_vestor(vestor);
+        if !registered {
+            self.add_storage_deposit(entry.owner_id);
+        }
+    }
+
+    // Function to index a vesting under its beneficiary
+    fn add_to_owner (&mut self, owner_id: &String, id: &str) {
+        let mut ids = self.vestors_by_owner.get(owner_id).unwrap_or_default();
+        ids.push(id.to_string());
+        self.vestors_by_owner.insert(owner_id, &ids);
+    }
+
+    // Function to remove a vesting from the index of its beneficiary
+    fn remove_from_owner (&mut self, owner_id: &String, id: &String) {
+        let mut ids = self.vestors_by_owner.get(owner_id).unwrap_or_default();
+        ids.retain(|i| i != id);
+        if ids.is_empty() {
+            self.vestors_by_owner.remove(owner_id);
+        } else {
+            self.vestors_by_owner.insert(owner_id, &ids);
         }
     }
 
+    // Function to replace a vestor by the new one, keeping the totals up to date
+    fn replace_vestor (&mut self, vestor:Vestors) {
+        if let Some(previous) = self.records.insert(&vestor.id, &vestor) {
+            self.total_locked_amount -= previous.locked_amount;
+            self.total_unlocked_amount -= previous.unlocked_amount;
+        }
+        self.total_locked_amount += vestor.locked_amount;
+        self.total_unlocked_amount += vestor.unlocked_amount;
+    }
+
     // Function to change data 
-    pub fn change_data (&mut self, v_id: &String){
+    fn change_data (&mut self, v_id: &String){
         let current_vestor = self.get_vestor(&v_id);
+        // The last payment unlocks everything left, including rounding leftovers
+        let tranche = if current_vestor.nb_time_payment as u64 + 1 >= current_vestor.duration {
+            current_vestor.locked_amount
+        } else {
+            current_vestor.amount_of_token/current_vestor.duration as u128
+        };
         let vestor = Vestors {
             id: current_vestor.id,
-            owner_id: env::signer_account_id().to_string(),
+            owner_id: current_vestor.owner_id,
             amount_of_token: current_vestor.amount_of_token,
-            locked_amount: current_vestor.locked_amount - current_vestor.amount_of_token/4,
-            unlocked_amount: current_vestor.unlocked_amount + current_vestor.amount_of_token/4,
-            duration: 4,
+            locked_amount: current_vestor.locked_amount - tranche,
+            unlocked_amount: current_vestor.unlocked_amount + tranche,
+            claimed_amount: current_vestor.claimed_amount,
+            duration: current_vestor.duration,
             timestamp: current_vestor.timestamp,
+            payment_interval: current_vestor.payment_interval,
             nb_time_payment: current_vestor.nb_time_payment + 1,
+            revocable: current_vestor.revocable,
+            revoked: current_vestor.revoked,
+            pending_owner_id: current_vestor.pending_owner_id,
         };
         self.replace_vestor(vestor);
     }
 
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::VMContextBuilder;
+    use near_sdk::testing_env;
+    use near_sdk::collections::Vector;
+
+    fn lts() -> AccountId {
+        "light-token.testnet".parse().unwrap()
+    }
+
+    fn beneficiary() -> AccountId {
+        "issameths.testnet".parse().unwrap()
+    }
+
+    // context where the call comes from `predecessor`
+    fn context(predecessor: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id("vesting.testnet".parse().unwrap())
+            .predecessor_account_id(predecessor.clone())
+            .signer_account_id(predecessor);
+        builder
+    }
+
+    // contract funded with `amount` LTS by the owner
+    fn funded_contract(amount: u128) -> VestingContract {
+        testing_env!(context("vesting.testnet".parse().unwrap()).build());
+        let mut contract = VestingContract::new("treasurydao.testnet".parse().unwrap());
+        testing_env!(context(lts()).build());
+        contract.ft_on_transfer("vesting.testnet".parse().unwrap(), U128(amount * ONE_LTS), "".to_string());
+        testing_env!(context("vesting.testnet".parse().unwrap()).build());
+        contract
+    }
+
+    #[test]
+    fn test_fund_and_add_lockup() {
+        let mut contract = funded_contract(100);
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, true);
+
+        //the grant is taken from the deposited balance
+        assert_eq!(contract.get_available_amount(), U128(60 * ONE_LTS));
+        let vestor = contract.get_vestor(&"v1".to_string());
+        assert_eq!(vestor.owner_id, beneficiary().to_string());
+        assert_eq!(vestor.unlocked_amount, 10);
+        assert_eq!(vestor.locked_amount, 30);
+        assert!(near_sdk::test_utils::get_logs().contains(&format!(
+            "EVENT_JSON:{{\"standard\":\"lightency\",\"version\":\"1.0.0\",\"event\":\"vesting_create\",\"data\":[{{\"id\":\"v1\",\"owner_id\":\"{}\",\"amount\":\"4000000000\",\"duration\":4,\"payment_interval\":120000,\"revocable\":true}}]}}",
+            beneficiary()
+        )));
+    }
+
+    #[test]
+    fn test_deposit_from_stranger_is_refunded() {
+        let mut contract = funded_contract(0);
+        testing_env!(context(lts()).build());
+        let refund = contract.ft_on_transfer(beneficiary(), U128(5 * ONE_LTS), "".to_string());
+        assert!(matches!(refund, PromiseOrValue::Value(U128(amount)) if amount == 5 * ONE_LTS));
+        assert_eq!(contract.get_available_amount(), U128(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the admin or the DAO can create vesting grants")]
+    fn test_add_lockup_not_admin() {
+        let mut contract = funded_contract(100);
+        testing_env!(context(beneficiary()).build());
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, true);
+    }
+
+    //testing that only the current admin creates grants once the owner changed it
+    #[test]
+    fn test_set_admin() {
+        let mut contract = funded_contract(100);
+        contract.set_admin(beneficiary());
+        assert_eq!(contract.get_admin(), beneficiary());
+        testing_env!(context(beneficiary()).build());
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, true);
+        assert_eq!(contract.get_available_amount(), U128(60 * ONE_LTS));
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the admin or the DAO can create vesting grants")]
+    fn test_former_admin() {
+        let mut contract = funded_contract(100);
+        contract.set_admin(beneficiary());
+        testing_env!(context("treasurydao.testnet".parse().unwrap()).build());
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, true);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by owner")]
+    fn test_set_admin_not_owner() {
+        let mut contract = funded_contract(100);
+        testing_env!(context("treasurydao.testnet".parse().unwrap()).build());
+        contract.set_admin(beneficiary());
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough LTS deposited to fund this grant")]
+    fn test_add_lockup_not_funded() {
+        let mut contract = funded_contract(10);
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, true);
+    }
+
+    #[test]
+    fn test_claim() {
+        let mut contract = funded_contract(100);
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, true);
+
+        //after 5 minutes the second and third payments are unlocked
+        testing_env!(context(beneficiary())
+            .block_timestamp((contract.get_vestor(&"v1".to_string()).timestamp + 5 * 60000) * 1_000_000)
+            .build());
+        contract.claim("v1".to_string());
+        let vestor = contract.get_vestor(&"v1".to_string());
+        assert_eq!(vestor.nb_time_payment, 3);
+        assert_eq!(vestor.claimed_amount, 30);
+        assert_eq!(contract.get_claimable_amount("v1".to_string()), 0);
+    }
+
+    #[test]
+    fn test_revoke() {
+        let mut contract = funded_contract(100);
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, true);
+
+        //revoked after the second payment
+        testing_env!(context("vesting.testnet".parse().unwrap())
+            .block_timestamp((contract.get_vestor(&"v1".to_string()).timestamp + 3 * 60000) * 1_000_000)
+            .build());
+        contract.revoke("v1".to_string());
+        let vestor = contract.get_vestor(&"v1".to_string());
+        assert!(vestor.revoked);
+        assert_eq!(vestor.unlocked_amount, 20);
+        assert_eq!(vestor.locked_amount, 0);
+
+        //nothing vests after the revocation
+        testing_env!(context(beneficiary())
+            .block_timestamp((vestor.timestamp + 10 * 60000) * 1_000_000)
+            .build());
+        contract.claim("v1".to_string());
+        assert_eq!(contract.get_vestor(&"v1".to_string()).claimed_amount, 20);
+    }
+
+    #[test]
+    fn test_revoke_fully_vested() {
+        let mut contract = funded_contract(100);
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, true);
+        testing_env!(context("vesting.testnet".parse().unwrap())
+            .block_timestamp((contract.get_vestor(&"v1".to_string()).timestamp + 10 * 60000) * 1_000_000)
+            .build());
+        contract.revoke("v1".to_string());
+        let vestor = contract.get_vestor(&"v1".to_string());
+        assert!(vestor.revoked);
+        assert_eq!(vestor.unlocked_amount, 40);
+        //no transfer of 0 LTS to the treasury
+        assert_eq!(near_sdk::test_utils::get_created_receipts().len(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "This vesting is not revocable")]
+    fn test_revoke_not_revocable() {
+        let mut contract = funded_contract(100);
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, false);
+        contract.revoke("v1".to_string());
+    }
+
+    #[test]
+    fn test_vestors_by_owner_and_totals() {
+        let mut contract = funded_contract(100);
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, true);
+        contract.add_lockup("v2".to_string(), beneficiary(), 20, true);
+        contract.add_lockup("v3".to_string(), "oussema.testnet".parse().unwrap(), 8, false);
+
+        let ids: Vec<String> = contract.get_vestors_by_owner(beneficiary()).into_iter().map(|v| v.id).collect();
+        assert_eq!(ids, vec!["v1".to_string(), "v2".to_string()]);
+        assert_eq!(contract.get_vestors(1, 5).len(), 2);
+        assert_eq!(contract.get_total_unlocked_amount(), 10 + 5 + 2);
+        assert_eq!(contract.get_total_locked_amount(), 30 + 15 + 6);
+
+        contract.revoke("v1".to_string());
+        assert_eq!(contract.get_total_locked_amount(), 15 + 6);
+    }
+
+    #[test]
+    #[should_panic(expected = "Vestor unknown not found")]
+    fn test_get_unknown_vestor() {
+        let contract = funded_contract(0);
+        contract.get_vestor(&"unknown".to_string());
+    }
+
+    #[test]
+    fn test_transfer_vesting() {
+        let mut contract = funded_contract(100);
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, true);
+        let oussema: AccountId = "oussema.testnet".parse().unwrap();
+
+        testing_env!(context(beneficiary()).build());
+        contract.transfer_vesting("v1".to_string(), oussema.clone());
+        //still owned by the beneficiary until accepted
+        assert_eq!(contract.get_vestor(&"v1".to_string()).owner_id, beneficiary().to_string());
+
+        testing_env!(context(oussema.clone()).build());
+        contract.accept_vesting("v1".to_string());
+        let vestor = contract.get_vestor(&"v1".to_string());
+        assert_eq!(vestor.owner_id, oussema.to_string());
+        assert_eq!(vestor.pending_owner_id, None);
+        assert_eq!(vestor.unlocked_amount, 10);
+        assert_eq!(contract.get_vestors_by_owner(oussema).len(), 1);
+        assert!(contract.get_vestors_by_owner(beneficiary()).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the beneficiary can transfer the vesting")]
+    fn test_transfer_vesting_not_owner() {
+        let mut contract = funded_contract(100);
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, true);
+        testing_env!(context("oussema.testnet".parse().unwrap()).build());
+        contract.transfer_vesting("v1".to_string(), "oussema.testnet".parse().unwrap());
+    }
+
+    #[test]
+    fn test_refresh_keeps_owner() {
+        let mut contract = funded_contract(100);
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, true);
+        testing_env!(context("oussema.testnet".parse().unwrap())
+            .block_timestamp((contract.get_vestor(&"v1".to_string()).timestamp + 3 * 60000) * 1_000_000)
+            .build());
+        contract.refresh("v1".to_string());
+        assert_eq!(contract.get_vestor(&"v1".to_string()).owner_id, beneficiary().to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the beneficiary can claim")]
+    fn test_claim_not_beneficiary() {
+        let mut contract = funded_contract(100);
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, true);
+        testing_env!(context("oussema.testnet".parse().unwrap())
+            .block_timestamp((contract.get_vestor(&"v1".to_string()).timestamp + 3 * 60000) * 1_000_000)
+            .build());
+        contract.claim("v1".to_string());
+    }
+
+    fn entry(id: &str, amount_of_token: u128) -> LockupEntry {
+        LockupEntry {
+            id: id.to_string(),
+            owner_id: beneficiary(),
+            amount_of_token,
+            duration: 10,
+            payment_interval: 60000,
+            revocable: false,
+        }
+    }
+
+    #[test]
+    fn test_add_lockups() {
+        let mut contract = funded_contract(100);
+        let summary = contract.add_lockups(vec![entry("v1", 50), entry("v2", 30)]);
+        assert_eq!(summary.created, 2);
+        assert_eq!(summary.amount_of_token, 80);
+        assert_eq!(summary.next_index, None);
+        assert_eq!(contract.get_available_amount(), U128(20 * ONE_LTS));
+
+        //each grant follows its own schedule
+        let vestor = contract.get_vestor(&"v1".to_string());
+        assert_eq!(vestor.unlocked_amount, 5);
+        testing_env!(context(beneficiary())
+            .block_timestamp((vestor.timestamp + 10 * 60000 + 1) * 1_000_000)
+            .build());
+        contract.refresh("v1".to_string());
+        assert_eq!(contract.get_vestor(&"v1".to_string()).unlocked_amount, 50);
+    }
+
+    //testing that a batch registers each new beneficiary once with the minimum storage deposit
+    #[test]
+    fn test_add_lockups_storage_deposit() {
+        let mut contract = funded_contract(100);
+        let mut other = entry("v3", 10);
+        other.owner_id = "oussema.testnet".parse().unwrap();
+        contract.add_lockups(vec![entry("v1", 50), entry("v2", 30), other]);
+        let deposits: Vec<u128> = near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                near_sdk::mock::VmAction::FunctionCall { function_name, deposit, .. } if function_name == "storage_deposit" => Some(deposit),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(deposits, vec![STORAGE_DEPOSIT, STORAGE_DEPOSIT]);
+    }
+
+    //testing that the deposit still registers a beneficiary on the LTS contract
+    #[test]
+    fn test_storage_deposit_covers_lts_registration() {
+        use near_contract_standards::storage_management::StorageManagement;
+        testing_env!(context(lts()).build());
+        let token = fungible_token::Contract::new_default_meta(lts(), 1_000 * ONE_LTS, 10_000 * ONE_LTS);
+        assert!(STORAGE_DEPOSIT >= token.storage_balance_bounds().min.0);
+    }
+
+    #[test]
+    fn test_add_lockups_out_of_gas() {
+        let mut contract = funded_contract(100);
+        testing_env!(context("vesting.testnet".parse().unwrap())
+            .prepaid_gas(Gas(GAS_PER_LOCKUP / 2))
+            .build());
+        let summary = contract.add_lockups(vec![entry("v1", 50), entry("v2", 30)]);
+        assert_eq!(summary.created, 0);
+        assert_eq!(summary.next_index, Some(0));
+        assert_eq!(contract.get_available_amount(), U128(100 * ONE_LTS));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough LTS deposited to fund this batch")]
+    fn test_add_lockups_not_funded() {
+        let mut contract = funded_contract(70);
+        contract.add_lockups(vec![entry("v1", 50), entry("v2", 30)]);
+    }
+
+    #[test]
+    fn test_migrate_from_v0() {
+        testing_env!(context("vesting.testnet".parse().unwrap()).build());
+        let mut records = Vector::new(b"a");
+        records.push(&VestorsV0 {
+            id: "v1".to_string(),
+            owner_id: beneficiary().to_string(),
+            amount_of_token: 40,
+            locked_amount: 20,
+            unlocked_amount: 20,
+            duration: 4,
+            timestamp: 0,
+            nb_time_payment: 2,
+        });
+        env::state_write(&VestingContractV0 { records });
+
+        let contract = VestingContract::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        let vestor = contract.get_vestor(&"v1".to_string());
+        assert_eq!(vestor.locked_amount, 20);
+        //the unlocked payments were already minted to the owner
+        assert_eq!(vestor.claimed_amount, 20);
+        assert_eq!(vestor.payment_interval, DEFAULT_PAYMENT_INTERVAL);
+        assert_eq!(contract.get_vestors_by_owner(beneficiary()).len(), 1);
+        assert_eq!(contract.get_total_locked_amount(), 20);
+        assert_eq!(contract.get_total_unlocked_amount(), 20);
+        //the old records are removed from the storage
+        assert!(env::storage_read(&[b"a".as_slice(), &0u64.to_le_bytes()].concat()).is_none());
+    }
+
+    #[test]
+    fn test_migrate_current() {
+        let mut contract = funded_contract(100);
+        contract.add_lockup("v1".to_string(), beneficiary(), 40, true);
+        env::state_write(&contract);
+
+        let contract = VestingContract::migrate();
+        assert_eq!(contract.get_state_version(), STATE_VERSION);
+        assert_eq!(contract.get_available_amount(), U128(60 * ONE_LTS));
+        assert!(contract.get_vestor(&"v1".to_string()).revocable);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by owner")]
+    fn test_migrate_not_owner() {
+        testing_env!(context(beneficiary()).build());
+        VestingContract::migrate();
+    }
+}
@@ -0,0 +1,105 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::collections::{UnorderedMap, Vector};
+use near_sdk::{env, near_bindgen};
+
+use crate::{assert_self, VestingContract, VestingContractExt, Vestors, DEFAULT_PAYMENT_INTERVAL};
+
+// Version of the layout written by this code, bumped with every change of the layout
+pub const STATE_VERSION: u8 = 1;
+
+// Vestor deployed before the state was versioned, its payments were minted to the owner directly
+#[derive(BorshDeserialize, BorshSerialize, Clone, Debug)]
+pub struct VestorsV0 {
+    pub id: String,
+    pub owner_id: String,
+    pub amount_of_token: u128,
+    pub locked_amount: u128,
+    pub unlocked_amount: u128,
+    pub duration: u64,
+    pub timestamp: u64,
+    pub nb_time_payment: u8,
+}
+
+// Layout deployed before the state was versioned
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct VestingContractV0 {
+    pub records: Vector<VestorsV0>,
+}
+
+// Every layout the state has had
+pub enum VersionedVestingContract {
+    V0(VestingContractV0),
+    V1(Box<VestingContract>),
+}
+
+impl VersionedVestingContract {
+    // Function to read the stored state, the current layout ends with its version and the unversioned
+    // one with the prefix of its records
+    pub fn read() -> Self {
+        let bytes = env::storage_read(b"STATE").expect("The contract isn't initialized");
+        match bytes.last().copied() {
+            Some(STATE_VERSION) => VersionedVestingContract::V1(
+                Box::new(VestingContract::try_from_slice(&bytes).expect("The state doesn't match its version")),
+            ),
+            _ => VersionedVestingContract::V0(
+                VestingContractV0::try_from_slice(&bytes).expect("The state doesn't match its version"),
+            ),
+        }
+    }
+
+    // Function to convert any layout to the current one
+    pub fn into_current(self) -> VestingContract {
+        match self {
+            VersionedVestingContract::V0(mut state) => {
+                let mut contract = VestingContract {
+                    records: UnorderedMap::new(b"r"),
+                    vestors_by_owner: UnorderedMap::new(b"o"),
+                    total_locked_amount: 0,
+                    total_unlocked_amount: 0,
+                    available_amount: 0,
+                    treasury_id: "treasurydao.testnet".parse().unwrap(),
+                    admin_id: "treasurydao.testnet".parse().unwrap(),
+                    state_version: STATE_VERSION,
+                };
+                for old in state.records.iter() {
+                    let vestor = Vestors {
+                        id: old.id,
+                        owner_id: old.owner_id,
+                        amount_of_token: old.amount_of_token,
+                        locked_amount: old.locked_amount,
+                        unlocked_amount: old.unlocked_amount,
+                        claimed_amount: old.unlocked_amount,
+                        duration: old.duration,
+                        timestamp: old.timestamp,
+                        payment_interval: DEFAULT_PAYMENT_INTERVAL,
+                        nb_time_payment: old.nb_time_payment,
+                        revocable: false,
+                        revoked: false,
+                        pending_owner_id: None,
+                    };
+                    contract.total_locked_amount += vestor.locked_amount;
+                    contract.total_unlocked_amount += vestor.unlocked_amount;
+                    contract.add_to_owner(&vestor.owner_id, &vestor.id);
+                    contract.records.insert(&vestor.id, &vestor);
+                }
+                state.records.clear();
+                contract
+            }
+            VersionedVestingContract::V1(state) => *state,
+        }
+    }
+}
+
+#[near_bindgen]
+impl VestingContract {
+    // Function to upgrade the stored state after deploying a new version of the code
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        assert_self();
+        VersionedVestingContract::read().into_current()
+    }
+
+    pub fn get_state_version(&self) -> u8 {
+        self.state_version
+    }
+}